@@ -76,11 +76,11 @@ impl ExecutionContext<'_> {
     }
 
     pub(crate) const fn should_cmd_lead_own_process_group(&self) -> bool {
-        self.shell.options.interactive
-            && matches!(
-                self.params.process_group_policy,
-                ProcessGroupPolicy::NewProcessGroup
-            )
+        match self.params.process_group_policy {
+            ProcessGroupPolicy::NewProcessGroup => self.shell.options.interactive,
+            ProcessGroupPolicy::IsolatedProcessGroup => true,
+            ProcessGroupPolicy::SameProcessGroup => false,
+        }
     }
 }
 
@@ -427,7 +427,7 @@ pub(crate) fn execute_external_command(
     // If we're to lead our own process group and stdin is a terminal,
     // then we need to arrange for the new process to move itself
     // to the foreground.
-    if new_pg && child_stdin_is_terminal {
+    if new_pg && child_stdin_is_terminal && context.shell.options.interactive {
         cmd.take_foreground();
     }
 
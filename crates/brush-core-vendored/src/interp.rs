@@ -38,7 +38,13 @@ impl From<std::process::Output> for results::ExecutionResult {
 
         if let Some(signal) = output.status.signal() {
             #[expect(clippy::cast_sign_loss)]
-            return Self::new((signal & 0xFF) as u8 + 128);
+            return Self {
+                signal: Some(results::ProcessSignal {
+                    number: signal,
+                    core_dumped: output.status.core_dumped(),
+                }),
+                ..Self::new((signal & 0xFF) as u8 + 128)
+            };
         }
 
         tracing::error!("unhandled process exit");
@@ -206,6 +212,12 @@ pub enum ProcessGroupPolicy {
     /// Place the process in a new process group.
     #[default]
     NewProcessGroup,
+    /// Place the process in a new process group even when the shell is not
+    /// interactive, so that it can be signalled as a group without reaching
+    /// the shell's own process group. The group never takes the terminal
+    /// foreground, so its processes stop if they read from the controlling
+    /// terminal.
+    IsolatedProcessGroup,
     /// Place the process in the same process group as its parent.
     SameProcessGroup,
 }
@@ -385,6 +397,8 @@ impl Execute for ast::Pipeline {
         // Invert the exit code if requested.
         if self.bang {
             result.exit_code = ExecutionExitCode::from(if result.is_success() { 1 } else { 0 });
+            // The inverted status no longer reflects a killing signal.
+            result.signal = None;
         }
 
         // Update statuses.
@@ -76,6 +76,16 @@ impl ChildProcess {
                     Ok(ProcessWaitResult::Completed(output_from_status(status)))
                 }
                 None => {
+                    // Take down the process group the child leads as well, so its
+                    // descendants don't outlive the cancellation. This fails
+                    // harmlessly when the child joined another process group.
+                    #[cfg(unix)]
+                    if let Some(pid) = self.pid {
+                        let _ = sys::signal::kill_process_group(
+                            pid,
+                            crate::traps::TrapSignal::Signal(sys::signal::Signal::SIGKILL),
+                        );
+                    }
                     if self.child.kill().await.is_ok() {
                         self.reaped = true;
                     } else if let Ok(Some(_)) = self.child.try_wait() {
@@ -9,6 +9,17 @@ pub struct ExecutionResult {
     pub next_control_flow: ExecutionControlFlow,
     /// The exit code resulting from execution.
     pub exit_code: ExecutionExitCode,
+    /// The signal that terminated the process this result was derived from, if any.
+    pub signal: Option<ProcessSignal>,
+}
+
+/// Describes a signal that terminated a process.
+#[derive(Clone, Copy, Debug)]
+pub struct ProcessSignal {
+    /// The signal number.
+    pub number: i32,
+    /// Whether the process dumped core.
+    pub core_dumped: bool,
 }
 
 impl ExecutionResult {
@@ -38,6 +49,7 @@ impl ExecutionResult {
         Self {
             next_control_flow: ExecutionControlFlow::Normal,
             exit_code: ExecutionExitCode::Success,
+            signal: None,
         }
     }
 
@@ -46,6 +58,7 @@ impl ExecutionResult {
         Self {
             next_control_flow: ExecutionControlFlow::Normal,
             exit_code: ExecutionExitCode::GeneralError,
+            signal: None,
         }
     }
 
@@ -92,6 +105,7 @@ impl From<ExecutionExitCode> for ExecutionResult {
         Self {
             next_control_flow: ExecutionControlFlow::Normal,
             exit_code,
+            signal: None,
         }
     }
 }
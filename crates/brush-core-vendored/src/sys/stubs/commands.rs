@@ -42,12 +42,19 @@ impl CommandExt for std::process::Command {
 pub trait ExitStatusExt {
     /// Returns the signal that terminated the process, if any.
     fn signal(&self) -> Option<i32>;
+
+    /// Returns whether the process dumped core.
+    fn core_dumped(&self) -> bool;
 }
 
 impl ExitStatusExt for std::process::ExitStatus {
     fn signal(&self) -> Option<i32> {
         None
     }
+
+    fn core_dumped(&self) -> bool {
+        false
+    }
 }
 
 /// Extension trait for injecting file descriptors into commands.
@@ -102,6 +102,16 @@ pub fn kill_process(
     Err(error::ErrorKind::NotSupportedOnThisPlatform("killing process").into())
 }
 
+/// Sends a signal to every process in a process group.
+///
+/// This is a stub implementation that returns an error.
+pub fn kill_process_group(
+    _pgid: sys::process::ProcessId,
+    _signal: traps::TrapSignal,
+) -> Result<(), error::Error> {
+    Err(error::ErrorKind::NotSupportedOnThisPlatform("killing process group").into())
+}
+
 pub(crate) fn lead_new_process_group() -> Result<(), error::Error> {
     Ok(())
 }
@@ -0,0 +1,841 @@
+//! Persistent trigram index used by `grep({ index: true })` to skip files that
+//! cannot match.
+//!
+//! Each indexed file gets a Bloom filter over the byte trigrams of its content
+//! (ASCII case folded), stored with the size and mtime it was built from.
+//! Queries are derived from the regex syntax tree: literal runs become
+//! required trigrams, alternations become unions, and anything else accepts
+//! every file. Filters only produce false positives, so the real matcher still
+//! decides every match.
+//!
+//! Indexes are kept in memory per (root, hidden, gitignore) key, like the
+//! [`fs_cache`] scan cache, and persisted as an append-only log that is
+//! compacted once superseded records outnumber live ones. Files whose size or
+//! mtime changed are re-indexed on every query, and `invalidateFsScanCache`
+//! drops records explicitly.
+//!
+//! # Configuration (environment overrides)
+//! - `FS_CONTENT_INDEX_DIR` – default `$XDG_CACHE_HOME/pi-natives/grep-index`
+//!   (`~/.cache/...`, or `%LOCALAPPDATA%\...` on Windows)
+
+use std::{
+	collections::HashMap,
+	fs::{self, File, OpenOptions},
+	io::{self, BufReader, Read, Write},
+	path::{Path, PathBuf},
+	sync::{Arc, LazyLock},
+	time::{Instant, UNIX_EPOCH},
+};
+
+use dashmap::DashMap;
+use napi::Result;
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use regex_syntax::hir::{Hir, HirKind};
+
+use crate::{fs_cache, task};
+
+/// File header; bump the version when the record layout changes.
+const MAGIC: &[u8; 8] = b"PIGRIX01";
+const MAX_LOADED_INDEXES: usize = 4;
+/// Bytes inspected for NULs and UTF-16 byte-order marks.
+const SNIFF_BYTES: usize = 512;
+const BITS_PER_TRIGRAM: usize = 10;
+const FILTER_HASHES: u64 = 3;
+const MIN_FILTER_WORDS: usize = 4;
+/// Caps a filter at 2 KiB; larger files saturate and simply stay candidates.
+const MAX_FILTER_WORDS: usize = 256;
+
+const RECORD_FILE: u8 = 1;
+const RECORD_REMOVED: u8 = 2;
+const UNINDEXED: u32 = u32::MAX;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Queries
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Trigram condition a file must satisfy to possibly match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Query {
+	/// No constraint; every file is a candidate.
+	All,
+	/// Every trigram must be present.
+	Trigrams(Vec<u32>),
+	/// Every sub-query must hold.
+	And(Vec<Self>),
+	/// At least one sub-query must hold.
+	Or(Vec<Self>),
+}
+
+impl Query {
+	/// Builds the query for `patterns`, any of which may match.
+	///
+	/// `case_insensitive` must be set whenever the matcher may fold case; the
+	/// index only folds ASCII, so non-ASCII characters then break literal runs.
+	/// Patterns the regex parser rejects (lookaround, backreferences) accept
+	/// every file.
+	pub fn for_patterns<P: AsRef<str>>(
+		patterns: &[P],
+		fixed_strings: bool,
+		case_insensitive: bool,
+	) -> Self {
+		let mut parser = regex_syntax::ParserBuilder::new().utf8(false).build();
+		Self::or(
+			patterns
+				.iter()
+				.map(|pattern| {
+					let pattern = pattern.as_ref();
+					if fixed_strings {
+						literal_query(pattern.as_bytes(), case_insensitive)
+					} else {
+						parser
+							.parse(pattern)
+							.map_or(Self::All, |hir| hir_query(&hir, case_insensitive))
+					}
+				})
+				.collect(),
+		)
+	}
+
+	pub const fn is_all(&self) -> bool {
+		matches!(self, Self::All)
+	}
+
+	fn and(queries: Vec<Self>) -> Self {
+		let mut trigrams = Vec::new();
+		let mut rest = Vec::new();
+		for query in queries {
+			match query {
+				Self::All => {},
+				Self::Trigrams(required) => trigrams.extend(required),
+				Self::And(queries) => {
+					for query in queries {
+						match query {
+							Self::Trigrams(required) => trigrams.extend(required),
+							query => rest.push(query),
+						}
+					}
+				},
+				query @ Self::Or(_) => rest.push(query),
+			}
+		}
+		trigrams.sort_unstable();
+		trigrams.dedup();
+		if !trigrams.is_empty() {
+			rest.insert(0, Self::Trigrams(trigrams));
+		}
+		match rest.len() {
+			0 => Self::All,
+			1 => rest.remove(0),
+			_ => Self::And(rest),
+		}
+	}
+
+	fn or(queries: Vec<Self>) -> Self {
+		if queries.is_empty() || queries.iter().any(Self::is_all) {
+			return Self::All;
+		}
+		let mut alternatives = Vec::new();
+		for query in queries {
+			match query {
+				Self::Or(queries) => alternatives.extend(queries),
+				query => alternatives.push(query),
+			}
+		}
+		if alternatives.len() == 1 {
+			alternatives.remove(0)
+		} else {
+			Self::Or(alternatives)
+		}
+	}
+
+	fn matches(&self, filter: &TrigramFilter) -> bool {
+		match self {
+			Self::All => true,
+			Self::Trigrams(trigrams) => trigrams.iter().all(|&trigram| filter.contains(trigram)),
+			Self::And(queries) => queries.iter().all(|query| query.matches(filter)),
+			Self::Or(queries) => queries.iter().any(|query| query.matches(filter)),
+		}
+	}
+}
+
+fn literal_query(literal: &[u8], case_insensitive: bool) -> Query {
+	if case_insensitive {
+		Query::and(
+			literal
+				.split(|byte| !byte.is_ascii())
+				.map(|run| Query::Trigrams(trigrams(&fold(run))))
+				.collect(),
+		)
+	} else {
+		Query::and(vec![Query::Trigrams(trigrams(&fold(literal)))])
+	}
+}
+
+fn hir_query(hir: &Hir, case_insensitive: bool) -> Query {
+	match hir.kind() {
+		HirKind::Literal(literal) => literal_query(&literal.0, case_insensitive),
+		HirKind::Capture(capture) => hir_query(&capture.sub, case_insensitive),
+		HirKind::Repetition(repetition) if repetition.min > 0 => {
+			hir_query(&repetition.sub, case_insensitive)
+		},
+		HirKind::Concat(subs) => {
+			let mut queries = Vec::new();
+			let mut run = Vec::new();
+			for sub in subs {
+				match sub.kind() {
+					HirKind::Literal(literal) => run.extend_from_slice(&literal.0),
+					// Zero-width assertions keep neighbouring literals adjacent.
+					HirKind::Look(_) | HirKind::Empty => {},
+					_ => {
+						queries.push(literal_query(&run, case_insensitive));
+						run.clear();
+						queries.push(hir_query(sub, case_insensitive));
+					},
+				}
+			}
+			queries.push(literal_query(&run, case_insensitive));
+			Query::and(queries)
+		},
+		HirKind::Alternation(subs) => Query::or(
+			subs
+				.iter()
+				.map(|sub| hir_query(sub, case_insensitive))
+				.collect(),
+		),
+		_ => Query::All,
+	}
+}
+
+/// Lowercases ASCII and maps the two non-ASCII characters whose case folding
+/// is ASCII (KELVIN SIGN and LONG S) to `k` and `s`, so that case-insensitive
+/// queries can rely on ASCII trigrams.
+fn fold(bytes: &[u8]) -> Vec<u8> {
+	let mut folded = Vec::with_capacity(bytes.len());
+	let mut rest = bytes;
+	loop {
+		rest = match rest {
+			[0xe2, 0x84, 0xaa, tail @ ..] => {
+				folded.push(b'k');
+				tail
+			},
+			[0xc5, 0xbf, tail @ ..] => {
+				folded.push(b's');
+				tail
+			},
+			[byte, tail @ ..] => {
+				folded.push(byte.to_ascii_lowercase());
+				tail
+			},
+			[] => break,
+		};
+	}
+	folded
+}
+
+/// Sorted, distinct trigrams of `bytes`, packed into the low 24 bits.
+fn trigrams(bytes: &[u8]) -> Vec<u32> {
+	let mut trigrams: Vec<u32> = bytes
+		.windows(3)
+		.map(|window| u32::from_be_bytes([0, window[0], window[1], window[2]]))
+		.collect();
+	trigrams.sort_unstable();
+	trigrams.dedup();
+	trigrams
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Filters
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Bloom filter over the trigrams of one file.
+#[derive(Clone, Debug)]
+struct TrigramFilter {
+	words: Box<[u64]>,
+}
+
+/// `SplitMix64` finalizer.
+const fn mix(mut value: u64) -> u64 {
+	value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	value ^ (value >> 31)
+}
+
+fn filter_bits(trigram: u32, words: usize) -> impl Iterator<Item = usize> {
+	let hash = mix(u64::from(trigram));
+	let step = (hash >> 32) | 1;
+	let mask = (words as u64 * 64) - 1;
+	(0..FILTER_HASHES).map(move |i| (hash.wrapping_add(i.wrapping_mul(step)) & mask) as usize)
+}
+
+impl TrigramFilter {
+	fn build(trigrams: &[u32]) -> Self {
+		let words = (trigrams.len() * BITS_PER_TRIGRAM)
+			.div_ceil(64)
+			.next_power_of_two()
+			.clamp(MIN_FILTER_WORDS, MAX_FILTER_WORDS);
+		let mut bits = vec![0u64; words];
+		for &trigram in trigrams {
+			for bit in filter_bits(trigram, words) {
+				bits[bit / 64] |= 1 << (bit % 64);
+			}
+		}
+		Self { words: bits.into_boxed_slice() }
+	}
+
+	fn contains(&self, trigram: u32) -> bool {
+		filter_bits(trigram, self.words.len())
+			.all(|bit| self.words[bit / 64] & (1 << (bit % 64)) != 0)
+	}
+}
+
+#[derive(Clone, Debug)]
+struct FileRecord {
+	mtime_ns: u64,
+	size:     u64,
+	/// `None` for binary and UTF-16 files, which always stay candidates.
+	filter:   Option<TrigramFilter>,
+}
+
+impl FileRecord {
+	fn is_candidate(&self, query: &Query) -> bool {
+		self
+			.filter
+			.as_ref()
+			.is_none_or(|filter| query.matches(filter))
+	}
+}
+
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+	let metadata = fs::metadata(path).ok()?;
+	let mtime_ns = metadata
+		.modified()
+		.ok()?
+		.duration_since(UNIX_EPOCH)
+		.ok()?
+		.as_nanos() as u64;
+	Some((mtime_ns, metadata.len()))
+}
+
+/// Builds the filter for the first `max_bytes` of `path` (the part grep
+/// searches).
+fn index_file(path: &Path, max_bytes: u64) -> io::Result<Option<TrigramFilter>> {
+	let mut content = Vec::new();
+	File::open(path)?
+		.take(max_bytes)
+		.read_to_end(&mut content)?;
+	let head = &content[..content.len().min(SNIFF_BYTES)];
+	// Binary files are skipped by grep, and UTF-16 is transcoded before
+	// matching, so their raw bytes say nothing about what can match.
+	if head.contains(&0) || head.starts_with(&[0xff, 0xfe]) || head.starts_with(&[0xfe, 0xff]) {
+		return Ok(None);
+	}
+	Ok(Some(TrigramFilter::build(&trigrams(&fold(&content)))))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Persistence
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct IndexKey {
+	root:           PathBuf,
+	include_hidden: bool,
+	use_gitignore:  bool,
+}
+
+impl IndexKey {
+	fn header(&self) -> Vec<u8> {
+		let root = self.root.to_string_lossy();
+		let mut header = MAGIC.to_vec();
+		header.push(u8::from(self.include_hidden));
+		header.push(u8::from(self.use_gitignore));
+		header.extend_from_slice(&(root.len() as u32).to_le_bytes());
+		header.extend_from_slice(root.as_bytes());
+		header
+	}
+}
+
+/// FNV-1a, used for file names and record checksums (stable across builds,
+/// unlike `DefaultHasher`).
+fn fnv1a(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+		(hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+	})
+}
+
+fn index_dir() -> PathBuf {
+	if let Some(dir) = std::env::var_os("FS_CONTENT_INDEX_DIR") {
+		return PathBuf::from(dir);
+	}
+	let cache_dir = if cfg!(windows) {
+		std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+	} else {
+		std::env::var_os("XDG_CACHE_HOME")
+			.map(PathBuf::from)
+			.or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+	};
+	cache_dir
+		.unwrap_or_else(std::env::temp_dir)
+		.join("pi-natives")
+		.join("grep-index")
+}
+
+fn encode_record(out: &mut Vec<u8>, key: &str, record: Option<&FileRecord>) {
+	let start = out.len();
+	out.push(if record.is_some() {
+		RECORD_FILE
+	} else {
+		RECORD_REMOVED
+	});
+	out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+	out.extend_from_slice(key.as_bytes());
+	if let Some(record) = record {
+		out.extend_from_slice(&record.mtime_ns.to_le_bytes());
+		out.extend_from_slice(&record.size.to_le_bytes());
+		match &record.filter {
+			Some(filter) => {
+				out.extend_from_slice(&(filter.words.len() as u32).to_le_bytes());
+				for word in &filter.words {
+					out.extend_from_slice(&word.to_le_bytes());
+				}
+			},
+			None => out.extend_from_slice(&UNINDEXED.to_le_bytes()),
+		}
+	}
+	let checksum = fnv1a(&out[start..]);
+	out.extend_from_slice(&checksum.to_le_bytes());
+}
+
+/// Reads `len` bytes, appending them to `record` for the checksum.
+fn read_bytes<R: Read>(reader: &mut R, record: &mut Vec<u8>, len: usize) -> io::Result<()> {
+	let start = record.len();
+	record.resize(start + len, 0);
+	reader.read_exact(&mut record[start..])
+}
+
+fn read_u32<R: Read>(reader: &mut R, record: &mut Vec<u8>) -> io::Result<u32> {
+	read_bytes(reader, record, 4)?;
+	Ok(u32::from_le_bytes(record[record.len() - 4..].try_into().unwrap_or_default()))
+}
+
+fn read_u64<R: Read>(reader: &mut R, record: &mut Vec<u8>) -> io::Result<u64> {
+	read_bytes(reader, record, 8)?;
+	Ok(u64::from_le_bytes(record[record.len() - 8..].try_into().unwrap_or_default()))
+}
+
+/// Decodes one record; `Ok(None)` at a clean end of file.
+fn decode_record<R: Read>(
+	reader: &mut R,
+	record: &mut Vec<u8>,
+) -> io::Result<Option<(String, Option<FileRecord>)>> {
+	record.clear();
+	let mut kind = [0u8];
+	if reader.read(&mut kind)? == 0 {
+		return Ok(None);
+	}
+	record.push(kind[0]);
+	let key_len = read_u32(reader, record)? as usize;
+	read_bytes(reader, record, key_len)?;
+	let key = String::from_utf8_lossy(&record[5..]).into_owned();
+	let file = match kind[0] {
+		RECORD_FILE => {
+			let mtime_ns = read_u64(reader, record)?;
+			let size = read_u64(reader, record)?;
+			let words = read_u32(reader, record)?;
+			let filter = if words == UNINDEXED {
+				None
+			} else if (MIN_FILTER_WORDS..=MAX_FILTER_WORDS).contains(&(words as usize))
+				&& words.is_power_of_two()
+			{
+				let mut bits = Vec::with_capacity(words as usize);
+				for _ in 0..words {
+					bits.push(read_u64(reader, record)?);
+				}
+				Some(TrigramFilter { words: bits.into_boxed_slice() })
+			} else {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid filter size"));
+			};
+			Some(FileRecord { mtime_ns, size, filter })
+		},
+		RECORD_REMOVED => None,
+		_ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid record kind")),
+	};
+	let checksum = fnv1a(record);
+	let mut stored = [0u8; 8];
+	reader.read_exact(&mut stored)?;
+	if u64::from_le_bytes(stored) != checksum {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "checksum mismatch"));
+	}
+	Ok(Some((key, file)))
+}
+
+struct ContentIndex {
+	key:       IndexKey,
+	/// On-disk log.
+	path:      PathBuf,
+	/// Records by normalized path relative to the root.
+	files:     HashMap<String, FileRecord>,
+	/// Paths changed since the last save.
+	pending:   Vec<String>,
+	/// Records in the log superseded by later ones.
+	garbage:   usize,
+	/// Whether `path` holds an intact log for this key.
+	persisted: bool,
+}
+
+impl ContentIndex {
+	/// Loads the log at `path`, keeping every record before the first damaged
+	/// one.
+	fn load(key: IndexKey, path: PathBuf) -> Self {
+		let mut index = Self {
+			key,
+			path,
+			files: HashMap::new(),
+			pending: Vec::new(),
+			garbage: 0,
+			persisted: false,
+		};
+		let Ok(file) = File::open(&index.path) else {
+			return index;
+		};
+		let mut reader = BufReader::new(file);
+		let header = index.key.header();
+		let mut stored = vec![0u8; header.len()];
+		if reader.read_exact(&mut stored).is_err() || stored != header {
+			return index;
+		}
+		let mut record = Vec::new();
+		loop {
+			match decode_record(&mut reader, &mut record) {
+				Ok(Some((key, file))) => {
+					let replaced = if let Some(file) = file {
+						index.files.insert(key, file).is_some()
+					} else {
+						// The tombstone itself is garbage too.
+						index.garbage += 1;
+						index.files.remove(&key).is_some()
+					};
+					index.garbage += usize::from(replaced);
+				},
+				Ok(None) => {
+					index.persisted = true;
+					break;
+				},
+				Err(_) => break,
+			}
+		}
+		index
+	}
+
+	fn insert(&mut self, key: String, record: FileRecord) {
+		self.files.insert(key.clone(), record);
+		self.pending.push(key);
+	}
+
+	/// Drops the record for `relative` and everything below it.
+	fn remove_prefix(&mut self, relative: &str) {
+		let removed: Vec<String> = self
+			.files
+			.keys()
+			.filter(|key| {
+				relative.is_empty()
+					|| key
+						.strip_prefix(relative)
+						.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+			})
+			.cloned()
+			.collect();
+		for key in removed {
+			self.files.remove(&key);
+			self.pending.push(key);
+		}
+	}
+
+	/// Appends pending changes, or rewrites the log when it is missing,
+	/// damaged, or mostly superseded records. Failures only cost a rebuild.
+	fn save(&mut self) {
+		if self.persisted && self.pending.is_empty() {
+			return;
+		}
+		let result = if !self.persisted || self.garbage + self.pending.len() > self.files.len() {
+			self.rewrite()
+		} else {
+			self.append()
+		};
+		self.pending.clear();
+		self.persisted = result.is_ok();
+	}
+
+	fn append(&mut self) -> io::Result<()> {
+		let mut batch = Vec::new();
+		for key in &self.pending {
+			encode_record(&mut batch, key, self.files.get(key));
+		}
+		// One write per batch keeps concurrent appenders from interleaving.
+		OpenOptions::new()
+			.append(true)
+			.open(&self.path)?
+			.write_all(&batch)?;
+		self.garbage += self.pending.len();
+		Ok(())
+	}
+
+	fn rewrite(&mut self) -> io::Result<()> {
+		let root = &self.key.root;
+		self.files.retain(|key, _| root.join(key).is_file());
+		let mut content = self.key.header();
+		for (key, record) in &self.files {
+			encode_record(&mut content, key, Some(record));
+		}
+		if let Some(dir) = self.path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		let temp = self
+			.path
+			.with_extension(format!("{}.tmp", std::process::id()));
+		fs::write(&temp, content)?;
+		fs::rename(&temp, &self.path).inspect_err(|_| {
+			let _ = fs::remove_file(&temp);
+		})?;
+		self.garbage = 0;
+		Ok(())
+	}
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Index API
+// ═══════════════════════════════════════════════════════════════════════════
+
+struct LoadedIndex {
+	last_used:   Instant,
+	index:       Arc<Mutex<ContentIndex>>,
+	/// Paths invalidated since the last `filter_candidates` call, which drops
+	/// their records. Kept apart so invalidating never waits for an indexing
+	/// pass.
+	invalidated: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+static INDEXES: LazyLock<DashMap<IndexKey, LoadedIndex>> = LazyLock::new(DashMap::new);
+
+/// Returns the index of `key` and its invalidated paths, loading it if needed.
+fn load_index(key: IndexKey) -> (Arc<Mutex<ContentIndex>>, Arc<Mutex<Vec<PathBuf>>>) {
+	let now = Instant::now();
+	let loaded = INDEXES
+		.entry(key.clone())
+		.and_modify(|loaded| loaded.last_used = now)
+		.or_insert_with(|| {
+			let path = index_dir().join(format!("{:016x}.idx", fnv1a(&key.header())));
+			LoadedIndex {
+				last_used:   now,
+				index:       Arc::new(Mutex::new(ContentIndex::load(key, path))),
+				invalidated: Arc::default(),
+			}
+		});
+	let index = (loaded.index.clone(), loaded.invalidated.clone());
+	drop(loaded);
+	if INDEXES.len() > MAX_LOADED_INDEXES
+		&& let Some(oldest) = INDEXES
+			.iter()
+			.min_by_key(|loaded| loaded.value().last_used)
+			.map(|loaded| loaded.key().clone())
+	{
+		INDEXES.remove(&oldest);
+	}
+	index
+}
+
+/// Keeps the entries that may match `query`, (re-)indexing new and changed
+/// files along the way.
+///
+/// `entries` are files under `root` as listed by a scan with the given
+/// visibility settings, as absolute paths. Only the first
+/// `max_bytes` of each file are indexed, matching what grep searches.
+pub fn filter_candidates<T: AsRef<Path> + Send + Sync>(
+	root: &Path,
+	include_hidden: bool,
+	use_gitignore: bool,
+	entries: Vec<T>,
+	query: &Query,
+	max_bytes: u64,
+	ct: &task::CancelToken,
+) -> Result<Vec<T>> {
+	if query.is_all() {
+		return Ok(entries);
+	}
+	let (index, invalidated) =
+		load_index(IndexKey { root: root.to_path_buf(), include_hidden, use_gitignore });
+	let mut index = index.lock();
+	let invalidated = std::mem::take(&mut *invalidated.lock());
+	for target in invalidated {
+		let relative = fs_cache::normalize_relative_path(root, &target).into_owned();
+		index.remove_prefix(&relative);
+	}
+	let files = &index.files;
+	let checked: Vec<(bool, Option<(String, FileRecord)>)> = entries
+		.par_iter()
+		.map(|entry| {
+			if ct.heartbeat().is_err() {
+				return (true, None);
+			}
+			let path = entry.as_ref();
+			let Some((mtime_ns, size)) = file_stamp(path) else {
+				return (true, None);
+			};
+			let key = fs_cache::normalize_relative_path(root, path);
+			if let Some(record) = files.get(key.as_ref())
+				&& record.mtime_ns == mtime_ns
+				&& record.size == size
+			{
+				return (record.is_candidate(query), None);
+			}
+			let Ok(filter) = index_file(path, max_bytes) else {
+				return (true, None);
+			};
+			let record = FileRecord { mtime_ns, size, filter };
+			(record.is_candidate(query), Some((key.into_owned(), record)))
+		})
+		.collect();
+	ct.heartbeat()?;
+
+	let mut keep = Vec::with_capacity(checked.len());
+	for (candidate, update) in checked {
+		keep.push(candidate);
+		if let Some((key, record)) = update {
+			index.insert(key, record);
+		}
+	}
+	index.save();
+	Ok(entries
+		.into_iter()
+		.zip(keep)
+		.filter_map(|(entry, candidate)| candidate.then_some(entry))
+		.collect())
+}
+
+/// Marks index records for `target` (a file or directory) as stale.
+///
+/// Applies to every loaded index whose root contains `target`; the records are
+/// dropped by the index's next `filter_candidates` call. Persisted indexes of
+/// other processes are revalidated by size and mtime instead.
+pub fn invalidate_path(target: &Path) {
+	for loaded in INDEXES.iter() {
+		if target.starts_with(&loaded.key().root) {
+			loaded.invalidated.lock().push(target.to_path_buf());
+		}
+	}
+}
+
+/// Drops every index, including the persisted ones.
+pub fn invalidate_all() {
+	INDEXES.clear();
+	let Ok(entries) = fs::read_dir(index_dir()) else {
+		return;
+	};
+	for entry in entries.flatten() {
+		if entry.path().extension().is_some_and(|ext| ext == "idx") {
+			let _ = fs::remove_file(entry.path());
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn query(pattern: &str) -> Query {
+		Query::for_patterns(&[pattern], false, false)
+	}
+
+	fn union(literals: &[&[u8]]) -> Vec<u32> {
+		let mut all: Vec<u32> = literals
+			.iter()
+			.flat_map(|literal| trigrams(literal))
+			.collect();
+		all.sort_unstable();
+		all.dedup();
+		all
+	}
+
+	fn filter(content: &str) -> TrigramFilter {
+		TrigramFilter::build(&trigrams(&fold(content.as_bytes())))
+	}
+
+	#[test]
+	fn test_query_extraction() {
+		assert_eq!(query("ab"), Query::All);
+		assert_eq!(query(r"\w+"), Query::All);
+		assert_eq!(query(r"(?=foo)bar"), Query::All);
+		assert_eq!(query("Foo"), Query::Trigrams(trigrams(b"foo")));
+		assert_eq!(query(r"^foo\bbar"), Query::Trigrams(trigrams(b"foobar")));
+		assert_eq!(query(r"fn \w+_test"), Query::Trigrams(union(&[b"fn ", b"_test"])));
+		assert_eq!(
+			query("(todo|fixme)"),
+			Query::Or(vec![Query::Trigrams(trigrams(b"todo")), Query::Trigrams(trigrams(b"fixme"))])
+		);
+		assert_eq!(query("(todo|x)"), Query::All);
+		assert_eq!(query("x?abc"), Query::Trigrams(trigrams(b"abc")));
+		assert_eq!(
+			Query::for_patterns(&["a.b(", "ERROR"], true, false),
+			Query::Or(vec![Query::Trigrams(trigrams(b"a.b(")), Query::Trigrams(trigrams(b"error"))])
+		);
+		assert_eq!(
+			Query::for_patterns(&["caf\u{e9} au lait"], false, true),
+			Query::Trigrams(union(&[b"caf", b" au lait"]))
+		);
+	}
+
+	#[test]
+	fn test_filters_never_miss() {
+		let content = "fn parse_config() {\n\tlet KEY = \u{212A}elvin;\n}\n";
+		let filter = filter(content);
+		for pattern in ["parse_config", r"fn \w+\(", "(?i)PARSE", "key", "kelvin", "config|missing"] {
+			assert!(query(pattern).matches(&filter), "{pattern}");
+		}
+		assert!(Query::for_patterns(&["PARSE_CONFIG"], true, true).matches(&filter));
+		assert!(!query("missing_function").matches(&filter));
+		assert!(!query("(alpha|beta)gamma").matches(&filter));
+	}
+
+	#[test]
+	fn test_log_roundtrip() {
+		let dir = std::env::temp_dir().join(format!("pi-natives-index-{}", std::process::id()));
+		let key =
+			IndexKey { root: dir.clone(), include_hidden: true, use_gitignore: true };
+		let path = dir.join("test.idx");
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("a.rs"), "alpha").unwrap();
+		fs::write(dir.join("b.rs"), "beta").unwrap();
+		fs::write(dir.join("c.rs"), "gamma").unwrap();
+		let record = |content: &str| FileRecord {
+			mtime_ns: 1,
+			size:     content.len() as u64,
+			filter:   Some(filter(content)),
+		};
+
+		let mut index = ContentIndex::load(key.clone(), path.clone());
+		index.insert("a.rs".to_string(), record("alpha"));
+		index.insert("b.rs".to_string(), record("beta"));
+		index.insert("c.rs".to_string(), record("gamma"));
+		index.save();
+		index.insert("b.rs".to_string(), FileRecord { filter: None, ..record("beta") });
+		index.remove_prefix("a.rs");
+		index.save();
+
+		let loaded = ContentIndex::load(key.clone(), path.clone());
+		assert!(loaded.persisted);
+		assert_eq!(loaded.garbage, 3);
+		assert!(!loaded.files.contains_key("a.rs"));
+		assert!(loaded.files["b.rs"].filter.is_none());
+
+		// A torn append keeps the intact prefix and forces a rewrite.
+		let mut content = fs::read(&path).unwrap();
+		content.truncate(content.len() - 3);
+		fs::write(&path, content).unwrap();
+		let damaged = ContentIndex::load(key, path);
+		assert!(!damaged.persisted);
+		assert!(damaged.files.contains_key("a.rs"));
+		assert!(damaged.files["b.rs"].filter.is_none());
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
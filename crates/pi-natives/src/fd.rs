@@ -1,14 +1,13 @@
 //! Fuzzy file path discovery for autocomplete and @-mention resolution.
 //!
 //! Searches for files and directories whose paths match a query string via
-//! subsequence scoring. Uses the shared [`fs_cache`] for directory scanning.
-
-use std::path::Path;
+//! optimal-alignment fuzzy scoring, reporting the matched positions. Uses the
+//! shared [`fs_cache`] for directory scanning.
 
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
-use crate::{fs_cache, task};
+use crate::{frecency, fs_cache, task};
 
 // ═══════════════════════════════════════════════════════════════════════════
 // Public types
@@ -18,23 +17,40 @@ use crate::{fs_cache, task};
 #[napi(object)]
 pub struct FuzzyFindOptions<'env> {
 	/// Fuzzy query to match against file paths (case-insensitive).
-	pub query:       String,
+	pub query:           String,
 	/// Directory to search.
-	pub path:        String,
+	pub path:            String,
 	/// Include hidden files (default: false).
-	pub hidden:      Option<bool>,
+	pub hidden:          Option<bool>,
 	/// Respect .gitignore (default: true).
-	pub gitignore:   Option<bool>,
+	pub gitignore:       Option<bool>,
 	/// Enable shared filesystem scan cache (default: false).
-	pub cache:       Option<bool>,
+	pub cache:           Option<bool>,
 	/// Maximum number of matches to return (default: 100).
 	#[napi(js_name = "maxResults")]
-	pub max_results: Option<u32>,
+	pub max_results:     Option<u32>,
+	/// Boost recently and frequently used files, as recorded by
+	/// `recordFileAccess` (default: false).
+	pub frecency:        Option<bool>,
+	/// Extra ignore filenames with gitignore syntax, honored like `.ignore`
+	/// when `gitignore` is set (default: `.ompignore`, `.piignore`).
+	#[napi(js_name = "ignoreFiles")]
+	pub ignore_files:    Option<Vec<String>>,
+	/// Override globs as for ripgrep's `--glob`, taking precedence over ignore
+	/// files: `!glob` excludes matches, and positive globs restrict files to
+	/// those matching one.
+	pub overrides:       Option<Vec<String>>,
+	/// Maximum directory depth to descend (1 lists only direct children).
+	#[napi(js_name = "maxDepth")]
+	pub max_depth:       Option<u32>,
+	/// Follow symbolic links to directories (default: false).
+	#[napi(js_name = "followSymlinks")]
+	pub follow_symlinks: Option<bool>,
 	/// Abort signal for cancelling the operation.
-	pub signal:      Option<Unknown<'env>>,
+	pub signal:          Option<Unknown<'env>>,
 	/// Timeout in milliseconds for the operation.
 	#[napi(js_name = "timeoutMs")]
-	pub timeout_ms:  Option<u32>,
+	pub timeout_ms:      Option<u32>,
 }
 
 /// A single match in fuzzy find results.
@@ -47,6 +63,9 @@ pub struct FuzzyFindMatch {
 	pub is_directory: bool,
 	/// Match quality score (higher is better).
 	pub score:        u32,
+	/// Offsets of the matched characters in `path`, in UTF-16 code units
+	/// (ascending; empty for an empty query).
+	pub positions:    Vec<u32>,
 }
 
 /// Result of fuzzy file path search.
@@ -73,78 +92,260 @@ fn normalize_fuzzy_text(value: &str) -> String {
 		.collect()
 }
 
-/// Scores a query as a subsequence of `target`. Returns 0 if not a subsequence.
-fn fuzzy_subsequence_score(query: &str, target: &str) -> u32 {
-	let query_chars: Vec<char> = query.chars().collect();
-	if query_chars.is_empty() {
-		return 1;
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+/// Bonus for a match right after a non-word character.
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+/// Bonus for matching a non-word character itself.
+const BONUS_NON_WORD: i32 = SCORE_MATCH / 2;
+const BONUS_BOUNDARY_WHITE: i32 = BONUS_BOUNDARY + 2;
+const BONUS_BOUNDARY_DELIMITER: i32 = BONUS_BOUNDARY + 1;
+/// Bonus for camelCase humps and the first digit of a number.
+const BONUS_CAMEL_123: i32 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+/// Minimum bonus inside a run of consecutive matches; offsets a gap.
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+/// Bonus when the whole query aligns within the file name.
+const BONUS_FILE_NAME: i32 = SCORE_MATCH * 2;
+/// Bonus when the file name (or its stem) equals the query.
+const BONUS_EXACT_NAME: i32 = SCORE_MATCH * 4;
+const BONUS_DIRECTORY: u32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CharClass {
+	White,
+	NonWord,
+	Delimiter,
+	Lower,
+	Upper,
+	Number,
+}
+
+impl CharClass {
+	fn of(ch: char) -> Self {
+		if ch.is_whitespace() {
+			Self::White
+		} else if matches!(ch, '/' | '\\' | ',' | ':' | ';' | '|') {
+			Self::Delimiter
+		} else if ch.is_numeric() {
+			Self::Number
+		} else if ch.is_uppercase() {
+			Self::Upper
+		} else if ch.is_alphabetic() {
+			Self::Lower
+		} else {
+			Self::NonWord
+		}
+	}
+
+	const fn is_word(self) -> bool {
+		matches!(self, Self::Lower | Self::Upper | Self::Number)
 	}
-	let mut query_index = 0usize;
-	let mut gaps = 0u32;
-	let mut last_match_index: Option<usize> = None;
-	for (target_index, target_ch) in target.chars().enumerate() {
-		if query_index >= query_chars.len() {
-			break;
+}
+
+/// Bonus for matching a character of class `class` that follows `prev`.
+const fn position_bonus(prev: CharClass, class: CharClass) -> i32 {
+	if class.is_word() {
+		match prev {
+			CharClass::White => return BONUS_BOUNDARY_WHITE,
+			CharClass::Delimiter => return BONUS_BOUNDARY_DELIMITER,
+			CharClass::NonWord => return BONUS_BOUNDARY,
+			_ => {},
 		}
-		if query_chars[query_index] == target_ch {
-			if let Some(last_index) = last_match_index
-				&& target_index > last_index + 1
-			{
-				gaps = gaps.saturating_add(1);
+	}
+	match (prev, class) {
+		(CharClass::Lower, CharClass::Upper) => BONUS_CAMEL_123,
+		(prev, CharClass::Number) if !matches!(prev, CharClass::Number) => BONUS_CAMEL_123,
+		(_, CharClass::NonWord | CharClass::Delimiter) => BONUS_NON_WORD,
+		(_, CharClass::White) => BONUS_BOUNDARY_WHITE,
+		_ => 0,
+	}
+}
+
+/// Finds the best alignment of `query` (lowercase) as a subsequence of `text`,
+/// in the style of fzf's v2 algorithm: a Smith-Waterman pass with affine gap
+/// penalties and bonuses for word boundaries, path separators, camelCase and
+/// consecutive runs.
+///
+/// Returns the score and the char indices of the matched characters, or `None`
+/// if `query` is not a subsequence of `text`.
+fn fuzzy_align(query: &[char], text: &[char]) -> Option<(i32, Vec<usize>)> {
+	let &last = query.last()?;
+	let lower: Vec<char> = text
+		.iter()
+		.map(|&ch| ch.to_lowercase().next().unwrap_or(ch))
+		.collect();
+
+	// A greedy pass rejects non-matches cheaply and bounds the window: the
+	// alignment starts at or after the first `first` and ends at or before the
+	// last `last`.
+	let mut query_index = 0;
+	let mut start = 0;
+	for (index, &ch) in lower.iter().enumerate() {
+		if ch == query[query_index] {
+			if query_index == 0 {
+				start = index;
 			}
-			last_match_index = Some(target_index);
 			query_index += 1;
+			if query_index == query.len() {
+				break;
+			}
+		}
+	}
+	if query_index < query.len() {
+		return None;
+	}
+	let end = lower.iter().rposition(|&ch| ch == last)?;
+
+	let width = end + 1 - start;
+	let bonuses: Vec<i32> = (start..=end)
+		.map(|index| {
+			let prev = index
+				.checked_sub(1)
+				.map_or(CharClass::Delimiter, |prev| CharClass::of(text[prev]));
+			position_bonus(prev, CharClass::of(text[index]))
+		})
+		.collect();
+
+	// Row-major `query.len() x width` tables. `matched` holds the best score
+	// with query[i] matched at column j, `gapped` the best score with query[i]
+	// matched before column j and the gap up to j penalized.
+	let cells = query.len() * width;
+	let unreachable = i32::MIN / 2;
+	let mut matched = vec![unreachable; cells];
+	let mut run_bonus = vec![0i32; cells];
+	let mut consecutive = vec![false; cells];
+	let mut gapped = vec![unreachable; cells];
+	let mut gap_origin = vec![0usize; cells];
+
+	for (row, &query_ch) in query.iter().enumerate() {
+		for col in 0..width {
+			let cell = row * width + col;
+			if lower[start + col] == query_ch {
+				let bonus = bonuses[col];
+				if row == 0 {
+					matched[cell] = SCORE_MATCH + bonus * BONUS_FIRST_CHAR_MULTIPLIER;
+					run_bonus[cell] = bonus;
+				} else if col > 0 {
+					let diagonal = cell - width - 1;
+					if matched[diagonal] > unreachable {
+						// Inside a run, every character earns at least the bonus of
+						// the run's first character, unless it starts a stronger run.
+						let run = run_bonus[diagonal];
+						let (bonus, run) = if bonus >= BONUS_BOUNDARY && bonus > run {
+							(bonus, bonus)
+						} else {
+							(bonus.max(run).max(BONUS_CONSECUTIVE), run)
+						};
+						matched[cell] = matched[diagonal] + SCORE_MATCH + bonus;
+						run_bonus[cell] = run;
+						consecutive[cell] = true;
+					}
+					let after_gap = gapped[diagonal].saturating_add(SCORE_MATCH + bonus);
+					if gapped[diagonal] > unreachable && after_gap > matched[cell] {
+						matched[cell] = after_gap;
+						run_bonus[cell] = bonus;
+						consecutive[cell] = false;
+					}
+				}
+			}
+			if col > 0 {
+				let left = cell - 1;
+				let open = matched[left].saturating_add(SCORE_GAP_START);
+				let extend = gapped[left].saturating_add(SCORE_GAP_EXTENSION);
+				if matched[left] > unreachable && open >= extend {
+					gapped[cell] = open;
+					gap_origin[cell] = col - 1;
+				} else if gapped[left] > unreachable {
+					gapped[cell] = extend;
+					gap_origin[cell] = gap_origin[left];
+				}
+			}
 		}
 	}
-	if query_index != query_chars.len() {
-		return 0;
+
+	let last_row = (query.len() - 1) * width;
+	let (mut col, score) = (0..width)
+		.map(|col| (col, matched[last_row + col]))
+		.filter(|&(_, score)| score > unreachable)
+		.max_by_key(|&(col, score)| (score, std::cmp::Reverse(col)))?;
+	let mut positions = vec![0; query.len()];
+	for row in (0..query.len()).rev() {
+		positions[row] = start + col;
+		if row > 0 {
+			let cell = row * width + col;
+			col = if consecutive[cell] {
+				col - 1
+			} else {
+				gap_origin[cell - width - 1]
+			};
+		}
 	}
-	let gap_penalty = gaps.saturating_mul(5);
-	40u32.saturating_sub(gap_penalty).max(1)
+	Some((score, positions))
 }
 
-/// Composite path scoring: exact > starts-with > contains > fuzzy subsequence.
+/// Scores `path` against the normalized query, returning the score and the
+/// matched positions as UTF-16 offsets into `path`.
+///
+/// The whole path is aligned, and the file name on its own; a query that fits
+/// entirely within the file name earns [`BONUS_FILE_NAME`].
 fn score_fuzzy_path(
 	path: &str,
 	is_directory: bool,
 	query_lower: &str,
-	normalized_query: &str,
-) -> u32 {
-	let lower_path = path.to_lowercase();
-	let normalized_path = normalize_fuzzy_text(path);
-	let file_name_source = path.trim_end_matches('/');
-	let file_name = Path::new(file_name_source)
-		.file_name()
-		.and_then(|name| name.to_str())
-		.unwrap_or(file_name_source);
-	let lower_file_name = file_name.to_lowercase();
-	let normalized_file_name = normalize_fuzzy_text(file_name);
-
-	let mut score = if query_lower.is_empty() {
-		1
-	} else if lower_file_name == query_lower {
-		120
-	} else if lower_file_name.starts_with(query_lower) {
-		100
-	} else if lower_file_name.contains(query_lower) {
-		80
-	} else if lower_path.contains(query_lower) {
-		60
-	} else {
-		let file_name_fuzzy = fuzzy_subsequence_score(normalized_query, &normalized_file_name);
-		if file_name_fuzzy > 0 {
-			50 + file_name_fuzzy
-		} else {
-			let path_fuzzy = fuzzy_subsequence_score(normalized_query, &normalized_path);
-			if path_fuzzy > 0 { 30 + path_fuzzy } else { 0 }
-		}
+	normalized_query: &[char],
+) -> Option<(u32, Vec<u32>)> {
+	let directory_bonus = if is_directory { BONUS_DIRECTORY } else { 0 };
+	if normalized_query.is_empty() {
+		return Some((1 + directory_bonus, Vec::new()));
+	}
+
+	let chars: Vec<char> = path.chars().collect();
+	let name_end = chars.len() - usize::from(chars.last() == Some(&'/'));
+	let name_start = chars[..name_end]
+		.iter()
+		.rposition(|&ch| ch == '/')
+		.map_or(0, |index| index + 1);
+	let full = fuzzy_align(normalized_query, &chars);
+	let in_name =
+		fuzzy_align(normalized_query, &chars[name_start..name_end]).map(|(score, positions)| {
+			let positions = positions.into_iter().map(|pos| pos + name_start).collect();
+			(score + BONUS_FILE_NAME, positions)
+		});
+	let (mut score, positions) = match (full, in_name) {
+		(Some(full), Some(in_name)) => {
+			if in_name.0 >= full.0 {
+				in_name
+			} else {
+				full
+			}
+		},
+		(full, in_name) => full.or(in_name)?,
 	};
 
-	if is_directory && score > 0 {
-		score += 10;
+	let file_name: String = chars[name_start..name_end]
+		.iter()
+		.collect::<String>()
+		.to_lowercase();
+	let stem = file_name
+		.rsplit_once('.')
+		.map_or(file_name.as_str(), |(stem, _)| stem);
+	if file_name == query_lower || stem == query_lower {
+		score += BONUS_EXACT_NAME;
 	}
 
-	score
+	let mut utf16_offsets = Vec::with_capacity(chars.len());
+	let mut offset = 0u32;
+	for ch in &chars {
+		utf16_offsets.push(offset);
+		offset += ch.len_utf16() as u32;
+	}
+	let positions = positions
+		.into_iter()
+		.map(|pos| utf16_offsets[pos])
+		.collect();
+	Some((score.max(1) as u32 + directory_bonus, positions))
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -153,12 +354,35 @@ fn score_fuzzy_path(
 
 /// Internal configuration for fuzzy find, extracted from options.
 struct FuzzyFindConfig {
-	query:       String,
-	path:        String,
-	hidden:      Option<bool>,
-	gitignore:   Option<bool>,
-	max_results: Option<u32>,
-	cache:       Option<bool>,
+	query:           String,
+	path:            String,
+	hidden:          Option<bool>,
+	gitignore:       Option<bool>,
+	max_results:     Option<u32>,
+	cache:           Option<bool>,
+	frecency:        Option<bool>,
+	ignore_files:    Option<Vec<String>>,
+	overrides:       Option<Vec<String>>,
+	max_depth:       Option<u32>,
+	follow_symlinks: Option<bool>,
+}
+
+/// Score bonus per unit of `ln(1 + frecency)`, so a file edited today gains
+/// about as much as a matched word boundary.
+const FRECENCY_WEIGHT: f64 = 24.0;
+
+/// Adds a bonus for files in the frecency store of `root`.
+fn apply_frecency(root: &std::path::Path, scored: &mut [FuzzyFindMatch]) {
+	let scores = frecency::scores_for(root);
+	if scores.is_empty() {
+		return;
+	}
+	for entry in scored.iter_mut().filter(|entry| !entry.is_directory) {
+		if let Some(score) = scores.get(&entry.path) {
+			let bonus = (score.ln_1p() * FRECENCY_WEIGHT).round() as u32;
+			entry.score = entry.score.saturating_add(bonus);
+		}
+	}
 }
 
 fn clamp_u32(value: u64) -> u32 {
@@ -167,22 +391,28 @@ fn clamp_u32(value: u64) -> u32 {
 
 fn fuzzy_find_sync(config: FuzzyFindConfig, ct: task::CancelToken) -> Result<FuzzyFindResult> {
 	let root = fs_cache::resolve_search_path(&config.path)?;
-	let include_hidden = config.hidden.unwrap_or(false);
-	let respect_gitignore = config.gitignore.unwrap_or(true);
+	let scan_options =
+		fs_cache::ScanOptions::new(config.hidden.unwrap_or(false), config.gitignore.unwrap_or(true))
+			.with_walk_options(
+				config.ignore_files,
+				config.overrides,
+				config.max_depth,
+				config.follow_symlinks,
+			)?;
 	let max_results = config.max_results.unwrap_or(100) as usize;
 	if max_results == 0 {
 		return Ok(FuzzyFindResult { matches: Vec::new(), total_matches: 0 });
 	}
 
 	let query_lower = config.query.trim().to_lowercase();
-	let normalized_query = normalize_fuzzy_text(&query_lower);
+	let normalized_query: Vec<char> = normalize_fuzzy_text(&query_lower).chars().collect();
 	if !query_lower.is_empty() && normalized_query.is_empty() {
 		return Ok(FuzzyFindResult { matches: Vec::new(), total_matches: 0 });
 	}
 
 	let use_cache = config.cache.unwrap_or(false);
 	let mut scored = if use_cache {
-		let scan = fs_cache::get_or_scan(&root, include_hidden, respect_gitignore, &ct)?;
+		let scan = fs_cache::get_or_scan(&root, &scan_options, &ct)?;
 		let mut scored = score_entries(&scan.entries, &query_lower, &normalized_query, &ct)?;
 		// Empty-result recheck: if the query was non-trivial but produced zero matches
 		// from a cached scan that's old enough, force one rescan before giving up.
@@ -190,16 +420,24 @@ fn fuzzy_find_sync(config: FuzzyFindConfig, ct: task::CancelToken) -> Result<Fuz
 			&& !query_lower.is_empty()
 			&& scan.cache_age_ms >= fs_cache::empty_recheck_ms()
 		{
-			let fresh = fs_cache::force_rescan(&root, include_hidden, respect_gitignore, true, &ct)?;
+			let fresh = fs_cache::force_rescan(&root, &scan_options, true, &ct)?;
 			scored = score_entries(&fresh, &query_lower, &normalized_query, &ct)?;
 		}
 		scored
 	} else {
-		let fresh = fs_cache::force_rescan(&root, include_hidden, respect_gitignore, false, &ct)?;
+		let fresh = fs_cache::force_rescan(&root, &scan_options, false, &ct)?;
 		score_entries(&fresh, &query_lower, &normalized_query, &ct)?
 	};
+	if config.frecency.unwrap_or(false) {
+		apply_frecency(&root, &mut scored);
+	}
 
-	scored.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+	scored.sort_by(|a, b| {
+		b.score
+			.cmp(&a.score)
+			.then_with(|| a.path.len().cmp(&b.path.len()))
+			.then_with(|| a.path.cmp(&b.path))
+	});
 	let total_matches = clamp_u32(scored.len() as u64);
 	let matches = scored.into_iter().take(max_results).collect();
 	Ok(FuzzyFindResult { matches, total_matches })
@@ -209,7 +447,7 @@ fn fuzzy_find_sync(config: FuzzyFindConfig, ct: task::CancelToken) -> Result<Fuz
 fn score_entries(
 	entries: &[fs_cache::GlobMatch],
 	query_lower: &str,
-	normalized_query: &str,
+	normalized_query: &[char],
 	ct: &task::CancelToken,
 ) -> Result<Vec<FuzzyFindMatch>> {
 	let mut scored = Vec::new();
@@ -225,12 +463,13 @@ fn score_entries(
 		} else {
 			entry.path.clone()
 		};
-		let score = score_fuzzy_path(&path, is_directory, query_lower, normalized_query);
-		if score == 0 {
+		let Some((score, positions)) =
+			score_fuzzy_path(&path, is_directory, query_lower, normalized_query)
+		else {
 			continue;
-		}
+		};
 
-		scored.push(FuzzyFindMatch { path, is_directory, score });
+		scored.push(FuzzyFindMatch { path, is_directory, score, positions });
 	}
 	Ok(scored)
 }
@@ -244,9 +483,69 @@ fn score_entries(
 /// Matching file and directory entries sorted by match quality.
 #[napi(js_name = "fuzzyFind")]
 pub fn fuzzy_find(options: FuzzyFindOptions<'_>) -> task::Async<FuzzyFindResult> {
-	let FuzzyFindOptions { query, path, hidden, gitignore, cache, max_results, timeout_ms, signal } =
-		options;
+	let FuzzyFindOptions {
+		query,
+		path,
+		hidden,
+		gitignore,
+		cache,
+		max_results,
+		frecency,
+		ignore_files,
+		overrides,
+		max_depth,
+		follow_symlinks,
+		timeout_ms,
+		signal,
+	} = options;
 	let ct = task::CancelToken::new(timeout_ms, signal);
-	let config = FuzzyFindConfig { query, path, hidden, gitignore, max_results, cache };
+	let config = FuzzyFindConfig {
+		query,
+		path,
+		hidden,
+		gitignore,
+		max_results,
+		cache,
+		frecency,
+		ignore_files,
+		overrides,
+		max_depth,
+		follow_symlinks,
+	};
 	task::blocking("fuzzy_find", ct, move |ct| fuzzy_find_sync(config, ct))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn score(path: &str, query: &str) -> Option<(u32, Vec<u32>)> {
+		let normalized: Vec<char> = normalize_fuzzy_text(query).chars().collect();
+		score_fuzzy_path(path, path.ends_with('/'), query, &normalized)
+	}
+
+	#[test]
+	fn test_prefers_boundaries_and_contiguity() {
+		let (real, positions) = score("src/utils/fsCache.ts", "fscache").unwrap();
+		let (fixture, _) = score("fixtures/super-long-chain-cache.ts", "fscache").unwrap();
+		assert!(real > fixture, "{real} <= {fixture}");
+		assert_eq!(positions, [10, 11, 12, 13, 14, 15, 16]);
+
+		assert!(score("src/main.rs", "xyz").is_none());
+		assert!(score("history-search.ts", "histsr").is_some());
+		assert!(
+			score("src/index.ts", "index").unwrap().0 > score("src/reindex.ts", "index").unwrap().0
+		);
+	}
+
+	#[test]
+	fn test_finds_optimal_alignment() {
+		// Greedy matching would take the first `a` and `b` ("xa" and "xb");
+		// the best alignment uses the word-initial "ab" of "abba".
+		let (_, positions) = score("xa_xb_abba", "ab").unwrap();
+		assert_eq!(positions, [6, 7]);
+
+		let (_, positions) = score("docs/\u{1f600}/ReadMe.md", "rm").unwrap();
+		assert_eq!(positions, [8, 12]);
+	}
+}
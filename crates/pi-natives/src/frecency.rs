@@ -0,0 +1,347 @@
+//! Frecency store for recently used files.
+//!
+//! Every recorded access adds a weight to the file's score, and scores decay
+//! exponentially with a half-life of [`HALF_LIFE_MS`], so files used often and
+//! recently rank first. The store lives in the project state directory at
+//! `<root>/.omp/frecency.tsv` as one `score\tlast_access\tcount\tpath` line
+//! per file, with paths relative to the root. Each entry keeps its score as of
+//! its last access; decay up to "now" is applied on read.
+//!
+//! The file is re-read on every operation and replaced atomically, so several
+//! processes working in the same project observe each other's accesses.
+
+use std::{
+	collections::HashMap,
+	fmt::Write as _,
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use parking_lot::Mutex;
+
+use crate::fs_cache;
+
+/// Directory holding per-project state, relative to the project root.
+const STATE_DIR: &str = ".omp";
+/// Store file name inside [`STATE_DIR`].
+const STORE_FILE: &str = "frecency.tsv";
+/// First line of the store file.
+const HEADER: &str = "# pi-natives frecency v1";
+/// Time for a score to decay to half its value (one week).
+const HALF_LIFE_MS: f64 = 7.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+/// Maximum number of files kept in the store.
+const MAX_ENTRIES: usize = 1000;
+/// Entries whose decayed score falls below this are dropped on save.
+const MIN_SCORE: f64 = 0.01;
+
+/// Serializes read-modify-write cycles within this process.
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Options for recording a file access.
+#[napi(object)]
+pub struct RecordFileAccessOptions {
+	/// Project root whose state directory holds the store.
+	pub root: String,
+	/// Accessed file, absolute or relative to `root`.
+	pub path: String,
+	/// Kind of access: `open` (default) or `edit`. Edits weigh twice as much.
+	pub kind: Option<String>,
+}
+
+/// Options for listing recently used files.
+#[napi(object)]
+pub struct ListRecentFilesOptions {
+	/// Project root whose state directory holds the store.
+	pub root:  String,
+	/// Maximum number of files to return (default: 20).
+	pub limit: Option<u32>,
+}
+
+/// A recently used file.
+#[napi(object)]
+pub struct RecentFile {
+	/// Path relative to the project root (uses `/` separators).
+	pub path:        String,
+	/// Decayed frecency score (higher is better).
+	pub score:       f64,
+	/// Time of the last access in milliseconds since the Unix epoch.
+	#[napi(js_name = "lastAccess")]
+	pub last_access: f64,
+	/// Number of recorded accesses.
+	pub count:       u32,
+}
+
+/// Kind of a recorded access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AccessKind {
+	Open,
+	Edit,
+}
+
+impl AccessKind {
+	fn parse(kind: Option<&str>) -> Result<Self> {
+		match kind {
+			None | Some("open") => Ok(Self::Open),
+			Some("edit") => Ok(Self::Edit),
+			Some(other) => Err(Error::from_reason(format!("Unknown access kind '{other}'"))),
+		}
+	}
+
+	const fn weight(self) -> f64 {
+		match self {
+			Self::Open => 1.0,
+			Self::Edit => 2.0,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Entry {
+	/// Score as of `last_access`.
+	score:       f64,
+	last_access: f64,
+	count:       u32,
+}
+
+impl Entry {
+	fn score_at(&self, now: f64) -> f64 {
+		let elapsed = (now - self.last_access).max(0.0);
+		self.score * 0.5f64.powf(elapsed / HALF_LIFE_MS)
+	}
+}
+
+fn now_ms() -> f64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0.0, |d| d.as_millis() as f64)
+}
+
+fn store_path(root: &Path) -> PathBuf {
+	root.join(STATE_DIR).join(STORE_FILE)
+}
+
+/// Reads the store for `root`. A missing or unreadable file is an empty
+/// store, and malformed lines are skipped.
+fn load(root: &Path) -> HashMap<String, Entry> {
+	let Ok(content) = std::fs::read_to_string(store_path(root)) else {
+		return HashMap::new();
+	};
+	content
+		.lines()
+		.filter(|line| !line.starts_with('#'))
+		.filter_map(|line| {
+			let mut fields = line.splitn(4, '\t');
+			let score = fields.next()?.parse().ok()?;
+			let last_access = fields.next()?.parse().ok()?;
+			let count = fields.next()?.parse().ok()?;
+			let path = fields.next().filter(|path| !path.is_empty())?;
+			Some((path.to_string(), Entry { score, last_access, count }))
+		})
+		.collect()
+}
+
+/// Writes the store for `root`, dropping decayed entries and keeping only the
+/// [`MAX_ENTRIES`] highest-scoring files.
+fn save(root: &Path, entries: HashMap<String, Entry>, now: f64) -> Result<()> {
+	let mut entries: Vec<(String, Entry)> = entries
+		.into_iter()
+		.filter(|(_, entry)| entry.score_at(now) >= MIN_SCORE)
+		.collect();
+	entries.sort_by(|a, b| b.1.score_at(now).total_cmp(&a.1.score_at(now)));
+	entries.truncate(MAX_ENTRIES);
+
+	let mut content = format!("{HEADER}\n");
+	for (path, entry) in &entries {
+		let _ = writeln!(content, "{}\t{}\t{}\t{path}", entry.score, entry.last_access, entry.count);
+	}
+
+	let path = store_path(root);
+	let write = || -> std::io::Result<()> {
+		std::fs::create_dir_all(root.join(STATE_DIR))?;
+		let tmp = path.with_extension(format!("tsv.{}.tmp", std::process::id()));
+		std::fs::write(&tmp, content)?;
+		std::fs::rename(&tmp, &path)
+	};
+	write().map_err(|err| {
+		Error::from_reason(format!("Failed to write frecency store {}: {err}", path.display()))
+	})
+}
+
+/// Resolves `path` against `root` and returns it relative to `root`.
+fn relative_to_root(root: &Path, path: &str) -> Result<String> {
+	let candidate = PathBuf::from(path);
+	let absolute = if candidate.is_absolute() {
+		candidate
+	} else {
+		root.join(candidate)
+	};
+	let target = std::fs::canonicalize(&absolute).unwrap_or(absolute);
+	let relative = target
+		.strip_prefix(root)
+		.map_err(|_| Error::from_reason(format!("Path is outside the project root: {path}")))?;
+	let relative = fs_cache::normalize_relative_path(root, relative);
+	if relative.is_empty() || relative.contains(['\t', '\n', '\r']) {
+		return Err(Error::from_reason(format!("Unsupported path for frecency: {path}")));
+	}
+	Ok(relative.into_owned())
+}
+
+/// Records an access to `path` at time `now`.
+fn record(root: &Path, path: &str, kind: AccessKind, now: f64) -> Result<()> {
+	let relative = relative_to_root(root, path)?;
+	let _guard = STORE_LOCK.lock();
+	let mut entries = load(root);
+	let entry = entries.entry(relative).or_insert(Entry {
+		score:       0.0,
+		last_access: now,
+		count:       0,
+	});
+	entry.score = entry.score_at(now) + kind.weight();
+	entry.last_access = entry.last_access.max(now);
+	entry.count = entry.count.saturating_add(1);
+	save(root, entries, now)
+}
+
+/// Returns the files of the store for `root` by descending score.
+fn recent(root: &Path, now: f64) -> Vec<RecentFile> {
+	let mut files: Vec<RecentFile> = load(root)
+		.into_iter()
+		.map(|(path, entry)| RecentFile {
+			score: entry.score_at(now),
+			last_access: entry.last_access,
+			count: entry.count,
+			path,
+		})
+		.collect();
+	files.sort_by(|a, b| {
+		b.score
+			.total_cmp(&a.score)
+			.then_with(|| b.last_access.total_cmp(&a.last_access))
+			.then_with(|| a.path.cmp(&b.path))
+	});
+	files
+}
+
+/// Frecency scores for files below `search_root`, keyed by path relative to
+/// `search_root`.
+///
+/// The store is taken from the nearest ancestor of `search_root` (inclusive)
+/// that has one, so searching a subdirectory still uses the project's history.
+pub fn scores_for(search_root: &Path) -> HashMap<String, f64> {
+	let Some(root) = search_root
+		.ancestors()
+		.find(|dir| store_path(dir).is_file())
+	else {
+		return HashMap::new();
+	};
+	let Ok(prefix) = search_root.strip_prefix(root) else {
+		return HashMap::new();
+	};
+	let prefix = fs_cache::normalize_relative_path(root, prefix);
+	let now = now_ms();
+	load(root)
+		.into_iter()
+		.filter_map(|(path, entry)| {
+			let relative = if prefix.is_empty() {
+				path
+			} else {
+				path
+					.strip_prefix(prefix.as_ref())?
+					.strip_prefix('/')?
+					.to_string()
+			};
+			Some((relative, entry.score_at(now)))
+		})
+		.collect()
+}
+
+/// Record that a file was opened or edited.
+///
+/// The access is added to the frecency store in the project state directory
+/// of `root`.
+#[napi(js_name = "recordFileAccess")]
+pub fn record_file_access(options: RecordFileAccessOptions) -> Result<()> {
+	let RecordFileAccessOptions { root, path, kind } = options;
+	let kind = AccessKind::parse(kind.as_deref())?;
+	let root = fs_cache::resolve_search_path(&root)?;
+	record(&root, &path, kind, now_ms())
+}
+
+/// List recently used files of a project, most frecent first.
+///
+/// Files that no longer exist are omitted.
+#[napi(js_name = "listRecentFiles")]
+pub fn list_recent_files(options: ListRecentFilesOptions) -> Result<Vec<RecentFile>> {
+	let root = fs_cache::resolve_search_path(&options.root)?;
+	let limit = options.limit.unwrap_or(20) as usize;
+	Ok(recent(&root, now_ms())
+		.into_iter()
+		.filter(|file| root.join(&file.path).exists())
+		.take(limit)
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TWO_WEEKS_MS: f64 = 2.0 * HALF_LIFE_MS;
+
+	fn temp_root(name: &str) -> PathBuf {
+		let root =
+			std::env::temp_dir().join(format!("pi-natives-frecency-{}-{name}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&root);
+		std::fs::create_dir_all(root.join("src")).unwrap();
+		std::fs::canonicalize(&root).unwrap()
+	}
+
+	#[test]
+	fn test_records_and_decays() {
+		let root = temp_root("decay");
+		let now = now_ms();
+		record(&root, "src/old.rs", AccessKind::Edit, now - TWO_WEEKS_MS).unwrap();
+		record(&root, "src/old.rs", AccessKind::Edit, now - TWO_WEEKS_MS).unwrap();
+		record(&root, &root.join("src/new.rs").to_string_lossy(), AccessKind::Open, now).unwrap();
+		assert!(record(&root, "/elsewhere/file.rs", AccessKind::Open, now).is_err());
+
+		let files = recent(&root, now);
+		let summary: Vec<(&str, u32)> = files
+			.iter()
+			.map(|file| (file.path.as_str(), file.count))
+			.collect();
+		assert_eq!(summary, [("src/new.rs", 1), ("src/old.rs", 2)]);
+		assert!((files[0].score - 1.0).abs() < 1e-9);
+		assert!((files[1].score - 1.0).abs() < 1e-9, "two weeks halve 4.0 twice");
+
+		record(&root, "src/old.rs", AccessKind::Open, now).unwrap();
+		assert_eq!(recent(&root, now)[0].path, "src/old.rs");
+
+		let scores = scores_for(&root.join("src"));
+		assert!((scores["old.rs"] - scores["new.rs"] - 1.0).abs() < 1e-6);
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+
+	#[test]
+	fn test_skips_malformed_lines_and_drops_stale_entries() {
+		let root = temp_root("malformed");
+		std::fs::create_dir_all(root.join(STATE_DIR)).unwrap();
+		std::fs::write(
+			store_path(&root),
+			format!(
+				"{HEADER}\n3\t0\t3\tsrc/a.rs\ngarbage\n1\tx\t1\tsrc/b.rs\n0.001\t0\t1\tsrc/c.rs\n"
+			),
+		)
+		.unwrap();
+		assert_eq!(load(&root).len(), 2);
+
+		record(&root, "src/d.rs", AccessKind::Open, 0.0).unwrap();
+		let paths: Vec<String> = recent(&root, 0.0)
+			.into_iter()
+			.map(|file| file.path)
+			.collect();
+		assert_eq!(paths, ["src/a.rs", "src/d.rs"]);
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+}
@@ -4,34 +4,51 @@
 //! - Global policy (no per-call TTL tuning)
 //! - Explicit invalidation for agent file mutations
 //! - Empty-result fast recheck to avoid stale negatives
+//! - Optional watcher mode that keeps entries current from filesystem events
+//!   instead of expiring them (see [`watch`])
+//! - Sorted entries (see [`tree`]), so invalidation re-lists only the affected
+//!   directory and subdirectory scans are served from a cached ancestor scan
+//! - Walker settings ([`ScanOptions`]: custom ignore files, override globs,
+//!   depth, symlinks) shared by glob, grep and fuzzyFind and keyed per scan
 //!
 //! # Policy Configuration (environment overrides)
 //! - `FS_SCAN_CACHE_TTL_MS`       – default `1000`
 //! - `FS_SCAN_EMPTY_RECHECK_MS`   – default `200`
 //! - `FS_SCAN_CACHE_MAX_ENTRIES`   – default `16`
+//! - `FS_SCAN_CACHE_WATCH`         – default `0` (set to `1` to watch scans)
 
 use std::{
 	borrow::Cow,
+	collections::BTreeSet,
 	path::{Path, PathBuf},
 	sync::LazyLock,
 	time::{Duration, Instant},
 };
 
 use dashmap::DashMap;
-use ignore::WalkBuilder;
-use napi::bindgen_prelude::*;
+use ignore::{
+	WalkBuilder,
+	overrides::{Override, OverrideBuilder},
+};
+use napi::{bindgen_prelude::*, threadsafe_function::ThreadsafeFunction};
 use napi_derive::napi;
 
-use crate::task;
+use crate::{content_index, task};
+
+mod tree;
+mod watch;
+
+use tree::ScanTree;
 
 // ═══════════════════════════════════════════════════════════════════════════
 // Public types (re-exported by glob for backward compatibility)
 // ═══════════════════════════════════════════════════════════════════════════
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[napi]
 pub enum FileType {
 	/// Regular file.
+	#[default]
 	File    = 1,
 	/// Directory.
 	Dir     = 2,
@@ -40,17 +57,77 @@ pub enum FileType {
 }
 
 /// A single filesystem entry from a directory scan.
-#[derive(Clone)]
+#[derive(Clone, Default)]
 #[napi(object)]
 pub struct GlobMatch {
 	/// Relative path from the search root, using forward slashes.
-	pub path:      String,
+	pub path:           String,
 	/// Resolved filesystem type for the match.
 	#[napi(js_name = "fileType")]
-	pub file_type: FileType,
+	pub file_type:      FileType,
 	/// Modification time in milliseconds since Unix epoch (from
 	/// `symlink_metadata`).
-	pub mtime:     Option<f64>,
+	pub mtime:          Option<f64>,
+	/// Size in bytes (files and symlinks only).
+	pub size:           Option<f64>,
+	/// Unix permission bits, including the file type bits.
+	pub mode:           Option<u32>,
+	/// Whether any execute bit is set (files only).
+	pub executable:     Option<bool>,
+	/// Raw target of a symlink, as stored in the link.
+	#[napi(js_name = "symlinkTarget")]
+	pub symlink_target: Option<String>,
+	/// Inode number; together with `dev`, identifies hardlinks.
+	pub ino:            Option<f64>,
+	/// Device number of the filesystem holding the entry.
+	pub dev:            Option<f64>,
+	/// Git working-tree status: "modified", "added", "renamed", "untracked",
+	/// "ignored", or "conflicted". Absent for clean entries and outside a
+	/// repository. Only computed by `glob` with `withMetadata`.
+	#[napi(js_name = "gitStatus")]
+	pub git_status:     Option<String>,
+}
+
+impl GlobMatch {
+	/// Drops the optional metadata, keeping path, type, and mtime.
+	pub fn without_metadata(self) -> Self {
+		Self { path: self.path, file_type: self.file_type, mtime: self.mtime, ..Self::default() }
+	}
+}
+
+/// Options for watching a directory for changes.
+#[napi(object)]
+pub struct FsWatchOptions {
+	/// Directory to watch.
+	pub path:      String,
+	/// Include hidden files (default: false).
+	pub hidden:    Option<bool>,
+	/// Respect .gitignore (default: true).
+	pub gitignore: Option<bool>,
+}
+
+/// A single change to a watched directory.
+#[derive(Clone)]
+#[napi(object)]
+pub struct FsChange {
+	/// Relative path from the watched root, using forward slashes.
+	pub path:      String,
+	/// Kind of change: `add`, `remove`, or `modify`.
+	pub kind:      String,
+	/// Filesystem type of the entry (before removal for `remove`).
+	#[napi(js_name = "fileType")]
+	pub file_type: FileType,
+}
+
+/// Changes to a watched directory, delivered in debounced batches.
+#[derive(Clone)]
+#[napi(object)]
+pub struct FsChangeEvent {
+	/// Changed entries, in the order they were applied.
+	pub changes: Vec<FsChange>,
+	/// Whether the batch comes from a full rescan (event overflow, ignore file
+	/// changes, or explicit invalidation).
+	pub rescan:  bool,
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -89,21 +166,114 @@ fn max_cache_entries() -> usize {
 	env_usize("FS_SCAN_CACHE_MAX_ENTRIES", DEFAULT_MAX_CACHE_ENTRIES)
 }
 
+/// Whether scans are watched and kept current instead of expiring.
+fn watch_enabled() -> bool {
+	env_u64("FS_SCAN_CACHE_WATCH", 0) != 0
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // Cache internals
 // ═══════════════════════════════════════════════════════════════════════════
 
+/// Ignore files with gitignore syntax honored in addition to `.gitignore`
+/// and `.ignore` unless a call names its own.
+const DEFAULT_IGNORE_FILES: [&str; 2] = [".ompignore", ".piignore"];
+
+/// Walker settings of a scan. Part of the cache key, so scans with different
+/// settings are cached separately.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ScanOptions {
+	/// Include hidden files.
+	pub include_hidden:  bool,
+	/// Honor `.gitignore`, `.ignore`, git excludes, and `ignore_files`.
+	pub use_gitignore:   bool,
+	/// Extra ignore filenames with gitignore syntax, honored like `.ignore`.
+	pub ignore_files:    Vec<String>,
+	/// Override globs as for ripgrep's `--glob`, rooted at the scan root and
+	/// taking precedence over ignore files: `!glob` excludes matches, and any
+	/// positive glob restricts files to those matching one.
+	pub overrides:       Vec<String>,
+	/// Maximum depth below the root (1 lists only direct children).
+	pub max_depth:       Option<usize>,
+	/// Follow symbolic links to directories.
+	pub follow_symlinks: bool,
+}
+
+impl ScanOptions {
+	/// Options with the default custom ignore files.
+	pub fn new(include_hidden: bool, use_gitignore: bool) -> Self {
+		Self {
+			include_hidden,
+			use_gitignore,
+			ignore_files: DEFAULT_IGNORE_FILES.map(String::from).to_vec(),
+			..Self::default()
+		}
+	}
+
+	/// Applies the per-call walker options shared by glob, grep and fuzzyFind.
+	///
+	/// # Errors
+	/// Returns an error when an override glob is invalid.
+	pub fn with_walk_options(
+		mut self,
+		ignore_files: Option<Vec<String>>,
+		overrides: Option<Vec<String>>,
+		max_depth: Option<u32>,
+		follow_symlinks: Option<bool>,
+	) -> Result<Self> {
+		if let Some(ignore_files) = ignore_files {
+			self.ignore_files = ignore_files;
+		}
+		if let Some(overrides) = overrides {
+			build_overrides(Path::new(""), &overrides)?;
+			self.overrides = overrides;
+		}
+		self.max_depth = max_depth.map(|depth| depth as usize);
+		self.follow_symlinks = follow_symlinks.unwrap_or(false);
+		Ok(self)
+	}
+
+	/// Whether the scan of a subdirectory equals the matching part of a scan
+	/// of an ancestor, so that subtrees can be served from and refreshed within
+	/// an ancestor scan. Overrides are rooted at the scan root, depth counts
+	/// from it, and followed links are not tracked as directories.
+	const fn allows_subtrees(&self) -> bool {
+		self.overrides.is_empty() && self.max_depth.is_none() && !self.follow_symlinks
+	}
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 struct CacheKey {
-	root:           PathBuf,
-	include_hidden: bool,
-	use_gitignore:  bool,
+	root:    PathBuf,
+	options: ScanOptions,
 }
 
 #[derive(Clone)]
 struct CacheEntry {
+	/// Time of the scan, or of the last watcher update.
 	created_at: Instant,
-	entries:    Vec<GlobMatch>,
+	tree:       ScanTree,
+	/// Maintained by a watcher; exempt from TTL expiry.
+	watched:    bool,
+	/// Invalidated paths (relative to the root) not yet refreshed in `tree`.
+	pending:    Vec<PathBuf>,
+}
+
+impl CacheEntry {
+	const fn new(created_at: Instant, tree: ScanTree, watched: bool) -> Self {
+		Self { created_at, tree, watched, pending: Vec::new() }
+	}
+
+	fn is_fresh(&self, now: Instant, ttl: Duration) -> bool {
+		self.watched || now.duration_since(self.created_at) < ttl
+	}
+
+	/// Refreshes the paths invalidated since the entry was last served.
+	fn apply_pending(&mut self, key: &CacheKey) {
+		for relative in std::mem::take(&mut self.pending) {
+			refresh_subtree(key, &mut self.tree, &relative);
+		}
+	}
 }
 
 static FS_CACHE: LazyLock<DashMap<CacheKey, CacheEntry>> = LazyLock::new(DashMap::new);
@@ -125,6 +295,7 @@ fn evict_oldest() {
 			.map(|entry| entry.key().clone())
 	{
 		FS_CACHE.remove(&oldest_key);
+		watch::release(&oldest_key);
 	}
 }
 
@@ -186,37 +357,58 @@ pub fn should_skip_path(path: &Path, mentions_node_modules: bool) -> bool {
 	false
 }
 
-pub fn classify_file_type(path: &Path) -> Option<(FileType, Option<f64>)> {
+/// Reads the `symlink_metadata` of `path` into a scan entry for `relative`.
+/// Returns `None` when the entry vanished since it was listed.
+pub fn read_entry(path: &Path, relative: String) -> Option<GlobMatch> {
 	let metadata = std::fs::symlink_metadata(path).ok()?;
 	let file_type = metadata.file_type();
-	let mtime_ms = metadata
+	let mtime = metadata
 		.modified()
 		.ok()
 		.and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
 		.map(|d| d.as_millis() as f64);
-	if file_type.is_symlink() {
-		Some((FileType::Symlink, mtime_ms))
+	let (file_type, symlink_target) = if file_type.is_symlink() {
+		let target = std::fs::read_link(path).ok();
+		(FileType::Symlink, target.map(|target| target.to_string_lossy().into_owned()))
 	} else if file_type.is_dir() {
-		Some((FileType::Dir, mtime_ms))
+		(FileType::Dir, None)
 	} else {
-		Some((FileType::File, mtime_ms))
+		(FileType::File, None)
+	};
+	let mut entry = GlobMatch {
+		path: relative,
+		file_type,
+		mtime,
+		size: (file_type != FileType::Dir).then_some(metadata.len() as f64),
+		symlink_target,
+		..GlobMatch::default()
+	};
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::MetadataExt;
+		entry.mode = Some(metadata.mode());
+		entry.executable = (file_type == FileType::File).then_some(metadata.mode() & 0o111 != 0);
+		entry.ino = Some(metadata.ino() as f64);
+		entry.dev = Some(metadata.dev() as f64);
 	}
+	Some(entry)
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
 // Walker + collection
 // ═══════════════════════════════════════════════════════════════════════════
 
-/// Builds a deterministic filesystem walker configured for visibility and
-/// ignore rules.
-pub fn build_walker(root: &Path, include_hidden: bool, use_gitignore: bool) -> WalkBuilder {
+/// Builds a deterministic filesystem walker configured for visibility,
+/// ignore rules, overrides, depth, and symlink handling.
+pub fn build_walker(root: &Path, options: &ScanOptions) -> WalkBuilder {
 	let mut builder = WalkBuilder::new(root);
 	builder
-		.hidden(!include_hidden)
-		.follow_links(false)
+		.hidden(!options.include_hidden)
+		.follow_links(options.follow_symlinks)
+		.max_depth(options.max_depth)
 		.sort_by_file_path(|a, b| a.cmp(b));
 
-	if use_gitignore {
+	if options.use_gitignore {
 		// Honor repository and global ignore files for repo-like behavior.
 		builder
 			.git_ignore(true)
@@ -224,6 +416,9 @@ pub fn build_walker(root: &Path, include_hidden: bool, use_gitignore: bool) -> W
 			.git_global(true)
 			.ignore(true)
 			.parents(true);
+		for name in &options.ignore_files {
+			builder.add_custom_ignore_filename(name);
+		}
 	} else {
 		// Disable all ignore sources for exhaustive filesystem traversal.
 		builder
@@ -233,8 +428,27 @@ pub fn build_walker(root: &Path, include_hidden: bool, use_gitignore: bool) -> W
 			.ignore(false)
 			.parents(false);
 	}
+	// Invalid globs are rejected by `ScanOptions::with_walk_options`.
+	if !options.overrides.is_empty()
+		&& let Ok(overrides) = build_overrides(root, &options.overrides)
+	{
+		builder.overrides(overrides);
+	}
+
+	builder
+}
 
+/// Compiles override globs rooted at `root`.
+fn build_overrides(root: &Path, globs: &[String]) -> Result<Override> {
+	let mut builder = OverrideBuilder::new(root);
+	for glob in globs {
+		builder
+			.add(glob)
+			.map_err(|err| Error::from_reason(format!("Invalid override glob: {err}")))?;
+	}
 	builder
+		.build()
+		.map_err(|err| Error::from_reason(format!("Failed to build overrides: {err}")))
 }
 
 /// Scans filesystem entries and records normalized relative paths with file
@@ -244,11 +458,10 @@ pub fn build_walker(root: &Path, include_hidden: bool, use_gitignore: bool) -> W
 /// exclusion.
 fn collect_entries(
 	root: &Path,
-	include_hidden: bool,
-	use_gitignore: bool,
+	options: &ScanOptions,
 	ct: &task::CancelToken,
 ) -> Result<Vec<GlobMatch>> {
-	let builder = build_walker(root, include_hidden, use_gitignore);
+	let builder = build_walker(root, options);
 	let mut entries = Vec::new();
 
 	for entry in builder.build() {
@@ -267,11 +480,11 @@ fn collect_entries(
 			continue;
 		}
 
-		let Some((file_type, mtime)) = classify_file_type(path) else {
+		let Some(entry) = read_entry(path, relative.into_owned()) else {
 			continue;
 		};
 
-		entries.push(GlobMatch { path: relative.into_owned(), file_type, mtime });
+		entries.push(entry);
 	}
 
 	Ok(entries)
@@ -289,38 +502,78 @@ fn collect_entries(
 /// empty.
 pub fn get_or_scan(
 	root: &Path,
-	include_hidden: bool,
-	use_gitignore: bool,
+	options: &ScanOptions,
 	ct: &task::CancelToken,
 ) -> Result<ScanResult> {
 	let ttl = cache_ttl_ms();
 	if ttl == 0 {
 		// Caching disabled – always scan fresh.
-		let entries = collect_entries(root, include_hidden, use_gitignore, ct)?;
+		let entries = collect_entries(root, options, ct)?;
 		return Ok(ScanResult { entries, cache_age_ms: 0 });
 	}
 
-	let key = CacheKey { root: root.to_path_buf(), include_hidden, use_gitignore };
+	let key = CacheKey { root: root.to_path_buf(), options: options.clone() };
+	let ttl = Duration::from_millis(ttl);
 
+	watch::flush(&key);
 	let now = Instant::now();
-	if let Some(entry) = FS_CACHE.get(&key) {
-		let age = now.duration_since(entry.created_at);
-		if age < Duration::from_millis(ttl) {
+	if let Some(mut entry) = FS_CACHE.get_mut(&key) {
+		if entry.is_fresh(now, ttl) {
+			entry.apply_pending(&key);
 			return Ok(ScanResult {
-				entries:      entry.entries.clone(),
-				cache_age_ms: age.as_millis() as u64,
+				entries:      entry.tree.to_vec(),
+				cache_age_ms: now.duration_since(entry.created_at).as_millis() as u64,
 			});
 		}
 		drop(entry);
 		FS_CACHE.remove(&key);
 	}
+	if let Some(scan) = scan_from_ancestor(&key, now, ttl) {
+		return Ok(scan);
+	}
 
-	let entries = collect_entries(root, include_hidden, use_gitignore, ct)?;
-	FS_CACHE.insert(key, CacheEntry { created_at: now, entries: entries.clone() });
+	let entries = collect_entries(root, options, ct)?;
+	FS_CACHE.insert(key.clone(), CacheEntry::new(now, ScanTree::new(entries.clone()), false));
 	evict_oldest();
+	if watch_enabled() {
+		// Without a watcher (e.g. when inotify watches run out) the entry simply
+		// expires by TTL.
+		let _ = watch::start(&key, entries.clone());
+	}
 	Ok(ScanResult { entries, cache_age_ms: 0 })
 }
 
+/// Serves a scan of `key.root` from the nearest fresh cached scan of an
+/// ancestor directory with the same settings.
+///
+/// The ancestor's entries below the subdirectory are what a direct scan would
+/// produce, since ignore files of parent directories apply to both. A
+/// subdirectory that the ancestor scan excludes (hidden or ignored) is not
+/// served, because a direct scan of it would still list its contents.
+fn scan_from_ancestor(key: &CacheKey, now: Instant, ttl: Duration) -> Option<ScanResult> {
+	let ancestor_key = FS_CACHE
+		.iter()
+		.filter(|entry| {
+			let candidate = entry.key();
+			candidate.options == key.options
+				&& key.options.allows_subtrees()
+				&& candidate.root != key.root
+				&& key.root.starts_with(&candidate.root)
+				&& entry.value().is_fresh(now, ttl)
+		})
+		.max_by_key(|entry| entry.key().root.components().count())
+		.map(|entry| entry.key().clone())?;
+	watch::flush(&ancestor_key);
+	let mut ancestor = FS_CACHE.get_mut(&ancestor_key)?;
+	ancestor.apply_pending(&ancestor_key);
+	let relative = key.root.strip_prefix(&ancestor_key.root).ok()?;
+	let entries = ancestor.tree.subtree_entries(relative)?;
+	Some(ScanResult {
+		entries,
+		cache_age_ms: now.duration_since(ancestor.created_at).as_millis() as u64,
+	})
+}
+
 /// Force a fresh scan, replacing any existing cache entry.
 ///
 /// Use when a cached query produced zero matches and the cache was old enough
@@ -328,18 +581,20 @@ pub fn get_or_scan(
 /// returned without repopulating the cache.
 pub fn force_rescan(
 	root: &Path,
-	include_hidden: bool,
-	use_gitignore: bool,
+	options: &ScanOptions,
 	store: bool,
 	ct: &task::CancelToken,
 ) -> Result<Vec<GlobMatch>> {
-	let key = CacheKey { root: root.to_path_buf(), include_hidden, use_gitignore };
+	let key = CacheKey { root: root.to_path_buf(), options: options.clone() };
+	if let Some(entries) = watch::rescan(&key) {
+		return Ok(entries);
+	}
 	FS_CACHE.remove(&key);
 
-	let entries = collect_entries(root, include_hidden, use_gitignore, ct)?;
+	let entries = collect_entries(root, options, ct)?;
 	if store {
 		let now = Instant::now();
-		FS_CACHE.insert(key, CacheEntry { created_at: now, entries: entries.clone() });
+		FS_CACHE.insert(key, CacheEntry::new(now, ScanTree::new(entries.clone()), false));
 		evict_oldest();
 	}
 	Ok(entries)
@@ -351,28 +606,67 @@ pub fn force_rescan(
 
 /// Invalidate cache entries whose root contains `target`.
 ///
-/// Removes any cache entry whose root is a prefix of (or equal to) `target`,
-/// because a file mutation under that root makes the scan stale.
+/// Every cache entry whose root is a prefix of `target` is marked for an
+/// in-place refresh the next time it is served: the directory containing
+/// `target` is re-listed, and if `target` is a directory its subtree is
+/// rescanned. Entries rooted at `target`, entries whose ignore rules change
+/// with `target` (a `.gitignore`, `.ignore`, or custom ignore file), and
+/// entries that cannot be refreshed per directory (see [`ScanOptions`]), are
+/// dropped. Records for `target` are marked stale in grep content indexes as
+/// well. No directory is listed here, so the call stays cheap.
 pub fn invalidate_path(target: &Path) {
-	let keys_to_remove: Vec<CacheKey> = FS_CACHE
+	let affected: Vec<CacheKey> = FS_CACHE
 		.iter()
-		.filter(|entry| target.starts_with(&entry.key().root))
+		.filter(|entry| !entry.value().watched && target.starts_with(&entry.key().root))
 		.map(|entry| entry.key().clone())
 		.collect();
-	for key in keys_to_remove {
-		FS_CACHE.remove(&key);
+	for key in affected {
+		let Ok(relative) = target.strip_prefix(&key.root) else {
+			continue;
+		};
+		if relative.as_os_str().is_empty()
+			|| !key.options.allows_subtrees()
+			|| tree::is_ignore_file(&key.options, relative)
+		{
+			FS_CACHE.remove(&key);
+		} else if let Some(mut entry) = FS_CACHE.get_mut(&key) {
+			entry.pending.push(relative.to_path_buf());
+		}
+	}
+	watch::refresh_path(target);
+	content_index::invalidate_path(target);
+}
+
+/// Re-lists the directory containing `relative` and, if `relative` was a
+/// directory of the scan, rescans its subtree with a single walk.
+fn refresh_subtree(key: &CacheKey, tree: &mut ScanTree, relative: &Path) {
+	let ct = task::CancelToken::default();
+	let rescan =
+		|dir: &Path| collect_entries(&key.root.join(dir), &key.options, &ct).unwrap_or_default();
+	let Some(parent) = relative.parent() else {
+		return;
+	};
+	let was_dir = tree.is_dir(relative);
+	tree.refresh(key, BTreeSet::from([parent.to_path_buf()]), &mut |_, _| {});
+	if was_dir && tree.is_dir(relative) {
+		tree.replace_subtree(relative, rescan(relative));
 	}
 }
 
-/// Clear the entire scan cache.
+/// Clear the entire scan cache and delete all grep content indexes. Watched
+/// entries are rescanned rather than dropped.
 pub fn invalidate_all() {
-	FS_CACHE.clear();
+	FS_CACHE.retain(|_, entry| entry.watched);
+	watch::rescan_all();
+	content_index::invalidate_all();
 }
 
 /// Invalidate the filesystem scan cache.
 ///
-/// When called with a path, removes entries for roots containing that path.
-/// When called without a path, clears the entire cache.
+/// When called with a path, marks the part of each cached scan that contains
+/// that path (its directory, or its subtree for a directory) for a refresh on
+/// the next scan, and drops scans rooted at it. When called without a path,
+/// clears the entire cache. Grep content indexes are invalidated the same way.
 ///
 /// Intended to be called after agent file mutations (write, edit, rename,
 /// delete).
@@ -402,3 +696,140 @@ pub fn invalidate_fs_scan_cache(path: Option<String>) {
 		None => invalidate_all(),
 	}
 }
+
+/// Subscription to changes of a watched directory, returned by `watchFs`.
+///
+/// Keeps the directory's scan cache entry current while open.
+#[napi]
+pub struct FsWatcher {
+	key: CacheKey,
+	id:  u32,
+}
+
+#[napi]
+impl FsWatcher {
+	/// Stop delivering changes. Idempotent.
+	#[napi]
+	pub fn close(&self) {
+		watch::unsubscribe(&self.key, self.id);
+	}
+}
+
+impl Drop for FsWatcher {
+	fn drop(&mut self) {
+		watch::unsubscribe(&self.key, self.id);
+	}
+}
+
+/// Watch a directory for changes.
+///
+/// Scans the directory (sharing the scan cache with glob, grep and
+/// fuzzyFind) and calls `on_change` with add/remove/modify deltas as files
+/// change, following the same hidden and gitignore rules as the scan. While
+/// the watcher is open, the cache entry is kept current instead of expiring.
+#[napi(js_name = "watchFs")]
+pub fn watch_fs(
+	options: FsWatchOptions,
+	#[napi(ts_arg_type = "(event: FsChangeEvent) => void")] on_change: ThreadsafeFunction<
+		FsChangeEvent,
+	>,
+) -> task::Async<FsWatcher> {
+	let FsWatchOptions { path, hidden, gitignore } = options;
+	task::blocking("watch_fs", (), move |ct| {
+		let root = resolve_search_path(&path)?;
+		let key = CacheKey {
+			root,
+			options: ScanOptions::new(hidden.unwrap_or(false), gitignore.unwrap_or(true)),
+		};
+		let id = watch::subscribe(&key, on_change, &ct)?;
+		Ok(FsWatcher { key, id })
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn paths(entries: &[GlobMatch]) -> Vec<&str> {
+		entries.iter().map(|entry| entry.path.as_str()).collect()
+	}
+
+	#[test]
+	fn test_serves_subdirectories_and_refreshes_in_place() {
+		let root = std::env::temp_dir().join(format!("pi-natives-fs-cache-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&root);
+		std::fs::create_dir_all(root.join("pkg/src")).unwrap();
+		std::fs::write(root.join("pkg/src/lib.rs"), "").unwrap();
+		std::fs::write(root.join("top.rs"), "").unwrap();
+		let root = std::fs::canonicalize(&root).unwrap();
+		let ct = task::CancelToken::default();
+		let options = ScanOptions::new(false, true);
+		let key = |root: &Path| CacheKey { root: root.to_path_buf(), options: options.clone() };
+
+		get_or_scan(&root, &options, &ct).unwrap();
+		std::fs::write(root.join("pkg/src/main.rs"), "").unwrap();
+		let pkg = get_or_scan(&root.join("pkg"), &options, &ct).unwrap();
+		assert_eq!(paths(&pkg.entries), ["src", "src/lib.rs"], "served from the root scan");
+		assert!(!FS_CACHE.contains_key(&key(&root.join("pkg"))));
+
+		invalidate_path(&root.join("pkg/src/main.rs"));
+		let pkg = get_or_scan(&root.join("pkg"), &options, &ct).unwrap();
+		assert_eq!(paths(&pkg.entries), ["src", "src/lib.rs", "src/main.rs"]);
+
+		std::fs::remove_dir_all(root.join("pkg/src")).unwrap();
+		std::fs::create_dir_all(root.join("pkg/src/nested")).unwrap();
+		std::fs::write(root.join("pkg/src/nested/mod.rs"), "").unwrap();
+		invalidate_path(&root.join("pkg/src"));
+		let scan = get_or_scan(&root, &options, &ct).unwrap();
+		assert_eq!(paths(&scan.entries), [
+			"pkg",
+			"pkg/src",
+			"pkg/src/nested",
+			"pkg/src/nested/mod.rs",
+			"top.rs"
+		]);
+
+		invalidate_path(&root.join("pkg"));
+		assert_eq!(FS_CACHE.get(&key(&root)).unwrap().pending, [Path::new("pkg")]);
+		invalidate_path(&root);
+		assert!(!FS_CACHE.contains_key(&key(&root)), "root targets drop the entry");
+
+		FS_CACHE.remove(&key(&root));
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+	#[test]
+	fn test_walk_options() {
+		let root = std::env::temp_dir().join(format!("pi-natives-walk-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&root);
+		std::fs::create_dir_all(root.join("dist/assets")).unwrap();
+		std::fs::create_dir_all(root.join("src")).unwrap();
+		std::fs::write(root.join(".ignore"), "dist/\n").unwrap();
+		std::fs::write(root.join(".ompignore"), "*.snap\n").unwrap();
+		std::fs::write(root.join("dist/assets/app.js"), "").unwrap();
+		std::fs::write(root.join("src/lib.rs"), "").unwrap();
+		std::fs::write(root.join("src/lib.snap"), "").unwrap();
+		let root = std::fs::canonicalize(&root).unwrap();
+		let ct = task::CancelToken::default();
+		let scan = |options: &ScanOptions| collect_entries(&root, options, &ct).unwrap();
+
+		let defaults = ScanOptions::new(false, true);
+		assert_eq!(paths(&scan(&defaults)), ["src", "src/lib.rs"]);
+		let custom = defaults
+			.clone()
+			.with_walk_options(Some(Vec::new()), None, Some(1), None)
+			.unwrap();
+		assert_eq!(paths(&scan(&custom)), ["src"]);
+		let overrides = defaults
+			.clone()
+			.with_walk_options(None, Some(vec!["dist".into(), "dist/**".into()]), None, None)
+			.unwrap();
+		assert_eq!(paths(&scan(&overrides)), ["dist", "dist/assets", "dist/assets/app.js", "src"]);
+		assert!(!overrides.allows_subtrees());
+		assert!(
+			defaults
+				.with_walk_options(None, Some(vec!["[".into()]), None, None)
+				.is_err()
+		);
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+}
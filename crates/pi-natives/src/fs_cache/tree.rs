@@ -0,0 +1,331 @@
+//! Sorted scan entries with directory-granular refresh.
+//!
+//! Entries are keyed by relative path. `Path` ordering compares components,
+//! which matches the depth-first order of the sorted walker, so the subtree
+//! of any directory is one contiguous range. That lets a scan of a
+//! subdirectory be cut out of its parent's entries, and a changed directory
+//! be re-listed and spliced back in without walking the rest of the tree.
+
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	ops::Bound,
+	path::{Path, PathBuf},
+};
+
+use super::{
+	CacheKey, FileType, FsChange, GlobMatch, ScanOptions, build_walker, normalize_relative_path,
+	read_entry, should_skip_path,
+};
+
+/// Files whose modification changes the ignore rules of a whole scan, besides
+/// the custom ignore files of its options.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Whether changing `path` can change which entries a scan with `options`
+/// includes beyond its own directory.
+pub fn is_ignore_file(options: &ScanOptions, path: &Path) -> bool {
+	options.use_gitignore
+		&& path.file_name().is_some_and(|name| {
+			IGNORE_FILES.iter().any(|file| name == *file)
+				|| options
+					.ignore_files
+					.iter()
+					.any(|file| name == file.as_str())
+		})
+}
+
+/// Directories to re-list after `relative` (a path below the scan root)
+/// changed: its parent, and the path itself in case it is a directory.
+pub fn dirty_dirs_for(relative: &Path) -> BTreeSet<PathBuf> {
+	let parent = relative.parent().unwrap_or(relative);
+	BTreeSet::from([parent.to_path_buf(), relative.to_path_buf()])
+}
+
+/// Scan entries of one cache key, in walker order.
+#[derive(Clone, Default)]
+pub struct ScanTree(BTreeMap<PathBuf, GlobMatch>);
+
+impl ScanTree {
+	pub fn new(entries: Vec<GlobMatch>) -> Self {
+		Self(
+			entries
+				.into_iter()
+				.map(|entry| (PathBuf::from(&entry.path), entry))
+				.collect(),
+		)
+	}
+
+	/// All entries in walker order.
+	pub fn to_vec(&self) -> Vec<GlobMatch> {
+		self.0.values().cloned().collect()
+	}
+
+	/// Whether `dir` is the root (empty path) or a directory of the scan.
+	pub fn is_dir(&self, dir: &Path) -> bool {
+		dir.as_os_str().is_empty()
+			|| self
+				.0
+				.get(dir)
+				.is_some_and(|entry| entry.file_type == FileType::Dir)
+	}
+
+	/// Relative paths of all directories.
+	pub fn dirs(&self) -> impl Iterator<Item = &Path> {
+		self
+			.0
+			.iter()
+			.filter(|(_, entry)| entry.file_type == FileType::Dir)
+			.map(|(path, _)| path.as_path())
+	}
+
+	/// Entries below `dir`, with paths relative to `dir`, or `None` when `dir`
+	/// is not a directory of the scan (e.g. because it is ignored).
+	pub fn subtree_entries(&self, dir: &Path) -> Option<Vec<GlobMatch>> {
+		if dir.as_os_str().is_empty() {
+			return Some(self.to_vec());
+		}
+		let prefix = self
+			.0
+			.get(dir)
+			.filter(|entry| entry.file_type == FileType::Dir)?;
+		let strip = prefix.path.len() + 1;
+		Some(
+			self
+				.subtree(dir)
+				.map(|path| {
+					let entry = &self.0[path];
+					GlobMatch { path: entry.path[strip..].to_string(), ..entry.clone() }
+				})
+				.collect(),
+		)
+	}
+
+	/// Re-lists the `dirty` directories (relative to the root of `key`) and
+	/// every directory they gained, returning the resulting changes.
+	///
+	/// `on_dir` is called with each added directory before it is listed and
+	/// with each removed one, so watchers can follow the tree without missing
+	/// entries created in between.
+	pub fn refresh(
+		&mut self,
+		key: &CacheKey,
+		dirty: BTreeSet<PathBuf>,
+		on_dir: &mut dyn FnMut(&Path, bool),
+	) -> Vec<FsChange> {
+		let mut changes = Vec::new();
+		let mut pending: Vec<PathBuf> = dirty.into_iter().collect();
+		while let Some(dir) = pending.pop() {
+			if !self.is_dir(&dir) {
+				// Ignored, hidden, or already removed.
+				continue;
+			}
+			for added in self.reconcile_dir(key, &dir, &mut changes, on_dir) {
+				on_dir(&added, true);
+				pending.push(added);
+			}
+		}
+		changes
+	}
+
+	/// Replaces the entries with `fresh` ones, returning the difference.
+	pub fn replace(&mut self, fresh: Self) -> Vec<FsChange> {
+		let mut changes = Vec::new();
+		for (path, entry) in &self.0 {
+			if !fresh.0.contains_key(path) {
+				changes.push(change(entry, "remove"));
+			}
+		}
+		for (path, entry) in &fresh.0 {
+			match self.0.get(path) {
+				None => changes.push(change(entry, "add")),
+				Some(previous) if previous.file_type != entry.file_type => {
+					changes.push(change(previous, "remove"));
+					changes.push(change(entry, "add"));
+				},
+				Some(previous) if previous.mtime != entry.mtime && entry.file_type != FileType::Dir => {
+					changes.push(change(entry, "modify"));
+				},
+				Some(_) => {},
+			}
+		}
+		*self = fresh;
+		changes
+	}
+
+	/// Replaces the subtree of the directory `dir` with `entries` from a fresh
+	/// scan of it (with paths relative to `dir`), returning the difference.
+	pub fn replace_subtree(&mut self, dir: &Path, entries: Vec<GlobMatch>) -> Vec<FsChange> {
+		if dir.as_os_str().is_empty() {
+			return self.replace(Self::new(entries));
+		}
+		let Some(prefix) = self.0.get(dir).map(|entry| entry.path.clone()) else {
+			return Vec::new();
+		};
+		let fresh = Self::new(
+			entries
+				.into_iter()
+				.map(|entry| GlobMatch { path: format!("{prefix}/{}", entry.path), ..entry })
+				.collect(),
+		);
+		let stale: Vec<PathBuf> = self.subtree(dir).cloned().collect();
+		let mut current = Self(
+			stale
+				.iter()
+				.filter_map(|path| self.0.remove_entry(path))
+				.collect(),
+		);
+		let changes = current.replace(fresh);
+		self.0.append(&mut current.0);
+		changes
+	}
+
+	/// Brings the direct children of `dir` in line with the filesystem,
+	/// returning the directories that were added.
+	fn reconcile_dir(
+		&mut self,
+		key: &CacheKey,
+		dir: &Path,
+		changes: &mut Vec<FsChange>,
+		on_dir: &mut dyn FnMut(&Path, bool),
+	) -> Vec<PathBuf> {
+		let listed = list_dir(key, &key.root.join(dir));
+		let stale: Vec<PathBuf> = self
+			.subtree(dir)
+			.filter(|path| path.parent() == Some(dir) && !listed.contains_key(*path))
+			.cloned()
+			.collect();
+		for path in stale {
+			self.remove_subtree(&path, changes, on_dir);
+		}
+
+		let mut added_dirs = Vec::new();
+		for (path, entry) in listed {
+			match self.0.get(&path) {
+				Some(previous) if previous.file_type == entry.file_type => {
+					if previous.mtime != entry.mtime {
+						if entry.file_type != FileType::Dir {
+							changes.push(change(&entry, "modify"));
+						}
+						self.0.insert(path, entry);
+					}
+					continue;
+				},
+				Some(_) => self.remove_subtree(&path, changes, on_dir),
+				None => {},
+			}
+			changes.push(change(&entry, "add"));
+			if entry.file_type == FileType::Dir {
+				added_dirs.push(path.clone());
+			}
+			self.0.insert(path, entry);
+		}
+		added_dirs
+	}
+
+	/// Paths strictly below `dir`.
+	fn subtree<'a>(&'a self, dir: &'a Path) -> impl Iterator<Item = &'a PathBuf> {
+		self
+			.0
+			.range::<Path, _>((Bound::Excluded(dir), Bound::Unbounded))
+			.map(|(path, _)| path)
+			.take_while(move |path| path.starts_with(dir))
+	}
+
+	fn remove_subtree(
+		&mut self,
+		path: &Path,
+		changes: &mut Vec<FsChange>,
+		on_dir: &mut dyn FnMut(&Path, bool),
+	) {
+		let mut removed: Vec<PathBuf> = self.subtree(path).cloned().collect();
+		removed.push(path.to_path_buf());
+		for path in removed {
+			if let Some(entry) = self.0.remove(&path) {
+				if entry.file_type == FileType::Dir {
+					on_dir(&path, false);
+				}
+				changes.push(change(&entry, "remove"));
+			}
+		}
+	}
+}
+
+/// Lists the direct children of `dir` that a full scan of `key` would
+/// include. A missing directory has no children.
+fn list_dir(key: &CacheKey, dir: &Path) -> BTreeMap<PathBuf, GlobMatch> {
+	let mut builder = build_walker(dir, &key.options);
+	builder.max_depth(Some(1));
+	let mut children = BTreeMap::new();
+	for entry in builder.build().flatten() {
+		let path = entry.path();
+		if entry.depth() == 0 || should_skip_path(path, true) {
+			continue;
+		}
+		let relative = normalize_relative_path(&key.root, path).into_owned();
+		let Some(entry) = read_entry(path, relative) else {
+			continue;
+		};
+		children.insert(PathBuf::from(&entry.path), entry);
+	}
+	children
+}
+
+fn change(entry: &GlobMatch, kind: &str) -> FsChange {
+	FsChange {
+		path:      entry.path.clone(),
+		kind:      kind.to_string(),
+		file_type: entry.file_type,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{fs_cache::collect_entries, task};
+
+	fn paths(entries: &[GlobMatch]) -> Vec<&str> {
+		entries.iter().map(|entry| entry.path.as_str()).collect()
+	}
+
+	#[test]
+	fn test_refresh_and_subtree() {
+		let root = std::env::temp_dir().join(format!("pi-natives-tree-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&root);
+		std::fs::create_dir_all(root.join("src/a")).unwrap();
+		std::fs::write(root.join("src/a/x.rs"), "").unwrap();
+		std::fs::write(root.join("src-b.rs"), "").unwrap();
+		let root = std::fs::canonicalize(&root).unwrap();
+		let key = CacheKey { root: root.clone(), options: ScanOptions::new(false, true) };
+		let ct = task::CancelToken::default();
+		let mut tree = ScanTree::new(collect_entries(&root, &key.options, &ct).unwrap());
+		assert_eq!(paths(&tree.to_vec()), ["src", "src/a", "src/a/x.rs", "src-b.rs"]);
+		assert_eq!(paths(&tree.subtree_entries(Path::new("src")).unwrap()), ["a", "a/x.rs"]);
+		assert!(tree.subtree_entries(Path::new("src-b.rs")).is_none());
+
+		std::fs::remove_dir_all(root.join("src/a")).unwrap();
+		std::fs::create_dir_all(root.join("src/c/d")).unwrap();
+		std::fs::write(root.join("src/c/d/y.rs"), "").unwrap();
+		let mut dirs = Vec::new();
+		let changes = tree.refresh(&key, dirty_dirs_for(Path::new("src/c")), &mut |dir, added| {
+			dirs.push((dir.to_path_buf(), added));
+		});
+		let summary: Vec<(&str, &str)> = changes
+			.iter()
+			.map(|change| (change.kind.as_str(), change.path.as_str()))
+			.collect();
+		assert_eq!(summary, [
+			("remove", "src/a/x.rs"),
+			("remove", "src/a"),
+			("add", "src/c"),
+			("add", "src/c/d"),
+			("add", "src/c/d/y.rs"),
+		]);
+		assert_eq!(dirs, [
+			(PathBuf::from("src/a"), false),
+			(PathBuf::from("src/c"), true),
+			(PathBuf::from("src/c/d"), true),
+		]);
+		assert_eq!(tree.to_vec().len(), collect_entries(&root, &key.options, &ct).unwrap().len());
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+}
@@ -0,0 +1,521 @@
+//! Watcher mode for the scan cache.
+//!
+//! A watched [`CacheEntry`] never expires. Instead, the directories of the
+//! scan are watched (inotify on Linux) without recursion, so ignored trees
+//! such as `node_modules` or `target` cost no watches, and every batch of
+//! events re-lists only the directories that changed. Listing goes through
+//! [`build_walker`], so additions follow the same hidden and gitignore rules
+//! as a full scan. Changes to ignore files, watcher errors and event queue
+//! overflows fall back to a full rescan, as does every change to a scan whose
+//! options rule out re-listing single directories.
+//!
+//! The resulting add/remove/modify deltas are delivered to JS subscribers.
+//!
+//! While a directory of the scan cannot be watched (e.g. when inotify watches
+//! run out) or a rescan fails, the entry is published unwatched and expires by
+//! TTL like any other; the next scan of the key retries the watches.
+
+use std::{
+	collections::{BTreeSet, HashSet},
+	path::{Path, PathBuf},
+	sync::{
+		Arc, LazyLock, Weak,
+		atomic::{AtomicU32, Ordering},
+		mpsc,
+	},
+	time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use napi::{
+	Error, Result,
+	threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
+};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+
+use super::{
+	CacheEntry, CacheKey, FS_CACHE, FsChange, FsChangeEvent, GlobMatch, collect_entries,
+	tree::{ScanTree, dirty_dirs_for, is_ignore_file},
+};
+use crate::task;
+
+/// Quiet period that ends a batch of events.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+/// Upper bound on how long a batch keeps collecting events.
+const MAX_BATCH_WAIT: Duration = Duration::from_millis(500);
+
+static WATCHES: LazyLock<DashMap<CacheKey, Arc<Watch>>> = LazyLock::new(DashMap::new);
+static NEXT_SUBSCRIBER: AtomicU32 = AtomicU32::new(1);
+
+type Events = mpsc::Receiver<notify::Result<notify::Event>>;
+type Subscriber = ThreadsafeFunction<FsChangeEvent>;
+
+struct Watch {
+	key:         CacheKey,
+	state:       Mutex<State>,
+	subscribers: Mutex<Vec<(u32, Subscriber)>>,
+	/// Refreshes requested through [`refresh_path`], applied by [`flush`] or
+	/// with the next batch of events.
+	pending:     Mutex<(bool, BTreeSet<PathBuf>)>,
+}
+
+struct State {
+	watcher:     RecommendedWatcher,
+	tree:        ScanTree,
+	/// Absolute paths of the directories currently watched.
+	watched:     HashSet<PathBuf>,
+	/// Absolute paths of the directories of the tree that could not be
+	/// watched.
+	unwatched:   HashSet<PathBuf>,
+	/// Whether the last rescan failed, leaving the previous tree in place.
+	scan_failed: bool,
+}
+
+/// Starts watching the scan of `key`, whose current `entries` become the
+/// watched cache entry. Fails if any directory of the scan cannot be watched.
+/// If `key` is already watched, its maintained entries are republished
+/// instead, or replaced by `entries` when the watch is incomplete.
+pub fn start(key: &CacheKey, entries: Vec<GlobMatch>) -> Result<()> {
+	if let Some(watch) = WATCHES.get(key).map(|watch| Arc::clone(watch.value())) {
+		let mut state = watch.state.lock();
+		if state.is_complete() {
+			watch.publish(&state, true);
+		} else {
+			let changes = state.reset(key, entries);
+			watch.publish(&state, true);
+			drop(state);
+			watch.notify(changes, true);
+		}
+		return Ok(());
+	}
+	let (tx, rx) = mpsc::channel();
+	let watcher = notify::recommended_watcher(move |event| {
+		let _ = tx.send(event);
+	})
+	.map_err(|err| watch_error(key, &err))?;
+
+	let mut state = State {
+		watcher,
+		tree: ScanTree::new(entries),
+		watched: HashSet::new(),
+		unwatched: HashSet::new(),
+		scan_failed: false,
+	};
+	state.sync_watches(key)?;
+
+	let watch = Arc::new(Watch {
+		key:         key.clone(),
+		state:       Mutex::new(state),
+		subscribers: Mutex::new(Vec::new()),
+		pending:     Mutex::default(),
+	});
+	let events = Arc::downgrade(&watch);
+	std::thread::Builder::new()
+		.name("fs-cache-watch".to_string())
+		.spawn(move || run(&events, &rx))
+		.map_err(|err| watch_error(key, &err))?;
+
+	watch.publish(&watch.state.lock(), true);
+	WATCHES.insert(key.clone(), watch);
+	Ok(())
+}
+
+/// Stops watching `key` unless JS subscribers still depend on it.
+pub fn release(key: &CacheKey) {
+	WATCHES.remove_if(key, |_, watch| watch.subscribers.lock().is_empty());
+}
+
+/// Marks `target` and the directory containing it for re-listing in every
+/// watched scan below which it lies. The scan is refreshed by the next
+/// [`flush`] of its key or batch of events, whichever comes first, so readers
+/// need not wait for the watcher to report the change.
+pub fn refresh_path(target: &Path) {
+	for watch in watches() {
+		if let Ok(relative) = target.strip_prefix(&watch.key.root) {
+			let mut pending = watch.pending.lock();
+			if is_ignore_file(&watch.key.options, relative) {
+				pending.0 = true;
+			} else {
+				pending.1.extend(dirty_dirs_for(relative));
+			}
+		}
+	}
+}
+
+/// Applies the refreshes requested for `key` by [`refresh_path`].
+pub fn flush(key: &CacheKey) {
+	let Some(watch) = WATCHES.get(key).map(|watch| Arc::clone(watch.value())) else {
+		return;
+	};
+	let (full, dirty) = std::mem::take(&mut *watch.pending.lock());
+	if full || !dirty.is_empty() {
+		watch.apply(full, dirty);
+	}
+}
+
+/// Rescans `key` if it is watched, returning the fresh entries.
+pub fn rescan(key: &CacheKey) -> Option<Vec<GlobMatch>> {
+	let watch = WATCHES.get(key).map(|watch| Arc::clone(watch.value()))?;
+	watch.apply(true, BTreeSet::new());
+	let entries = watch.state.lock().tree.to_vec();
+	Some(entries)
+}
+
+/// Rescans every watched scan.
+pub fn rescan_all() {
+	for watch in watches() {
+		watch.apply(true, BTreeSet::new());
+	}
+}
+
+/// Registers `callback` for changes to the scan of `key`, starting a watch
+/// (and a scan) when needed. Returns the subscription id.
+pub fn subscribe(key: &CacheKey, callback: Subscriber, ct: &task::CancelToken) -> Result<u32> {
+	if !WATCHES.contains_key(key) {
+		let entries = collect_entries(&key.root, &key.options, ct)?;
+		start(key, entries)?;
+	}
+	let watch = WATCHES
+		.get(key)
+		.map(|watch| Arc::clone(watch.value()))
+		.ok_or_else(|| Error::from_reason(format!("Failed to watch {}", key.root.display())))?;
+	let id = NEXT_SUBSCRIBER.fetch_add(1, Ordering::Relaxed);
+	watch.subscribers.lock().push((id, callback));
+	Ok(id)
+}
+
+/// Removes a subscription. The watch stops with its last subscriber unless
+/// watcher mode is enabled for the whole cache.
+pub fn unsubscribe(key: &CacheKey, id: u32) {
+	let Some(watch) = WATCHES.get(key).map(|watch| Arc::clone(watch.value())) else {
+		return;
+	};
+	let mut subscribers = watch.subscribers.lock();
+	subscribers.retain(|(subscriber, _)| *subscriber != id);
+	let unused = subscribers.is_empty();
+	drop(subscribers);
+	if unused && !super::watch_enabled() {
+		release(key);
+		if let Some(mut entry) = FS_CACHE.get_mut(key) {
+			// Age out through the regular TTL from now on.
+			entry.watched = false;
+		}
+	}
+}
+
+fn watches() -> Vec<Arc<Watch>> {
+	WATCHES
+		.iter()
+		.map(|watch| Arc::clone(watch.value()))
+		.collect()
+}
+
+fn watch_error(key: &CacheKey, err: &dyn std::fmt::Display) -> Error {
+	Error::from_reason(format!("Failed to watch {}: {err}", key.root.display()))
+}
+
+/// Event loop of one watch: gathers events into debounced batches until the
+/// watcher is dropped.
+fn run(watch: &Weak<Watch>, events: &Events) {
+	while let Ok(first) = events.recv() {
+		let mut batch = vec![first];
+		let deadline = Instant::now() + MAX_BATCH_WAIT;
+		while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+			match events.recv_timeout(DEBOUNCE.min(remaining)) {
+				Ok(event) => batch.push(event),
+				Err(mpsc::RecvTimeoutError::Timeout) => break,
+				Err(mpsc::RecvTimeoutError::Disconnected) => return,
+			}
+		}
+		let Some(watch) = watch.upgrade() else {
+			return;
+		};
+		let (mut full, mut dirty) = watch.dirty_dirs(batch);
+		let (pending_full, pending_dirty) = std::mem::take(&mut *watch.pending.lock());
+		full |= pending_full;
+		dirty.extend(pending_dirty);
+		watch.apply(full, dirty);
+	}
+}
+
+impl Watch {
+	/// Directories (relative to the root) to re-list for `batch`, or `true`
+	/// when the batch requires a full rescan.
+	fn dirty_dirs(&self, batch: Vec<notify::Result<notify::Event>>) -> (bool, BTreeSet<PathBuf>) {
+		let mut full = false;
+		let mut dirty = BTreeSet::new();
+		for event in batch {
+			let Ok(event) = event else {
+				full = true;
+				continue;
+			};
+			full |= event.need_rescan();
+			for path in event.paths {
+				let Ok(relative) = path.strip_prefix(&self.key.root) else {
+					continue;
+				};
+				full |= is_ignore_file(&self.key.options, relative);
+				dirty.insert(relative.parent().unwrap_or(relative).to_path_buf());
+			}
+		}
+		(full, dirty)
+	}
+
+	/// Updates the tree, republishes the cache entry and notifies subscribers.
+	fn apply(&self, full: bool, dirty: BTreeSet<PathBuf>) {
+		let full = full || !self.key.options.allows_subtrees();
+		let mut state = self.state.lock();
+		let changes = if full {
+			state.rescan(&self.key, &task::CancelToken::default())
+		} else {
+			state.refresh(&self.key, dirty)
+		};
+		if changes.is_empty() && !full {
+			return;
+		}
+		self.publish(&state, full && !state.scan_failed);
+		drop(state);
+		self.notify(changes, full);
+	}
+
+	fn notify(&self, changes: Vec<FsChange>, rescan: bool) {
+		let event = FsChangeEvent { changes, rescan };
+		for (_, callback) in self.subscribers.lock().iter() {
+			callback.call(Ok(event.clone()), ThreadsafeFunctionCallMode::NonBlocking);
+		}
+	}
+
+	/// Publishes the tree as the cache entry of the key. An incomplete watch
+	/// publishes an unwatched entry, whose age only resets on a `fresh` scan so
+	/// that it still expires.
+	fn publish(&self, state: &State, fresh: bool) {
+		let watched = state.is_complete();
+		let created_at = if watched || fresh {
+			Instant::now()
+		} else {
+			FS_CACHE
+				.get(&self.key)
+				.map_or_else(Instant::now, |entry| entry.created_at)
+		};
+		FS_CACHE.insert(self.key.clone(), CacheEntry::new(created_at, state.tree.clone(), watched));
+	}
+}
+
+impl State {
+	/// Whether every directory of the tree is watched and the tree is current.
+	fn is_complete(&self) -> bool {
+		self.unwatched.is_empty() && !self.scan_failed
+	}
+
+	fn refresh(&mut self, key: &CacheKey, dirty: BTreeSet<PathBuf>) -> Vec<FsChange> {
+		let Self { watcher, tree, watched, unwatched, .. } = self;
+		tree.refresh(key, dirty, &mut |dir, added| {
+			let dir = key.root.join(dir);
+			if added {
+				if watch_dir(watcher, watched, &dir).is_err() {
+					unwatched.insert(dir);
+				}
+			} else {
+				// Fails harmlessly when the kernel already dropped the watch.
+				let _ = watcher.unwatch(&dir);
+				watched.remove(&dir);
+				unwatched.remove(&dir);
+			}
+		})
+	}
+
+	/// Replaces the tree with a full scan, reporting the difference. If the
+	/// scan fails, the previous tree is kept.
+	fn rescan(&mut self, key: &CacheKey, ct: &task::CancelToken) -> Vec<FsChange> {
+		if let Ok(entries) = collect_entries(&key.root, &key.options, ct) {
+			self.reset(key, entries)
+		} else {
+			self.scan_failed = true;
+			Vec::new()
+		}
+	}
+
+	/// Replaces the tree with the scan `entries` and watches its directories,
+	/// reporting the difference.
+	fn reset(&mut self, key: &CacheKey, entries: Vec<GlobMatch>) -> Vec<FsChange> {
+		let changes = self.tree.replace(ScanTree::new(entries));
+		self.scan_failed = false;
+		// Directories that can't be watched leave the watch incomplete.
+		let _ = self.sync_watches(key);
+		changes
+	}
+
+	/// Watches the root and every directory of the tree, and nothing else.
+	/// Fails if any of them cannot be watched.
+	fn sync_watches(&mut self, key: &CacheKey) -> Result<()> {
+		let wanted: HashSet<PathBuf> = std::iter::once(key.root.clone())
+			.chain(self.tree.dirs().map(|dir| key.root.join(dir)))
+			.collect();
+		let stale: Vec<PathBuf> = self.watched.difference(&wanted).cloned().collect();
+		for dir in stale {
+			let _ = self.watcher.unwatch(&dir);
+			self.watched.remove(&dir);
+		}
+		self.unwatched.clear();
+		let mut failure = None;
+		for dir in wanted {
+			if let Err(err) = watch_dir(&mut self.watcher, &mut self.watched, &dir) {
+				failure.get_or_insert_with(|| format!("{}: {err}", dir.display()));
+				self.unwatched.insert(dir);
+			}
+		}
+		failure.map_or(Ok(()), |failure| Err(watch_error(key, &failure)))
+	}
+}
+
+fn watch_dir(
+	watcher: &mut RecommendedWatcher,
+	watched: &mut HashSet<PathBuf>,
+	dir: &Path,
+) -> notify::Result<()> {
+	if !watched.contains(dir) {
+		watcher.watch(dir, RecursiveMode::NonRecursive)?;
+		watched.insert(dir.to_path_buf());
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::fs_cache::ScanOptions;
+
+	fn temp_root(name: &str) -> PathBuf {
+		let root =
+			std::env::temp_dir().join(format!("pi-natives-watch-{}-{name}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&root);
+		std::fs::create_dir_all(root.join("src")).unwrap();
+		std::fs::write(root.join("src/lib.rs"), "").unwrap();
+		std::fs::canonicalize(&root).unwrap()
+	}
+
+	fn cached_paths(key: &CacheKey) -> Vec<String> {
+		FS_CACHE.get(key).map_or_else(Vec::new, |entry| {
+			entry
+				.tree
+				.to_vec()
+				.into_iter()
+				.map(|entry| entry.path)
+				.collect()
+		})
+	}
+
+	fn wait_for(key: &CacheKey, expected: &[&str]) {
+		let deadline = Instant::now() + Duration::from_secs(5);
+		while cached_paths(key) != expected && Instant::now() < deadline {
+			std::thread::sleep(Duration::from_millis(20));
+		}
+		assert_eq!(cached_paths(key), expected);
+	}
+
+	#[test]
+	fn test_applies_deltas_from_events() {
+		let root = temp_root("events");
+		let key = CacheKey { root: root.clone(), options: ScanOptions::new(false, true) };
+		let ct = task::CancelToken::default();
+		start(&key, collect_entries(&root, &key.options, &ct).unwrap()).unwrap();
+		wait_for(&key, &["src", "src/lib.rs"]);
+
+		std::fs::create_dir_all(root.join("src/nested/deep")).unwrap();
+		std::fs::write(root.join("src/nested/deep/mod.rs"), "").unwrap();
+		std::fs::write(root.join(".hidden"), "").unwrap();
+		wait_for(&key, &[
+			"src",
+			"src/lib.rs",
+			"src/nested",
+			"src/nested/deep",
+			"src/nested/deep/mod.rs",
+		]);
+
+		std::fs::remove_dir_all(root.join("src/nested")).unwrap();
+		wait_for(&key, &["src", "src/lib.rs"]);
+
+		WATCHES.remove(&key);
+		FS_CACHE.remove(&key);
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+
+	#[test]
+	fn test_refresh_and_rescan_follow_ignore_rules() {
+		let root = temp_root("refresh");
+		let key = CacheKey { root: root.clone(), options: ScanOptions::new(false, true) };
+		let ct = task::CancelToken::default();
+		start(&key, collect_entries(&root, &key.options, &ct).unwrap()).unwrap();
+
+		std::fs::write(root.join(".ignore"), "*.log\n").unwrap();
+		std::fs::write(root.join("src/app.log"), "").unwrap();
+		std::fs::write(root.join("src/main.rs"), "").unwrap();
+		refresh_path(&root.join("src/main.rs"));
+		flush(&key);
+		assert_eq!(cached_paths(&key), ["src", "src/lib.rs", "src/main.rs"]);
+
+		std::fs::write(root.join(".ignore"), "").unwrap();
+		let entries = rescan(&key).unwrap();
+		let paths: Vec<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+		assert_eq!(paths, ["src", "src/app.log", "src/lib.rs", "src/main.rs"]);
+
+		WATCHES.remove(&key);
+		FS_CACHE.remove(&key);
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+
+	#[test]
+	fn test_unwatched_dirs_fall_back_to_ttl() {
+		let root = temp_root("unwatched");
+		let key = CacheKey { root: root.clone(), options: ScanOptions::new(false, true) };
+		let ct = task::CancelToken::default();
+		let entries = collect_entries(&root, &key.options, &ct).unwrap();
+
+		// A directory that can no longer be watched fails the watch.
+		std::fs::remove_dir_all(root.join("src")).unwrap();
+		assert!(start(&key, entries.clone()).is_err());
+		assert!(!WATCHES.contains_key(&key));
+
+		std::fs::create_dir_all(root.join("src")).unwrap();
+		start(&key, entries).unwrap();
+		let watch = Arc::clone(WATCHES.get(&key).unwrap().value());
+		let mut state = watch.state.lock();
+		// As when the watch limit runs out for a directory added later.
+		state.unwatched.insert(root.join("src"));
+		watch.publish(&state, false);
+		drop(state);
+		assert!(!FS_CACHE.get(&key).unwrap().watched);
+
+		// The next scan of the key retries the watches.
+		start(&key, collect_entries(&root, &key.options, &ct).unwrap()).unwrap();
+		assert!(FS_CACHE.get(&key).unwrap().watched);
+
+		WATCHES.remove(&key);
+		FS_CACHE.remove(&key);
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+
+	#[test]
+	fn test_failed_rescan_keeps_tree() {
+		let root = temp_root("rescan-error");
+		let key = CacheKey { root: root.clone(), options: ScanOptions::new(false, true) };
+		start(&key, collect_entries(&root, &key.options, &task::CancelToken::default()).unwrap())
+			.unwrap();
+		let watch = Arc::clone(WATCHES.get(&key).unwrap().value());
+
+		let mut ct = task::CancelToken::default();
+		ct.emplace_abort_token().abort(task::AbortReason::Signal);
+		let mut state = watch.state.lock();
+		assert!(state.rescan(&key, &ct).is_empty());
+		assert_eq!(state.tree.to_vec().len(), 2);
+		watch.publish(&state, false);
+		drop(state);
+		assert!(!FS_CACHE.get(&key).unwrap().watched);
+		assert_eq!(cached_paths(&key), ["src", "src/lib.rs"]);
+
+		WATCHES.remove(&key);
+		FS_CACHE.remove(&key);
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+}
@@ -14,7 +14,7 @@
 //! // JS: await native.glob({ pattern: "*.rs", path: "." })
 //! ```
 
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use napi::{
@@ -31,7 +31,12 @@ use crate::{fs_cache, task};
 #[napi(object)]
 pub struct GlobOptions<'env> {
 	/// Glob pattern to match (e.g., "*.ts").
-	pub pattern:              String,
+	pub pattern:              Option<String>,
+	/// Additional glob patterns; entries matching any pattern are returned.
+	pub patterns:             Option<Vec<String>>,
+	/// Glob patterns to exclude. An excluded directory excludes everything
+	/// below it.
+	pub exclude:              Option<Vec<String>>,
 	/// Directory to search.
 	pub path:                 String,
 	/// Filter by file type: "file", "dir", or "symlink". Symlinks are
@@ -56,6 +61,36 @@ pub struct GlobOptions<'env> {
 	/// mention them.
 	#[napi(js_name = "includeNodeModules")]
 	pub include_node_modules: Option<bool>,
+	/// Only match files of at least this many bytes. Excludes directories.
+	#[napi(js_name = "minSize")]
+	pub min_size:             Option<f64>,
+	/// Only match files of at most this many bytes. Excludes directories.
+	#[napi(js_name = "maxSize")]
+	pub max_size:             Option<f64>,
+	/// Only match entries modified within this many milliseconds.
+	#[napi(js_name = "changedWithin")]
+	pub changed_within:       Option<f64>,
+	/// Only match entries modified more than this many milliseconds ago.
+	#[napi(js_name = "changedBefore")]
+	pub changed_before:       Option<f64>,
+	/// Extra ignore filenames with gitignore syntax, honored like `.ignore`
+	/// when `gitignore` is set (default: `.ompignore`, `.piignore`).
+	#[napi(js_name = "ignoreFiles")]
+	pub ignore_files:         Option<Vec<String>>,
+	/// Override globs as for ripgrep's `--glob`, taking precedence over ignore
+	/// files: `!glob` excludes matches, and positive globs restrict files to
+	/// those matching one.
+	pub overrides:            Option<Vec<String>>,
+	/// Maximum directory depth to descend (1 lists only direct children).
+	#[napi(js_name = "maxDepth")]
+	pub max_depth:            Option<u32>,
+	/// Follow symbolic links to directories (default: false).
+	#[napi(js_name = "followSymlinks")]
+	pub follow_symlinks:      Option<bool>,
+	/// Report size, mode, symlink target, inode/device, and git status for
+	/// each match (default: false).
+	#[napi(js_name = "withMetadata")]
+	pub with_metadata:        Option<bool>,
 	/// Abort signal for cancelling the operation.
 	pub signal:               Option<Unknown<'env>>,
 	/// Timeout in milliseconds for the operation.
@@ -85,12 +120,14 @@ fn build_glob_pattern(glob: &str, recursive: bool) -> String {
 	}
 }
 
-fn compile_glob(glob: &str, recursive: bool) -> Result<GlobSet> {
+fn compile_globs(globs: &[String], recursive: bool, kind: &str) -> Result<GlobSet> {
 	let mut builder = GlobSetBuilder::new();
-	let pattern = build_glob_pattern(glob, recursive);
-	let glob = Glob::new(&pattern)
-		.map_err(|err| Error::from_reason(format!("Invalid glob pattern: {err}")))?;
-	builder.add(glob);
+	for glob in globs {
+		let pattern = build_glob_pattern(glob, recursive);
+		let glob = Glob::new(&pattern)
+			.map_err(|err| Error::from_reason(format!("Invalid {kind} pattern: {err}")))?;
+		builder.add(glob);
+	}
 	builder
 		.build()
 		.map_err(|err| Error::from_reason(format!("Failed to build glob matcher: {err}")))
@@ -99,15 +136,57 @@ fn compile_glob(glob: &str, recursive: bool) -> Result<GlobSet> {
 /// Internal runtime config for a single glob execution.
 struct GlobConfig {
 	root:                  std::path::PathBuf,
-	pattern:               String,
+	scan:                  fs_cache::ScanOptions,
+	patterns:              Vec<String>,
+	exclude:               Vec<String>,
 	recursive:             bool,
-	include_hidden:        bool,
 	file_type_filter:      Option<FileType>,
 	max_results:           usize,
-	use_gitignore:         bool,
 	mentions_node_modules: bool,
 	sort_by_mtime:         bool,
 	use_cache:             bool,
+	with_metadata:         bool,
+	min_size:              Option<f64>,
+	max_size:              Option<f64>,
+	/// Inclusive lower bound on mtime (ms since epoch).
+	modified_after:        Option<f64>,
+	/// Exclusive upper bound on mtime (ms since epoch).
+	modified_before:       Option<f64>,
+}
+
+impl GlobConfig {
+	/// Whether `entry` passes the size and modification-time filters.
+	fn matches_filters(&self, entry: &GlobMatch) -> bool {
+		if self.min_size.is_some() || self.max_size.is_some() {
+			let Some(size) = entry.size.filter(|_| entry.file_type == FileType::File) else {
+				return false;
+			};
+			if self.min_size.is_some_and(|min| size < min)
+				|| self.max_size.is_some_and(|max| size > max)
+			{
+				return false;
+			}
+		}
+		if self.modified_after.is_some() || self.modified_before.is_some() {
+			let Some(mtime) = entry.mtime else {
+				return false;
+			};
+			if self.modified_after.is_some_and(|after| mtime < after)
+				|| self.modified_before.is_some_and(|before| mtime >= before)
+			{
+				return false;
+			}
+		}
+		true
+	}
+}
+
+/// Whether `path` or one of its parent directories matches `exclude`.
+fn is_excluded(path: &str, exclude: &GlobSet) -> bool {
+	Path::new(path)
+		.ancestors()
+		.take_while(|ancestor| !ancestor.as_os_str().is_empty())
+		.any(|ancestor| exclude.is_match(ancestor))
 }
 
 fn resolve_symlink_target_type(root: &Path, relative_path: &str) -> Option<FileType> {
@@ -145,11 +224,93 @@ fn apply_file_type_filter(entry: &GlobMatch, config: &GlobConfig) -> Option<File
 	}
 }
 
+/// Working-tree status of a repository, keyed by repository-relative path.
+///
+/// Untracked and ignored directories are reported once rather than per file,
+/// so lookups fall back to the nearest listed ancestor.
+///
+/// Loaded by a libgit2 status pass of its own for each `withMetadata` glob,
+/// since entries may come from the scan cache rather than a fresh walk.
+struct GitStatuses {
+	/// Search root relative to the repository work tree.
+	prefix:   std::path::PathBuf,
+	statuses: HashMap<std::path::PathBuf, &'static str>,
+}
+
+impl GitStatuses {
+	/// Reads the status of the repository containing `root`, or `None` when
+	/// `root` is not inside a work tree.
+	fn load(root: &Path) -> Option<Self> {
+		let repo = git2::Repository::discover(root).ok()?;
+		let workdir = std::fs::canonicalize(repo.workdir()?).ok()?;
+		let root = std::fs::canonicalize(root).ok()?;
+		let prefix = root.strip_prefix(&workdir).ok()?.to_path_buf();
+		let mut options = git2::StatusOptions::new();
+		options
+			.include_untracked(true)
+			.include_ignored(true)
+			.recurse_untracked_dirs(false)
+			.recurse_ignored_dirs(false)
+			.exclude_submodules(true);
+		let statuses = repo
+			.statuses(Some(&mut options))
+			.ok()?
+			.iter()
+			.filter_map(|entry| {
+				let label = status_label(entry.status())?;
+				let path = entry.path()?.trim_end_matches('/');
+				Some((std::path::PathBuf::from(path), label))
+			})
+			.collect();
+		Some(Self { prefix, statuses })
+	}
+
+	/// Status of `relative` (a path below the search root).
+	fn status_of(&self, relative: &str) -> Option<&'static str> {
+		let path = self.prefix.join(relative);
+		if let Some(status) = self.statuses.get(&path) {
+			return Some(status);
+		}
+		path
+			.ancestors()
+			.skip(1)
+			.find_map(|ancestor| self.statuses.get(ancestor))
+			.copied()
+			.filter(|status| matches!(*status, "untracked" | "ignored"))
+	}
+}
+
+fn status_label(status: git2::Status) -> Option<&'static str> {
+	use git2::Status;
+	if status.is_conflicted() {
+		Some("conflicted")
+	} else if status.is_ignored() {
+		Some("ignored")
+	} else if status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED) {
+		Some("renamed")
+	} else if status.intersects(
+		Status::INDEX_MODIFIED
+			| Status::WT_MODIFIED
+			| Status::INDEX_TYPECHANGE
+			| Status::WT_TYPECHANGE,
+	) {
+		Some("modified")
+	} else if status.is_index_new() {
+		Some("added")
+	} else if status.is_wt_new() {
+		Some("untracked")
+	} else {
+		None
+	}
+}
+
 /// Filter and collect matching entries from a pre-scanned list.
 fn filter_entries(
 	entries: &[GlobMatch],
 	glob_set: &GlobSet,
+	exclude: Option<&GlobSet>,
 	config: &GlobConfig,
+	git: Option<&GitStatuses>,
 	on_match: Option<&ThreadsafeFunction<GlobMatch>>,
 	ct: &task::CancelToken,
 ) -> Result<Vec<GlobMatch>> {
@@ -164,14 +325,24 @@ fn filter_entries(
 			// Apply post-scan node_modules policy before glob matching.
 			continue;
 		}
-		if !glob_set.is_match(&entry.path) {
+		if !glob_set.is_match(&entry.path)
+			|| !config.matches_filters(entry)
+			|| exclude.is_some_and(|exclude| is_excluded(&entry.path, exclude))
+		{
 			continue;
 		}
 		let Some(effective_file_type) = apply_file_type_filter(entry, config) else {
 			continue;
 		};
-		let mut matched_entry = entry.clone();
+		let mut matched_entry = if config.with_metadata {
+			entry.clone()
+		} else {
+			entry.clone().without_metadata()
+		};
 		matched_entry.file_type = effective_file_type;
+		if let Some(git) = git {
+			matched_entry.git_status = git.status_of(&matched_entry.path).map(str::to_string);
+		}
 		if let Some(callback) = on_match {
 			callback.call(Ok(matched_entry.clone()), ThreadsafeFunctionCallMode::NonBlocking);
 		}
@@ -192,37 +363,51 @@ fn run_glob(
 	on_match: Option<&ThreadsafeFunction<GlobMatch>>,
 	ct: task::CancelToken,
 ) -> Result<GlobResult> {
-	let glob_set = compile_glob(&config.pattern, config.recursive)?;
+	let glob_set = compile_globs(&config.patterns, config.recursive, "glob")?;
+	let exclude = if config.exclude.is_empty() {
+		None
+	} else {
+		Some(compile_globs(&config.exclude, config.recursive, "exclude")?)
+	};
 	if config.max_results == 0 {
 		return Ok(GlobResult { matches: Vec::new(), total_matches: 0 });
 	}
+	let git = if config.with_metadata {
+		GitStatuses::load(&config.root)
+	} else {
+		None
+	};
 
 	let mut matches = if config.use_cache {
-		let scan =
-			fs_cache::get_or_scan(&config.root, config.include_hidden, config.use_gitignore, &ct)?;
-		let mut matches = filter_entries(&scan.entries, &glob_set, &config, on_match, &ct)?;
-		// Empty-result recheck: if we got zero matches from a cached scan that's old
-		// enough, force a rescan and try once more before returning empty.
+		let scan = fs_cache::get_or_scan(&config.root, &config.scan, &ct)?;
+		let mut matches = filter_entries(
+			&scan.entries,
+			&glob_set,
+			exclude.as_ref(),
+			&config,
+			git.as_ref(),
+			on_match,
+			&ct,
+		)?;
+		// Empty-result recheck: if we got zero matches from a cached scan that's
+		// old enough, force a rescan and try once more before returning
+		// empty.
 		if matches.is_empty() && scan.cache_age_ms >= fs_cache::empty_recheck_ms() {
-			let fresh = fs_cache::force_rescan(
-				&config.root,
-				config.include_hidden,
-				config.use_gitignore,
-				true,
+			let fresh = fs_cache::force_rescan(&config.root, &config.scan, true, &ct)?;
+			matches = filter_entries(
+				&fresh,
+				&glob_set,
+				exclude.as_ref(),
+				&config,
+				git.as_ref(),
+				on_match,
 				&ct,
 			)?;
-			matches = filter_entries(&fresh, &glob_set, &config, on_match, &ct)?;
 		}
 		matches
 	} else {
-		let fresh = fs_cache::force_rescan(
-			&config.root,
-			config.include_hidden,
-			config.use_gitignore,
-			false,
-			&ct,
-		)?;
-		filter_entries(&fresh, &glob_set, &config, on_match, &ct)?
+		let fresh = fs_cache::force_rescan(&config.root, &config.scan, false, &ct)?;
+		filter_entries(&fresh, &glob_set, exclude.as_ref(), &config, git.as_ref(), on_match, &ct)?
 	};
 
 	if config.sort_by_mtime {
@@ -242,8 +427,9 @@ fn run_glob(
 
 /// Find filesystem entries matching a glob pattern.
 ///
-/// Resolves the search root, scans entries, applies glob and optional file-type
-/// filters, and optionally streams each accepted match through `on_match`.
+/// Resolves the search root, scans entries, applies glob, exclude, file-type,
+/// size, and modification-time filters, and optionally streams each accepted
+/// match through `on_match`.
 ///
 /// If `sortByMtime` is enabled, all matching entries are collected, sorted by
 /// descending mtime, then truncated to `maxResults`.
@@ -261,6 +447,8 @@ pub fn glob(
 ) -> task::Async<GlobResult> {
 	let GlobOptions {
 		pattern,
+		patterns,
+		exclude,
 		path,
 		file_type,
 		recursive,
@@ -270,13 +458,31 @@ pub fn glob(
 		sort_by_mtime,
 		cache,
 		include_node_modules,
+		min_size,
+		max_size,
+		changed_within,
+		changed_before,
+		ignore_files,
+		overrides,
+		max_depth,
+		follow_symlinks,
+		with_metadata,
 		timeout_ms,
 		signal,
 	} = options;
 
-	let pattern = pattern.trim();
-	let pattern = if pattern.is_empty() { "*" } else { pattern };
-	let pattern = pattern.to_string();
+	let mut patterns: Vec<String> = pattern
+		.into_iter()
+		.chain(patterns.unwrap_or_default())
+		.map(|pattern| pattern.trim().to_string())
+		.filter(|pattern| !pattern.is_empty())
+		.collect();
+	if patterns.is_empty() {
+		patterns.push("*".to_string());
+	}
+	let now = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.map_or(0.0, |d| d.as_millis() as f64);
 
 	let ct = task::CancelToken::new(timeout_ms, signal);
 
@@ -284,19 +490,133 @@ pub fn glob(
 		run_glob(
 			GlobConfig {
 				root: fs_cache::resolve_search_path(&path)?,
-				include_hidden: hidden.unwrap_or(false),
+				scan: fs_cache::ScanOptions::new(hidden.unwrap_or(false), gitignore.unwrap_or(true))
+					.with_walk_options(ignore_files, overrides, max_depth, follow_symlinks)?,
 				file_type_filter: file_type,
 				recursive: recursive.unwrap_or(true),
 				max_results: max_results.map_or(usize::MAX, |value| value as usize),
-				use_gitignore: gitignore.unwrap_or(true),
-				mentions_node_modules: include_node_modules
-					.unwrap_or_else(|| pattern.contains("node_modules")),
+				mentions_node_modules: include_node_modules.unwrap_or_else(|| {
+					patterns
+						.iter()
+						.any(|pattern| pattern.contains("node_modules"))
+				}),
 				sort_by_mtime: sort_by_mtime.unwrap_or(false),
 				use_cache: cache.unwrap_or(false),
-				pattern,
+				with_metadata: with_metadata.unwrap_or(false),
+				min_size,
+				max_size,
+				modified_after: changed_within.map(|within| now - within),
+				modified_before: changed_before.map(|before| now - before),
+				patterns,
+				exclude: exclude.unwrap_or_default(),
 			},
 			on_match.as_ref(),
 			ct,
 		)
 	})
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(path: &str, file_type: FileType, size: f64, mtime: f64) -> GlobMatch {
+		GlobMatch {
+			path: path.to_string(),
+			file_type,
+			size: (file_type == FileType::File).then_some(size),
+			mtime: Some(mtime),
+			..GlobMatch::default()
+		}
+	}
+
+	#[test]
+	fn test_filter_entries() {
+		let entries = [
+			entry("src", FileType::Dir, 0.0, 300.0),
+			entry("src/lib.rs", FileType::File, 2000.0, 300.0),
+			entry("src/small.rs", FileType::File, 10.0, 300.0),
+			entry("src/old.ts", FileType::File, 2000.0, 100.0),
+			entry("target", FileType::Dir, 0.0, 300.0),
+			entry("target/out.rs", FileType::File, 2000.0, 300.0),
+		];
+		let mut config = GlobConfig {
+			root:                  std::path::PathBuf::from("/"),
+			scan:                  fs_cache::ScanOptions::new(false, true),
+			patterns:              vec!["*.rs".to_string(), "*.ts".to_string()],
+			exclude:               vec!["target".to_string()],
+			recursive:             true,
+			file_type_filter:      None,
+			max_results:           usize::MAX,
+			mentions_node_modules: false,
+			sort_by_mtime:         false,
+			use_cache:             false,
+			with_metadata:         false,
+			min_size:              Some(1000.0),
+			max_size:              None,
+			modified_after:        None,
+			modified_before:       None,
+		};
+		let ct = task::CancelToken::default();
+		let run = |config: &GlobConfig| {
+			let glob_set = compile_globs(&config.patterns, true, "glob").unwrap();
+			let exclude = compile_globs(&config.exclude, true, "exclude").unwrap();
+			let matches = filter_entries(&entries, &glob_set, Some(&exclude), config, None, None, &ct);
+			matches
+				.unwrap()
+				.into_iter()
+				.map(|entry| entry.path)
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(run(&config), ["src/lib.rs", "src/old.ts"]);
+		config.modified_after = Some(200.0);
+		assert_eq!(run(&config), ["src/lib.rs"]);
+		config.modified_after = None;
+		config.modified_before = Some(200.0);
+		config.min_size = None;
+		assert_eq!(run(&config), ["src/old.ts"]);
+	}
+
+	#[test]
+	fn test_git_statuses() {
+		let root = std::env::temp_dir().join(format!("pi-natives-glob-git-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&root);
+		std::fs::create_dir_all(root.join("pkg/new")).unwrap();
+		let root = std::fs::canonicalize(&root).unwrap();
+		let repo = git2::Repository::init(&root).unwrap();
+		std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+		std::fs::write(root.join("pkg/clean.rs"), "").unwrap();
+		std::fs::write(root.join("pkg/edited.rs"), "").unwrap();
+		let mut index = repo.index().unwrap();
+		for path in [".gitignore", "pkg/clean.rs", "pkg/edited.rs"] {
+			index.add_path(Path::new(path)).unwrap();
+		}
+		index.write().unwrap();
+		let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+		let signature = git2::Signature::now("test", "test@example.com").unwrap();
+		repo
+			.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+			.unwrap();
+		std::fs::write(root.join("pkg/edited.rs"), "fn main() {}").unwrap();
+		std::fs::write(root.join("pkg/new/a.rs"), "").unwrap();
+		std::fs::write(root.join("pkg/debug.log"), "").unwrap();
+
+		let git = GitStatuses::load(&root.join("pkg")).unwrap();
+		assert_eq!(git.status_of("clean.rs"), None);
+		assert_eq!(git.status_of("edited.rs"), Some("modified"));
+		assert_eq!(git.status_of("new"), Some("untracked"));
+		assert_eq!(git.status_of("new/a.rs"), Some("untracked"));
+		assert_eq!(git.status_of("debug.log"), Some("ignored"));
+
+		#[cfg(unix)]
+		{
+			let link = root.with_extension("link");
+			let _ = std::fs::remove_file(&link);
+			std::os::unix::fs::symlink(&root, &link).unwrap();
+			let git = GitStatuses::load(&link.join("pkg")).unwrap();
+			assert_eq!(git.status_of("edited.rs"), Some("modified"), "search root behind a symlink");
+			std::fs::remove_file(&link).unwrap();
+		}
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+}
@@ -1,24 +1,34 @@
 //! Ripgrep-backed search exported via N-API.
 //!
-//! Provides two layers:
+//! Provides three layers:
 //! - `search()` for in-memory content search.
 //! - `grep()` for filesystem search with glob/type filtering.
+//! - `replace()` for search-and-replace over the same file set, either as a
+//!   unified-diff preview or applied in place.
 //!
 //! The filesystem search matches the previous JS wrapper behavior, including
 //! global offsets, optional match limits, and per-file match summaries.
 
+mod decompress;
+mod encoding;
+mod file_types;
+mod matcher;
+
 use std::{
+	collections::HashMap,
 	fs::File,
-	io::{self, Cursor, Read},
+	io::{self, BufRead, BufReader, Cursor, Read, Write},
 	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
 };
 
 use globset::{Glob, GlobSet, GlobSetBuilder};
-use grep_matcher::Matcher;
+use grep_matcher::{Captures, Matcher};
 use grep_regex::RegexMatcherBuilder;
 use grep_searcher::{
 	BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkContextKind, SinkMatch,
 };
+use ignore::types::Types;
 use napi::{
 	JsString,
 	bindgen_prelude::*,
@@ -28,7 +38,15 @@ use napi_derive::napi;
 use rayon::prelude::*;
 use smallvec::SmallVec;
 
-use crate::{fs_cache, task};
+use self::{
+	encoding::TextEncoding,
+	file_types::{build_type_filter, default_definitions, matches_type_filter},
+	matcher::{Engine, FancyMatcher, SearchMatcher, fancy_pattern, has_uppercase_literal},
+};
+use crate::{
+	content_index::{self, Query},
+	fs_cache, task,
+};
 
 const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
 
@@ -38,16 +56,46 @@ enum OutputMode {
 	Count,
 }
 
+/// Order in which `grep()` reports files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SortOrder {
+	Path,
+	Mtime,
+	MatchCount,
+	Relevance,
+}
+
 /// Options for searching file content.
 #[napi(object)]
 pub struct SearchOptions {
 	/// Regex pattern to search for.
-	pub pattern:        String,
+	pub pattern:        Option<String>,
+	/// Additional patterns; a line matches if any pattern matches.
+	pub patterns:       Option<Vec<String>>,
 	/// Case-insensitive search.
 	#[napi(js_name = "ignoreCase")]
 	pub ignore_case:    Option<bool>,
 	/// Enable multiline matching.
 	pub multiline:      Option<bool>,
+	/// Regex engine: "default", "fancy" (lookaround/backreferences), or
+	/// "auto" (default; falls back to fancy when the pattern needs it).
+	pub engine:         Option<String>,
+	/// Treat the pattern as a literal string instead of a regex.
+	#[napi(js_name = "fixedStrings")]
+	pub fixed_strings:  Option<bool>,
+	/// Only match whole words.
+	#[napi(js_name = "wordRegexp")]
+	pub word_regexp:    Option<bool>,
+	/// Only match whole lines.
+	#[napi(js_name = "lineRegexp")]
+	pub line_regexp:    Option<bool>,
+	/// Case-insensitive unless the pattern contains an uppercase character.
+	#[napi(js_name = "smartCase")]
+	pub smart_case:     Option<bool>,
+	/// Report lines that do not match any pattern. Counts and
+	/// `filesWithMatches` then refer to non-matching lines, as with `grep -v`.
+	#[napi(js_name = "invertMatch")]
+	pub invert_match:   Option<bool>,
 	/// Maximum number of matches to return.
 	#[napi(js_name = "maxCount")]
 	pub max_count:      Option<u32>,
@@ -72,46 +120,104 @@ pub struct SearchOptions {
 #[napi(object)]
 pub struct GrepOptions<'env> {
 	/// Regex pattern to search for.
-	pub pattern:        String,
+	pub pattern:           Option<String>,
+	/// Additional patterns; a line matches if any pattern matches.
+	pub patterns:          Option<Vec<String>>,
 	/// Directory or file to search.
-	pub path:           String,
+	pub path:              String,
 	/// Glob filter for filenames (e.g., "*.ts").
-	pub glob:           Option<String>,
-	/// Filter by file type (e.g., "js", "py", "rust").
+	pub glob:              Option<String>,
+	/// Filter by file type (e.g., "js", "py", "rust"); several types may be
+	/// given and `!name` excludes a type.
 	#[napi(js_name = "type")]
-	pub type_filter:    Option<String>,
+	pub type_filter:       Option<Either<String, Vec<String>>>,
+	/// Extra file type definitions as `name:glob` (e.g., "proto:*.proto").
+	#[napi(js_name = "typeAdd")]
+	pub type_add:          Option<Vec<String>>,
 	/// Case-insensitive search.
 	#[napi(js_name = "ignoreCase")]
-	pub ignore_case:    Option<bool>,
+	pub ignore_case:       Option<bool>,
 	/// Enable multiline matching.
-	pub multiline:      Option<bool>,
+	pub multiline:         Option<bool>,
+	/// Regex engine: "default", "fancy" (lookaround/backreferences), or
+	/// "auto" (default; falls back to fancy when the pattern needs it).
+	pub engine:            Option<String>,
+	/// Treat the pattern as a literal string instead of a regex.
+	#[napi(js_name = "fixedStrings")]
+	pub fixed_strings:     Option<bool>,
+	/// Only match whole words.
+	#[napi(js_name = "wordRegexp")]
+	pub word_regexp:       Option<bool>,
+	/// Only match whole lines.
+	#[napi(js_name = "lineRegexp")]
+	pub line_regexp:       Option<bool>,
+	/// Case-insensitive unless the pattern contains an uppercase character.
+	#[napi(js_name = "smartCase")]
+	pub smart_case:        Option<bool>,
+	/// Report lines that do not match any pattern. Counts and
+	/// `filesWithMatches` then refer to non-matching lines, as with `grep -v`.
+	#[napi(js_name = "invertMatch")]
+	pub invert_match:      Option<bool>,
+	/// Skip files in which any of these patterns matches (files without
+	/// match, as with `grep -L`), using the same pattern flags.
+	#[napi(js_name = "excludePatterns")]
+	pub exclude_patterns:  Option<Vec<String>>,
 	/// Include hidden files (default: true).
-	pub hidden:         Option<bool>,
+	pub hidden:            Option<bool>,
+	/// Extra ignore filenames with gitignore syntax, honored like `.ignore`
+	/// (default: `.ompignore`, `.piignore`).
+	#[napi(js_name = "ignoreFiles")]
+	pub ignore_files:      Option<Vec<String>>,
+	/// Override globs as for ripgrep's `--glob`, taking precedence over ignore
+	/// files: `!glob` excludes matches, and positive globs restrict files to
+	/// those matching one.
+	pub overrides:         Option<Vec<String>>,
+	/// Maximum directory depth to descend (1 lists only direct children).
+	#[napi(js_name = "maxDepth")]
+	pub max_depth:         Option<u32>,
+	/// Follow symbolic links to directories (default: false).
+	#[napi(js_name = "followSymlinks")]
+	pub follow_symlinks:   Option<bool>,
 	/// Enable shared filesystem scan cache (default: false).
-	pub cache:          Option<bool>,
+	pub cache:             Option<bool>,
+	/// Skip files that cannot match using the persistent trigram content index
+	/// (default: false).
+	pub index:             Option<bool>,
+	/// Search inside gzip/zstd/xz/bzip2-compressed files and zip/tar archives
+	/// (default: false).
+	#[napi(js_name = "searchCompressed")]
+	pub search_compressed: Option<bool>,
+	/// Text encoding: `auto` (default; BOM or UTF-16 detection, else UTF-8)
+	/// or a label such as `utf-16le`, `utf-16be`, `latin1` or `shift_jis`.
+	/// A byte-order mark always takes precedence.
+	pub encoding:          Option<String>,
 	/// Maximum number of matches to return.
 	#[napi(js_name = "maxCount")]
-	pub max_count:      Option<u32>,
+	pub max_count:         Option<u32>,
 	/// Skip first N matches.
-	pub offset:         Option<u32>,
+	pub offset:            Option<u32>,
 	/// Lines of context before matches.
 	#[napi(js_name = "contextBefore")]
-	pub context_before: Option<u32>,
+	pub context_before:    Option<u32>,
 	/// Lines of context after matches.
 	#[napi(js_name = "contextAfter")]
-	pub context_after:  Option<u32>,
+	pub context_after:     Option<u32>,
 	/// Lines of context before/after matches (legacy).
-	pub context:        Option<u32>,
+	pub context:           Option<u32>,
 	/// Truncate lines longer than this (characters).
 	#[napi(js_name = "maxColumns")]
-	pub max_columns:    Option<u32>,
+	pub max_columns:       Option<u32>,
 	/// Output mode (content, filesWithMatches, or count).
-	pub mode:           Option<String>,
+	pub mode:              Option<String>,
+	/// File order: path (default), mtime (newest first), matchCount (most
+	/// matches first), or relevance (match density, depth and recency).
+	/// `offset` and `maxCount` apply in this order.
+	pub sort:              Option<String>,
 	/// Abort signal for cancelling the operation.
-	pub signal:         Option<Unknown<'env>>,
+	pub signal:            Option<Unknown<'env>>,
 	/// Timeout in milliseconds for the operation.
 	#[napi(js_name = "timeoutMs")]
-	pub timeout_ms:     Option<u32>,
+	pub timeout_ms:        Option<u32>,
 }
 
 /// A context line (before or after a match).
@@ -124,6 +230,29 @@ pub struct ContextLine {
 	pub line:        String,
 }
 
+/// A matched span within a reported line.
+///
+/// Offsets are relative to the reported `line` (before truncation), in UTF-8
+/// bytes and UTF-16 code units.
+#[derive(Clone)]
+#[napi(object)]
+pub struct Submatch {
+	/// The matched text.
+	pub text:        String,
+	/// Start offset in UTF-8 bytes.
+	pub start:       u32,
+	/// End offset (exclusive) in UTF-8 bytes.
+	pub end:         u32,
+	/// Start offset in UTF-16 code units.
+	#[napi(js_name = "startUtf16")]
+	pub start_utf16: u32,
+	/// End offset (exclusive) in UTF-16 code units.
+	#[napi(js_name = "endUtf16")]
+	pub end_utf16:   u32,
+	/// Whether the span was cut (partially or fully) by `maxColumns`.
+	pub truncated:   Option<bool>,
+}
+
 /// A single match in the content.
 #[napi(object)]
 pub struct Match {
@@ -132,6 +261,12 @@ pub struct Match {
 	pub line_number:    u32,
 	/// The matched line content.
 	pub line:           String,
+	/// Matched spans within the line.
+	pub submatches:     Option<Vec<Submatch>>,
+	/// Index of the first pattern that matched the line, counting `pattern`
+	/// (if set) before `patterns`. Only set when several patterns are given.
+	#[napi(js_name = "patternIndex")]
+	pub pattern_index:  Option<u32>,
 	/// Context lines before the match.
 	#[napi(js_name = "contextBefore")]
 	pub context_before: Option<Vec<ContextLine>>,
@@ -163,11 +298,21 @@ pub struct SearchResult {
 pub struct GrepMatch {
 	/// File path for the match (relative for directory searches).
 	pub path:           String,
+	/// Archive member containing the match (`searchCompressed` only).
+	pub member:         Option<String>,
+	/// Encoding the file was decoded from, when not UTF-8.
+	pub encoding:       Option<String>,
 	/// 1-indexed line number (0 for count-only entries).
 	#[napi(js_name = "lineNumber")]
 	pub line_number:    u32,
 	/// The matched line content (empty for count-only entries).
 	pub line:           String,
+	/// Matched spans within the line (content mode only).
+	pub submatches:     Option<Vec<Submatch>>,
+	/// Index of the first pattern that matched the line, counting `pattern`
+	/// (if set) before `patterns`. Only set when several patterns are given.
+	#[napi(js_name = "patternIndex")]
+	pub pattern_index:  Option<u32>,
 	/// Context lines before the match.
 	#[napi(js_name = "contextBefore")]
 	pub context_before: Option<Vec<ContextLine>>,
@@ -198,30 +343,113 @@ pub struct GrepResult {
 	/// Whether the limit/offset stopped the search early.
 	#[napi(js_name = "limitReached")]
 	pub limit_reached:      Option<bool>,
+	/// Files (or archive members) that could not be read, in path order.
+	pub errors:             Option<Vec<GrepFileError>>,
 }
 
-enum TypeFilter {
-	Known { exts: &'static [&'static str], names: &'static [&'static str] },
-	Custom(String),
+/// A file or archive member that could not be searched.
+#[napi(object)]
+pub struct GrepFileError {
+	/// File path (relative for directory searches).
+	pub path:    String,
+	/// Archive member that failed (`searchCompressed` only).
+	pub member:  Option<String>,
+	/// Error message.
+	pub message: String,
 }
 
-impl TypeFilter {
-	fn match_ext(&self, ext: &str) -> bool {
-		match self {
-			Self::Known { exts, .. } => exts.iter().any(|e| ext.eq_ignore_ascii_case(e)),
-			Self::Custom(custom_ext) => ext.eq_ignore_ascii_case(custom_ext),
-		}
-	}
+/// A file type known to the `type` filter.
+#[napi(object)]
+pub struct FileTypeDefinition {
+	/// Type name accepted by the `type` option.
+	pub name:  String,
+	/// Globs matched against file names.
+	pub globs: Vec<String>,
+}
 
-	fn match_name(&self, name: &str) -> bool {
-		match self {
-			Self::Known { names, .. } => names.iter().any(|n| name.eq_ignore_ascii_case(n)),
-			Self::Custom(ext) => ext.eq_ignore_ascii_case(name),
-		}
-	}
+/// Options for search-and-replace across files.
+#[napi(object)]
+pub struct ReplaceOptions<'env> {
+	/// Regex pattern to search for.
+	pub pattern:       String,
+	/// Replacement text. Supports `$1`, `${name}` and `$$` (literal `$`).
+	pub replacement:   String,
+	/// Directory or file to search.
+	pub path:          String,
+	/// Glob filter for filenames (e.g., "*.ts").
+	pub glob:          Option<String>,
+	/// Filter by file type (e.g., "js", "py", "rust"); several types may be
+	/// given and `!name` excludes a type.
+	#[napi(js_name = "type")]
+	pub type_filter:   Option<Either<String, Vec<String>>>,
+	/// Extra file type definitions as `name:glob` (e.g., "proto:*.proto").
+	#[napi(js_name = "typeAdd")]
+	pub type_add:      Option<Vec<String>>,
+	/// Case-insensitive search.
+	#[napi(js_name = "ignoreCase")]
+	pub ignore_case:   Option<bool>,
+	/// Enable multiline matching (replacements may span lines).
+	pub multiline:     Option<bool>,
+	/// Regex engine: "default", "fancy" (lookaround/backreferences), or
+	/// "auto" (default; falls back to fancy when the pattern needs it).
+	pub engine:        Option<String>,
+	/// Treat the pattern as a literal string instead of a regex.
+	#[napi(js_name = "fixedStrings")]
+	pub fixed_strings: Option<bool>,
+	/// Only match whole words.
+	#[napi(js_name = "wordRegexp")]
+	pub word_regexp:   Option<bool>,
+	/// Only match whole lines.
+	#[napi(js_name = "lineRegexp")]
+	pub line_regexp:   Option<bool>,
+	/// Case-insensitive unless the pattern contains an uppercase character.
+	#[napi(js_name = "smartCase")]
+	pub smart_case:    Option<bool>,
+	/// Include hidden files (default: true).
+	pub hidden:        Option<bool>,
+	/// Enable shared filesystem scan cache (default: false).
+	pub cache:         Option<bool>,
+	/// Write changes to disk (default: false, preview only).
+	pub apply:         Option<bool>,
+	/// Abort signal for cancelling the operation.
+	pub signal:        Option<Unknown<'env>>,
+	/// Timeout in milliseconds for the operation.
+	#[napi(js_name = "timeoutMs")]
+	pub timeout_ms:    Option<u32>,
 }
 
-struct MatchCollector {
+/// Per-file outcome of a replace operation.
+#[napi(object)]
+pub struct ReplaceFileResult {
+	/// File path (relative for directory searches).
+	pub path:         String,
+	/// Number of replaced matches in this file.
+	pub replacements: u32,
+	/// Unified diff of the change (preview mode only).
+	pub diff:         Option<String>,
+}
+
+/// Result of a replace operation.
+#[napi(object)]
+pub struct ReplaceResult {
+	/// Files with at least one replacement, sorted by path. With `apply`,
+	/// only the files that were rewritten.
+	pub files:              Vec<ReplaceFileResult>,
+	/// Total replacements across all files.
+	#[napi(js_name = "totalReplacements")]
+	pub total_replacements: u32,
+	/// Number of files searched.
+	#[napi(js_name = "filesSearched")]
+	pub files_searched:     u32,
+	/// Whether changes were written to disk.
+	pub applied:            bool,
+	/// Files that could not be rewritten, including those modified since they
+	/// were read (`apply` only).
+	pub errors:             Option<Vec<GrepFileError>>,
+}
+
+struct MatchCollector<'m> {
+	patterns:        &'m CompiledPatterns,
 	matches:         Vec<CollectedMatch>,
 	match_count:     u64,
 	collected_count: u64,
@@ -237,6 +465,8 @@ struct MatchCollector {
 struct CollectedMatch {
 	line_number:    u64,
 	line:           String,
+	submatches:     Vec<Submatch>,
+	pattern_index:  Option<u32>,
 	context_before: SmallVec<[ContextLine; 8]>,
 	context_after:  SmallVec<[ContextLine; 8]>,
 	truncated:      bool,
@@ -247,6 +477,7 @@ struct SearchResultInternal {
 	match_count:   u64,
 	collected:     u64,
 	limit_reached: bool,
+	encoding:      &'static encoding_rs::Encoding,
 }
 
 struct FileEntry {
@@ -254,20 +485,39 @@ struct FileEntry {
 	relative_path: String,
 }
 
+impl AsRef<Path> for FileEntry {
+	fn as_ref(&self) -> &Path {
+		&self.path
+	}
+}
+
 struct FileSearchResult {
 	relative_path: String,
-	matches:       Vec<CollectedMatch>,
-	match_count:   u64,
+	/// Whether the file could be opened.
+	searched:      bool,
+	streams:       Vec<StreamSearchResult>,
+	/// Streams that failed, after those searched before them.
+	errors:        Vec<GrepFileError>,
 }
 
-impl MatchCollector {
+/// Result for one searched stream: the file itself, or an archive member.
+struct StreamSearchResult {
+	member:      Option<String>,
+	encoding:    Option<String>,
+	matches:     Vec<CollectedMatch>,
+	match_count: u64,
+}
+
+impl<'m> MatchCollector<'m> {
 	fn new(
+		patterns: &'m CompiledPatterns,
 		max_count: Option<u64>,
 		offset: u64,
 		max_columns: Option<usize>,
 		collect_matches: bool,
 	) -> Self {
 		Self {
+			patterns,
 			matches: Vec::new(),
 			match_count: 0,
 			collected_count: 0,
@@ -292,6 +542,53 @@ impl MatchCollector {
 			_ => (line.to_string(), false),
 		}
 	}
+
+	/// Number of bytes of `line` kept verbatim by [`Self::truncate_line`].
+	const fn kept_len(&self, line: &str) -> usize {
+		match self.max_columns {
+			Some(max) if line.len() > max => line.floor_char_boundary(max.saturating_sub(3)),
+			_ => line.len(),
+		}
+	}
+
+	/// Re-runs the matcher over a matched block and maps each non-empty match
+	/// onto `line`, the decoded and trimmed form of `bytes`.
+	fn collect_submatches(&self, bytes: &[u8], line: &str) -> Vec<Submatch> {
+		let kept = self.kept_len(line);
+		let lossless = std::str::from_utf8(bytes).is_ok();
+		let decoded_offset = |offset: usize| {
+			let offset = if lossless {
+				offset
+			} else {
+				String::from_utf8_lossy(&bytes[..offset]).len()
+			};
+			offset.min(line.len())
+		};
+
+		let mut submatches = Vec::new();
+		let _ = self.patterns.matcher.find_iter(bytes, |m| {
+			if m.is_empty() {
+				return true;
+			}
+			let start = line.floor_char_boundary(decoded_offset(m.start()));
+			let end = line.ceil_char_boundary(decoded_offset(m.end()));
+			if start >= end {
+				return true;
+			}
+			let start_utf16 = line[..start].encode_utf16().count();
+			let end_utf16 = start_utf16 + line[start..end].encode_utf16().count();
+			submatches.push(Submatch {
+				text:        line[start..end].to_string(),
+				start:       clamp_u32(start as u64),
+				end:         clamp_u32(end as u64),
+				start_utf16: clamp_u32(start_utf16 as u64),
+				end_utf16:   clamp_u32(end_utf16 as u64),
+				truncated:   if end > kept { Some(true) } else { None },
+			});
+			true
+		});
+		submatches
+	}
 }
 
 fn bytes_to_trimmed_string(bytes: &[u8]) -> String {
@@ -301,7 +598,7 @@ fn bytes_to_trimmed_string(bytes: &[u8]) -> String {
 	}
 }
 
-impl Sink for MatchCollector {
+impl Sink for MatchCollector<'_> {
 	type Error = io::Error;
 
 	fn matched(
@@ -326,11 +623,15 @@ impl Sink for MatchCollector {
 		if self.collect_matches {
 			let raw_line = bytes_to_trimmed_string(mat.bytes());
 			let (line, truncated) = self.truncate_line(&raw_line);
+			let submatches = self.collect_submatches(mat.bytes(), &raw_line);
+			let pattern_index = self.patterns.pattern_index(mat.bytes());
 			let line_number = mat.line_number().unwrap_or(0);
 
 			self.matches.push(CollectedMatch {
 				line_number,
 				line,
+				submatches,
+				pattern_index,
 				context_before: std::mem::take(&mut self.context_before),
 				context_after: SmallVec::new(),
 				truncated,
@@ -395,6 +696,15 @@ fn parse_output_mode(mode: Option<&str>) -> OutputMode {
 	}
 }
 
+fn parse_sort_order(sort: Option<&str>) -> SortOrder {
+	match sort {
+		Some("mtime") => SortOrder::Mtime,
+		Some("matchCount") => SortOrder::MatchCount,
+		Some("relevance") => SortOrder::Relevance,
+		_ => SortOrder::Path,
+	}
+}
+
 fn resolve_search_path(path: &str) -> Result<PathBuf> {
 	let candidate = PathBuf::from(path);
 	if candidate.is_absolute() {
@@ -429,63 +739,6 @@ fn compile_glob(glob: Option<&str>) -> Result<Option<GlobSet>> {
 		.map_err(|err| Error::from_reason(format!("Failed to build glob matcher: {err}")))
 }
 
-fn resolve_type_filter(type_name: Option<&str>) -> Option<TypeFilter> {
-	let normalized = type_name
-		.map(str::trim)
-		.filter(|value| !value.is_empty())
-		.map(|value| value.trim_start_matches('.').to_lowercase())?;
-
-	let (exts, names): (&[&str], &[&str]) = match normalized.as_str() {
-		"js" | "javascript" => (&["js", "jsx", "mjs", "cjs"], &[]),
-		"ts" | "typescript" => (&["ts", "tsx", "mts", "cts"], &[]),
-		"json" => (&["json", "jsonc", "json5"], &[]),
-		"yaml" | "yml" => (&["yaml", "yml"], &[]),
-		"toml" => (&["toml"], &[]),
-		"md" | "markdown" => (&["md", "markdown", "mdx"], &[]),
-		"py" | "python" => (&["py", "pyi"], &[]),
-		"rs" | "rust" => (&["rs"], &[]),
-		"go" => (&["go"], &[]),
-		"java" => (&["java"], &[]),
-		"kt" | "kotlin" => (&["kt", "kts"], &[]),
-		"c" => (&["c", "h"], &[]),
-		"cpp" | "cxx" => (&["cpp", "cc", "cxx", "hpp", "hxx", "hh"], &[]),
-		"cs" | "csharp" => (&["cs", "csx"], &[]),
-		"php" => (&["php", "phtml"], &[]),
-		"rb" | "ruby" => (&["rb", "rake", "gemspec"], &[]),
-		"sh" | "bash" => (&["sh", "bash", "zsh"], &[]),
-		"zsh" => (&["zsh"], &[]),
-		"fish" => (&["fish"], &[]),
-		"html" => (&["html", "htm"], &[]),
-		"css" => (&["css"], &[]),
-		"scss" => (&["scss"], &[]),
-		"sass" => (&["sass"], &[]),
-		"less" => (&["less"], &[]),
-		"xml" => (&["xml"], &[]),
-		"docker" | "dockerfile" => (&[], &["dockerfile"]),
-		"make" | "makefile" => (&[], &["makefile"]),
-		_ => {
-			return Some(TypeFilter::Custom(normalized));
-		},
-	};
-
-	Some(TypeFilter::Known { exts, names })
-}
-
-fn matches_type_filter(path: &Path, filter: &TypeFilter) -> bool {
-	let base_name = path
-		.file_name()
-		.and_then(|name| name.to_str())
-		.unwrap_or("");
-	if filter.match_name(base_name) {
-		return true;
-	}
-	let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
-	if ext.is_empty() {
-		return false;
-	}
-	filter.match_ext(ext)
-}
-
 fn resolve_context(
 	context: Option<u32>,
 	context_before: Option<u32>,
@@ -499,63 +752,75 @@ fn resolve_context(
 	}
 }
 
-fn build_searcher(before_context: u32, after_context: u32) -> Searcher {
+fn build_searcher(params: &SearchParams, encoding: &'static encoding_rs::Encoding) -> Searcher {
+	let (before_context, after_context) = if params.mode == OutputMode::Content {
+		(params.context_before, params.context_after)
+	} else {
+		(0, 0)
+	};
 	SearcherBuilder::new()
 		.binary_detection(BinaryDetection::quit(b'\x00'))
 		.line_number(true)
+		.multi_line(params.multiline)
+		.invert_match(params.invert_match)
 		.before_context(before_context as usize)
 		.after_context(after_context as usize)
+		.encoding(if encoding == encoding_rs::UTF_8 {
+			None
+		} else {
+			grep_searcher::Encoding::new(encoding.name()).ok()
+		})
 		.build()
 }
 
 #[derive(Clone, Copy)]
 struct SearchParams {
+	multiline:      bool,
+	invert_match:   bool,
 	context_before: u32,
 	context_after:  u32,
 	max_columns:    Option<u32>,
 	mode:           OutputMode,
 	max_count:      Option<u64>,
 	offset:         u64,
+	encoding:       TextEncoding,
 }
 
 fn run_search(
-	matcher: &grep_regex::RegexMatcher,
+	patterns: &CompiledPatterns,
 	content: &[u8],
 	params: SearchParams,
 ) -> io::Result<SearchResultInternal> {
-	run_search_reader(matcher, Cursor::new(content), params)
+	run_search_reader(patterns, Cursor::new(content), params)
 }
 
-/// Stream-based search that reads directly from a `Read` without buffering.
+/// Stream-based search that reads directly from a `Read`, transcoding it to
+/// UTF-8 first when its detected encoding differs.
 fn run_search_reader<R: Read>(
-	matcher: &grep_regex::RegexMatcher,
+	patterns: &CompiledPatterns,
 	reader: R,
 	params: SearchParams,
 ) -> io::Result<SearchResultInternal> {
-	let mut searcher = build_searcher(
-		if params.mode == OutputMode::Content {
-			params.context_before
-		} else {
-			0
-		},
-		if params.mode == OutputMode::Content {
-			params.context_after
-		} else {
-			0
-		},
-	);
+	let mut reader = BufReader::new(reader);
+	let head = reader.fill_buf()?;
+	let encoding = params
+		.encoding
+		.detect(&head[..head.len().min(encoding::SNIFF_BYTES)]);
+	let mut searcher = build_searcher(&params, encoding);
 	let mut collector = MatchCollector::new(
+		patterns,
 		params.max_count,
 		params.offset,
 		params.max_columns.map(|v| v as usize),
 		params.mode == OutputMode::Content,
 	);
-	searcher.search_reader(matcher, reader, &mut collector)?;
+	searcher.search_reader(&patterns.matcher, reader, &mut collector)?;
 	Ok(SearchResultInternal {
-		matches:       collector.matches,
-		match_count:   collector.match_count,
-		collected:     collector.collected_count,
+		matches: collector.matches,
+		match_count: collector.match_count,
+		collected: collector.collected_count,
 		limit_reached: collector.limit_reached,
+		encoding,
 	})
 }
 
@@ -573,13 +838,20 @@ fn to_public_match(matched: CollectedMatch) -> Match {
 	Match {
 		line_number: clamp_u32(matched.line_number),
 		line: matched.line,
+		submatches: Some(matched.submatches),
+		pattern_index: matched.pattern_index,
 		context_before,
 		context_after,
 		truncated: if matched.truncated { Some(true) } else { None },
 	}
 }
 
-fn to_grep_match(path: &str, matched: CollectedMatch) -> GrepMatch {
+fn to_grep_match(
+	path: &str,
+	member: Option<&str>,
+	encoding: Option<&str>,
+	matched: CollectedMatch,
+) -> GrepMatch {
 	let context_before = if matched.context_before.is_empty() {
 		None
 	} else {
@@ -592,8 +864,12 @@ fn to_grep_match(path: &str, matched: CollectedMatch) -> GrepMatch {
 	};
 	GrepMatch {
 		path: path.to_string(),
+		member: member.map(str::to_string),
+		encoding: encoding.map(str::to_string),
 		line_number: clamp_u32(matched.line_number),
 		line: matched.line,
+		submatches: Some(matched.submatches),
+		pattern_index: matched.pattern_index,
 		context_before,
 		context_after,
 		truncated: if matched.truncated { Some(true) } else { None },
@@ -607,28 +883,45 @@ const fn empty_search_result(error: Option<String>) -> SearchResult {
 
 /// Internal configuration for grep, extracted from options.
 struct GrepConfig {
-	pattern:        String,
-	path:           String,
-	glob:           Option<String>,
-	type_filter:    Option<String>,
-	ignore_case:    Option<bool>,
-	multiline:      Option<bool>,
-	hidden:         Option<bool>,
-	cache:          Option<bool>,
-	max_count:      Option<u32>,
-	offset:         Option<u32>,
-	context_before: Option<u32>,
-	context_after:  Option<u32>,
-	context:        Option<u32>,
-	max_columns:    Option<u32>,
-	mode:           Option<String>,
+	pattern:           Option<String>,
+	patterns:          Option<Vec<String>>,
+	path:              String,
+	glob:              Option<String>,
+	type_filter:       Option<Either<String, Vec<String>>>,
+	type_add:          Option<Vec<String>>,
+	ignore_case:       Option<bool>,
+	multiline:         Option<bool>,
+	engine:            Option<String>,
+	fixed_strings:     Option<bool>,
+	word_regexp:       Option<bool>,
+	line_regexp:       Option<bool>,
+	smart_case:        Option<bool>,
+	invert_match:      Option<bool>,
+	exclude_patterns:  Option<Vec<String>>,
+	hidden:            Option<bool>,
+	ignore_files:      Option<Vec<String>>,
+	overrides:         Option<Vec<String>>,
+	max_depth:         Option<u32>,
+	follow_symlinks:   Option<bool>,
+	cache:             Option<bool>,
+	index:             Option<bool>,
+	search_compressed: Option<bool>,
+	encoding:          Option<String>,
+	max_count:         Option<u32>,
+	offset:            Option<u32>,
+	context_before:    Option<u32>,
+	context_after:     Option<u32>,
+	context:           Option<u32>,
+	max_columns:       Option<u32>,
+	mode:              Option<String>,
+	sort:              Option<String>,
 }
 
 fn collect_files(
 	root: &Path,
 	scanned_entries: &[fs_cache::GlobMatch],
 	glob_set: Option<&GlobSet>,
-	type_filter: Option<&TypeFilter>,
+	type_filter: Option<&Types>,
 ) -> Vec<FileEntry> {
 	let mut entries = Vec::new();
 	for entry in scanned_entries {
@@ -642,7 +935,7 @@ fn collect_files(
 		}
 		let path = root.join(&entry.path);
 		if let Some(filter) = type_filter
-			&& !matches_type_filter(&path, filter)
+			&& !matches_type_filter(filter, &path)
 		{
 			continue;
 		}
@@ -651,39 +944,155 @@ fn collect_files(
 	entries
 }
 
-fn build_matcher(
-	pattern: &str,
-	ignore_case: bool,
-	multiline: bool,
-) -> Result<grep_regex::RegexMatcher> {
-	RegexMatcherBuilder::new()
-		.case_insensitive(ignore_case)
-		.multi_line(multiline)
-		.build(pattern)
-		.map_err(|err| Error::from_reason(format!("Regex error: {err}")))
+/// Scans a directory root (optionally through the shared scan cache) and
+/// returns the files accepted by the glob and type filters.
+fn scan_files(
+	root: &Path,
+	options: &fs_cache::ScanOptions,
+	use_cache: bool,
+	glob_set: Option<&GlobSet>,
+	type_filter: Option<&Types>,
+	ct: &task::CancelToken,
+) -> Result<Vec<FileEntry>> {
+	if use_cache {
+		let scan = fs_cache::get_or_scan(root, options, ct)?;
+		let mut entries = collect_files(root, &scan.entries, glob_set, type_filter);
+		if entries.is_empty() && scan.cache_age_ms >= fs_cache::empty_recheck_ms() {
+			let fresh = fs_cache::force_rescan(root, options, true, ct)?;
+			entries = collect_files(root, &fresh, glob_set, type_filter);
+		}
+		Ok(entries)
+	} else {
+		let fresh = fs_cache::force_rescan(root, options, false, ct)?;
+		Ok(collect_files(root, &fresh, glob_set, type_filter))
+	}
+}
+
+/// How a pattern is interpreted, shared by all grep entry points.
+#[derive(Clone, Copy, Default)]
+struct PatternFlags {
+	ignore_case:   bool,
+	smart_case:    bool,
+	multiline:     bool,
+	fixed_strings: bool,
+	word:          bool,
+	whole_line:    bool,
+	engine:        Engine,
+}
+
+fn build_matcher<P: AsRef<str>>(patterns: &[P], flags: PatternFlags) -> Result<SearchMatcher> {
+	let build_fancy = || {
+		let ignore_case = flags.ignore_case
+			|| (flags.smart_case
+				&& !patterns
+					.iter()
+					.any(|pattern| has_uppercase_literal(pattern.as_ref(), flags.fixed_strings)));
+		let pattern = fancy_pattern(patterns, flags.fixed_strings, flags.word, flags.whole_line);
+		FancyMatcher::new(&pattern, ignore_case, flags.multiline)
+			.map(SearchMatcher::Fancy)
+			.map_err(|err| Error::from_reason(format!("Regex error: {err}")))
+	};
+	if flags.engine == Engine::Fancy {
+		return build_fancy();
+	}
+
+	match RegexMatcherBuilder::new()
+		.case_insensitive(flags.ignore_case)
+		.case_smart(flags.smart_case && !flags.ignore_case)
+		.multi_line(flags.multiline)
+		.fixed_strings(flags.fixed_strings)
+		.word(flags.word)
+		.whole_line(flags.whole_line)
+		.build_many(patterns)
+	{
+		Ok(matcher) => Ok(SearchMatcher::Regex(matcher)),
+		// Lookaround and backreferences are rejected by the default engine; retry
+		// with the backtracking engine but keep the original error if that fails too.
+		Err(err) if flags.engine == Engine::Auto => {
+			build_fancy().map_err(|_| Error::from_reason(format!("Regex error: {err}")))
+		},
+		Err(err) => Err(Error::from_reason(format!("Regex error: {err}"))),
+	}
+}
+
+/// Matcher for the alternation of all requested patterns, plus one matcher
+/// per pattern to attribute matched lines when there are several.
+struct CompiledPatterns {
+	matcher:      SearchMatcher,
+	alternatives: Vec<SearchMatcher>,
+}
+
+impl CompiledPatterns {
+	fn new(patterns: &[String], flags: PatternFlags) -> Result<Self> {
+		if patterns.is_empty() {
+			return Err(Error::from_reason("Either pattern or patterns is required"));
+		}
+		let matcher = build_matcher(patterns, flags)?;
+		let alternatives = if patterns.len() > 1 {
+			patterns
+				.iter()
+				.map(|pattern| build_matcher(&[pattern], flags))
+				.collect::<Result<_>>()?
+		} else {
+			Vec::new()
+		};
+		Ok(Self { matcher, alternatives })
+	}
+
+	fn pattern_index(&self, line: &[u8]) -> Option<u32> {
+		self
+			.alternatives
+			.iter()
+			.position(|matcher| matcher.is_match(line).unwrap_or(false))
+			.map(|index| clamp_u32(index as u64))
+	}
+}
+
+/// Name reported in `GrepMatch.encoding`; UTF-8 is the default and omitted.
+fn reported_encoding(encoding: &'static encoding_rs::Encoding) -> Option<String> {
+	(encoding != encoding_rs::UTF_8).then(|| encoding::display_name(encoding))
+}
+
+/// Merges `pattern` and `patterns` into one list, `pattern` first.
+fn collect_patterns(pattern: Option<String>, patterns: Option<Vec<String>>) -> Vec<String> {
+	pattern
+		.into_iter()
+		.chain(patterns.unwrap_or_default())
+		.collect()
 }
 
 fn run_parallel_search(
 	entries: &[FileEntry],
-	matcher: &grep_regex::RegexMatcher,
-	context_before: u32,
-	context_after: u32,
-	max_columns: Option<u32>,
-	mode: OutputMode,
+	patterns: &CompiledPatterns,
+	params: SearchParams,
+	search_compressed: bool,
+	ct: &task::CancelToken,
 ) -> Vec<FileSearchResult> {
-	let params =
-		SearchParams { context_before, context_after, max_columns, mode, max_count: None, offset: 0 };
 	let mut results: Vec<FileSearchResult> = entries
 		.par_iter()
-		.filter_map(|entry| {
-			let file = File::open(&entry.path).ok()?;
-			let reader = file.take(MAX_FILE_BYTES);
-			let search = run_search_reader(matcher, reader, params).ok()?;
-			Some(FileSearchResult {
-				relative_path: entry.relative_path.clone(),
-				matches:       search.matches,
-				match_count:   search.match_count,
-			})
+		.map(|entry| {
+			let mut searched = false;
+			let mut streams = Vec::new();
+			let mut errors = Vec::new();
+			let opened = open_streams(&entry.path, search_compressed, ct, |member, reader| {
+				searched = true;
+				match run_search_reader(patterns, reader, params) {
+					Ok(search) => streams.push(StreamSearchResult {
+						member,
+						encoding: reported_encoding(search.encoding),
+						matches: search.matches,
+						match_count: search.match_count,
+					}),
+					Err(err) => errors.push(file_error(&entry.relative_path, member, &err)),
+				}
+				Ok(true)
+			});
+			// Archive iteration errors end the file; members already searched
+			// are kept.
+			if let Err(err) = opened {
+				errors.push(file_error(&entry.relative_path, None, &err));
+			}
+			FileSearchResult { relative_path: entry.relative_path.clone(), searched, streams, errors }
 		})
 		.collect();
 
@@ -691,10 +1100,57 @@ fn run_parallel_search(
 	results
 }
 
+/// Drops the files in which `exclude` matches in any stream. Files that
+/// cannot be opened are kept for the main search to report.
+fn exclude_files(
+	entries: Vec<FileEntry>,
+	exclude: &CompiledPatterns,
+	params: SearchParams,
+	search_compressed: bool,
+	ct: &task::CancelToken,
+) -> Vec<FileEntry> {
+	entries
+		.into_par_iter()
+		.filter(|entry| {
+			let mut excluded = false;
+			let _ = open_streams(&entry.path, search_compressed, ct, |_, reader| {
+				excluded = run_search_reader(exclude, reader, params)
+					.is_ok_and(|search| search.match_count > 0);
+				Ok(!excluded)
+			});
+			!excluded
+		})
+		.collect()
+}
+
+fn file_error(path: &str, member: Option<String>, err: &io::Error) -> GrepFileError {
+	GrepFileError { path: path.to_string(), member, message: err.to_string() }
+}
+
+/// Runs `visit` on the content of `path`, capped at `MAX_FILE_BYTES`. With
+/// `search_compressed`, compressed files are decoded first and archives are
+/// visited once per member.
+fn open_streams(
+	path: &Path,
+	search_compressed: bool,
+	ct: &task::CancelToken,
+	mut visit: impl FnMut(Option<String>, &mut dyn Read) -> io::Result<bool>,
+) -> io::Result<()> {
+	let file = File::open(path)?;
+	if search_compressed {
+		decompress::for_each_stream(file, MAX_FILE_BYTES, ct, visit)
+	} else {
+		visit(None, &mut file.take(MAX_FILE_BYTES)).map(drop)
+	}
+}
+
 fn run_sequential_search(
 	entries: &[FileEntry],
-	matcher: &grep_regex::RegexMatcher,
+	patterns: &CompiledPatterns,
 	params: SearchParams,
+	search_compressed: bool,
+	errors: &mut Vec<GrepFileError>,
+	ct: &task::CancelToken,
 ) -> (Vec<GrepMatch>, u64, u32, u32, bool) {
 	let SearchParams { mode, max_count, offset, .. } = params;
 	let mut matches = Vec::new();
@@ -705,72 +1161,166 @@ fn run_sequential_search(
 	let mut limit_reached = false;
 
 	for entry in entries {
-		if limit_reached {
-			break;
-		}
-
-		// Calculate offset for this file (skip matches we've already seen)
-		let file_offset = offset.saturating_sub(total_matches);
-		// Calculate remaining based on collected count, not total matches
-		let remaining = max_count.map(|max| max.saturating_sub(collected));
-		if remaining == Some(0) {
+		if limit_reached || max_count.is_some_and(|max| collected >= max) {
 			limit_reached = true;
 			break;
 		}
 
 		// Open file and search directly - no intermediate buffer, no precheck scan
-		let Ok(file) = File::open(&entry.path) else {
-			continue;
-		};
-		files_searched = files_searched.saturating_add(1);
-		let reader = file.take(MAX_FILE_BYTES);
+		let mut opened = false;
+		let mut file_matched = false;
+		let result = open_streams(&entry.path, search_compressed, ct, |member, reader| {
+			opened = true;
+			// Skip matches already seen in earlier files or members, and only
+			// collect what is left of the limit.
+			let file_offset = offset.saturating_sub(total_matches);
+			let remaining = max_count.map(|max| max.saturating_sub(collected));
+			let file_params = SearchParams { max_count: remaining, offset: file_offset, ..params };
+			let search = match run_search_reader(patterns, reader, file_params) {
+				Ok(search) => search,
+				Err(err) => {
+					errors.push(file_error(&entry.relative_path, member, &err));
+					return Ok(true);
+				},
+			};
 
-		let file_params = SearchParams { max_count: remaining, offset: file_offset, ..params };
-		let Ok(search) = run_search_reader(matcher, reader, file_params) else {
-			continue;
-		};
+			if search.match_count == 0 {
+				return Ok(true);
+			}
 
-		if search.match_count == 0 {
-			continue;
-		}
+			file_matched = true;
+			let encoding = reported_encoding(search.encoding);
+			total_matches = total_matches.saturating_add(search.match_count);
+			collected = collected.saturating_add(search.collected);
 
-		files_with_matches = files_with_matches.saturating_add(1);
-		total_matches = total_matches.saturating_add(search.match_count);
-		collected = collected.saturating_add(search.collected);
+			match mode {
+				OutputMode::Content => {
+					for matched in search.matches {
+						matches.push(to_grep_match(
+							&entry.relative_path,
+							member.as_deref(),
+							encoding.as_deref(),
+							matched,
+						));
+					}
+				},
+				OutputMode::Count => {
+					matches.push(GrepMatch {
+						path: entry.relative_path.clone(),
+						member,
+						encoding,
+						line_number: 0,
+						line: String::new(),
+						submatches: None,
+						pattern_index: None,
+						context_before: None,
+						context_after: None,
+						truncated: None,
+						match_count: Some(clamp_u32(search.match_count)),
+					});
+				},
+			}
 
-		match mode {
-			OutputMode::Content => {
-				for matched in search.matches {
-					matches.push(to_grep_match(&entry.relative_path, matched));
-				}
-			},
-			OutputMode::Count => {
-				matches.push(GrepMatch {
-					path:           entry.relative_path.clone(),
-					line_number:    0,
-					line:           String::new(),
-					context_before: None,
-					context_after:  None,
-					truncated:      None,
-					match_count:    Some(clamp_u32(search.match_count)),
-				});
-			},
+			if search.limit_reached || max_count.is_some_and(|max| collected >= max) {
+				limit_reached = true;
+			}
+			Ok(!limit_reached)
+		});
+		if let Err(err) = result {
+			errors.push(file_error(&entry.relative_path, None, &err));
 		}
 
-		if search.limit_reached || max_count.is_some_and(|max| collected >= max) {
-			limit_reached = true;
+		if opened {
+			files_searched = files_searched.saturating_add(1);
+		}
+		if file_matched {
+			files_with_matches = files_with_matches.saturating_add(1);
 		}
 	}
 
 	(matches, total_matches, files_with_matches, files_searched, limit_reached)
 }
 
+/// Finds the files with matches and orders them by `sort`, best first, with
+/// ties kept in path order. Returns them with the number of files searched
+/// and the files that could not be read.
+///
+/// Matching files are searched again afterwards so that `offset` and
+/// `maxCount` can be applied in the new order.
+fn rank_entries(
+	entries: Vec<FileEntry>,
+	patterns: &CompiledPatterns,
+	params: SearchParams,
+	search_compressed: bool,
+	sort: SortOrder,
+	ct: &task::CancelToken,
+) -> (Vec<FileEntry>, u32, Vec<GrepFileError>) {
+	let results = run_parallel_search(&entries, patterns, params, search_compressed, ct);
+	let files_searched = clamp_u32(results.iter().filter(|result| result.searched).count() as u64);
+	let mut errors = Vec::new();
+	let counts: HashMap<String, u64> = results
+		.into_iter()
+		.map(|mut result| {
+			errors.append(&mut result.errors);
+			let matches = result.streams.iter().map(|stream| stream.match_count).sum();
+			(result.relative_path, matches)
+		})
+		.filter(|&(_, matches)| matches > 0)
+		.collect();
+
+	let now = SystemTime::now();
+	let mut ranked: Vec<(f64, FileEntry)> = entries
+		.into_iter()
+		.filter_map(|entry| {
+			let matches = *counts.get(&entry.relative_path)?;
+			let metadata = std::fs::metadata(&entry.path).ok();
+			let modified = metadata.as_ref().and_then(|meta| meta.modified().ok());
+			let score = match sort {
+				SortOrder::Path | SortOrder::MatchCount => matches as f64,
+				SortOrder::Mtime => modified
+					.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+					.map_or(f64::NEG_INFINITY, |since| since.as_secs_f64()),
+				SortOrder::Relevance => {
+					let age_days = modified
+						.and_then(|time| now.duration_since(time).ok())
+						.map_or(f64::INFINITY, |age| age.as_secs_f64() / 86_400.0);
+					let size = metadata.map_or(0, |meta| meta.len());
+					let depth = entry.relative_path.matches('/').count();
+					relevance_score(matches, size, depth, age_days)
+				},
+			};
+			Some((score, entry))
+		})
+		.collect();
+	ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+	(ranked.into_iter().map(|(_, entry)| entry).collect(), files_searched, errors)
+}
+
+/// Ranks dense matches in shallow, recently modified files first. Each term
+/// is roughly within `0..=1`, except density, which grows logarithmically.
+fn relevance_score(matches: u64, size: u64, depth: usize, age_days: f64) -> f64 {
+	let density = (matches as f64 / (1.0 + size as f64 / 1024.0)).ln_1p();
+	let shallowness = 1.0 / (1.0 + depth as f64);
+	let recency = 1.0 / (1.0 + age_days / 7.0);
+	density + shallowness + recency
+}
+
 fn search_sync(content: &[u8], options: SearchOptions) -> SearchResult {
-	let ignore_case = options.ignore_case.unwrap_or(false);
 	let multiline = options.multiline.unwrap_or(false);
 	let mode = parse_output_mode(options.mode.as_deref());
-	let matcher = match build_matcher(&options.pattern, ignore_case, multiline) {
-		Ok(matcher) => matcher,
+	let flags = PatternFlags {
+		ignore_case: options.ignore_case.unwrap_or(false),
+		smart_case: options.smart_case.unwrap_or(false),
+		multiline,
+		fixed_strings: options.fixed_strings.unwrap_or(false),
+		word: options.word_regexp.unwrap_or(false),
+		whole_line: options.line_regexp.unwrap_or(false),
+		engine: Engine::parse(options.engine.as_deref()),
+	};
+	let invert_match = options.invert_match.unwrap_or(false);
+	let patterns = collect_patterns(options.pattern, options.patterns);
+	let patterns = match CompiledPatterns::new(&patterns, flags) {
+		Ok(patterns) => patterns,
 		Err(err) => return empty_search_result(Some(err.to_string())),
 	};
 
@@ -779,10 +1329,19 @@ fn search_sync(content: &[u8], options: SearchOptions) -> SearchResult {
 	let max_columns = options.max_columns;
 	let max_count = options.max_count.map(u64::from);
 	let offset = options.offset.unwrap_or(0) as u64;
-	let params =
-		SearchParams { context_before, context_after, max_columns, mode, max_count, offset };
+	let params = SearchParams {
+		multiline,
+		invert_match,
+		context_before,
+		context_after,
+		max_columns,
+		mode,
+		max_count,
+		offset,
+		encoding: TextEncoding::Auto,
+	};
 
-	let result = match run_search(&matcher, content, params) {
+	let result = match run_search(&patterns, content, params) {
 		Ok(result) => result,
 		Err(err) => return empty_search_result(Some(err.to_string())),
 	};
@@ -803,10 +1362,30 @@ fn grep_sync(
 	let search_path = resolve_search_path(&options.path)?;
 	let metadata = std::fs::metadata(&search_path)
 		.map_err(|err| Error::from_reason(format!("Path not found: {err}")))?;
-	let ignore_case = options.ignore_case.unwrap_or(false);
 	let multiline = options.multiline.unwrap_or(false);
 	let output_mode = parse_output_mode(options.mode.as_deref());
-	let matcher = build_matcher(&options.pattern, ignore_case, multiline)?;
+	let invert_match = options.invert_match.unwrap_or(false);
+	let pattern_list = collect_patterns(options.pattern, options.patterns);
+	let ignore_case = options.ignore_case.unwrap_or(false);
+	let smart_case = options.smart_case.unwrap_or(false);
+	let fixed_strings = options.fixed_strings.unwrap_or(false);
+	let flags = PatternFlags {
+		ignore_case,
+		smart_case,
+		multiline,
+		fixed_strings,
+		word: options.word_regexp.unwrap_or(false),
+		whole_line: options.line_regexp.unwrap_or(false),
+		engine: Engine::parse(options.engine.as_deref()),
+	};
+	let patterns = CompiledPatterns::new(&pattern_list, flags)?;
+	let exclude = match options.exclude_patterns.filter(|list| !list.is_empty()) {
+		Some(list) => Some(CompiledPatterns {
+			matcher:      build_matcher(&list, flags)?,
+			alternatives: Vec::new(),
+		}),
+		None => None,
+	};
 
 	let (context_before, context_after) =
 		resolve_context(options.context, options.context_before, options.context_after);
@@ -819,160 +1398,183 @@ fn grep_sync(
 	let max_count = options.max_count.map(u64::from);
 	let offset = options.offset.unwrap_or(0) as u64;
 	let include_hidden = options.hidden.unwrap_or(true);
+	let scan_options = fs_cache::ScanOptions::new(include_hidden, true).with_walk_options(
+		options.ignore_files,
+		options.overrides,
+		options.max_depth,
+		options.follow_symlinks,
+	)?;
 	let use_cache = options.cache.unwrap_or(false);
+	let use_index = options.index.unwrap_or(false);
+	let search_compressed = options.search_compressed.unwrap_or(false);
+	let encoding = TextEncoding::parse(options.encoding.as_deref())?;
 	let glob_set = compile_glob(options.glob.as_deref())?;
-	let type_filter = resolve_type_filter(options.type_filter.as_deref());
+	let type_filter = build_type_filter(options.type_filter, options.type_add.as_deref())?;
 
-	if metadata.is_file() {
+	let empty_result = |files_searched| GrepResult {
+		matches: Vec::new(),
+		total_matches: 0,
+		files_with_matches: 0,
+		files_searched,
+		limit_reached: None,
+		errors: None,
+	};
+	let entries = if metadata.is_file() {
 		if let Some(filter) = type_filter.as_ref()
-			&& !matches_type_filter(&search_path, filter)
+			&& !matches_type_filter(filter, &search_path)
 		{
-			return Ok(GrepResult {
-				matches:            Vec::new(),
-				total_matches:      0,
-				files_with_matches: 0,
-				files_searched:     0,
-				limit_reached:      None,
-			});
-		}
-
-		let Ok(file) = File::open(&search_path) else {
-			return Ok(GrepResult {
-				matches:            Vec::new(),
-				total_matches:      0,
-				files_with_matches: 0,
-				files_searched:     0,
-				limit_reached:      None,
-			});
-		};
-		let reader = file.take(MAX_FILE_BYTES);
-
-		let params = SearchParams {
-			context_before,
-			context_after,
-			max_columns,
-			mode: output_mode,
-			max_count,
-			offset,
-		};
-		let search = run_search_reader(&matcher, reader, params)
-			.map_err(|err| Error::from_reason(format!("Search failed: {err}")))?;
-
-		if search.match_count == 0 {
-			return Ok(GrepResult {
-				matches:            Vec::new(),
-				total_matches:      0,
-				files_with_matches: 0,
-				files_searched:     1,
-				limit_reached:      None,
-			});
+			return Ok(empty_result(0));
 		}
-
-		let path_string = search_path.to_string_lossy().to_string();
-		let mut matches = Vec::new();
-		match output_mode {
-			OutputMode::Content => {
-				for matched in search.matches {
-					matches.push(to_grep_match(&path_string, matched));
-				}
-			},
-			OutputMode::Count => {
-				matches.push(GrepMatch {
-					path:           path_string,
-					line_number:    0,
-					line:           String::new(),
-					context_before: None,
-					context_after:  None,
-					truncated:      None,
-					match_count:    Some(clamp_u32(search.match_count)),
-				});
-			},
+		let relative_path = search_path.to_string_lossy().to_string();
+		vec![FileEntry { path: search_path, relative_path }]
+	} else {
+		let entries = scan_files(
+			&search_path,
+			&scan_options,
+			use_cache,
+			glob_set.as_ref(),
+			type_filter.as_ref(),
+			&ct,
+		)?;
+		// Check cancellation before heavy work
+		ct.heartbeat()?;
+		// The index covers raw file bytes only, so it cannot rule out matches in
+		// decompressed or explicitly transcoded content, or inverted matches.
+		let entries =
+			if use_index && !invert_match && !search_compressed && encoding == TextEncoding::Auto {
+				let query =
+					Query::for_patterns(&pattern_list, fixed_strings, ignore_case || smart_case);
+				content_index::filter_candidates(
+					&search_path,
+					include_hidden,
+					true,
+					entries,
+					&query,
+					MAX_FILE_BYTES,
+					&ct,
+				)?
+			} else {
+				entries
+			};
+		if entries.is_empty() {
+			return Ok(empty_result(0));
 		}
-
-		let limit_reached =
-			search.limit_reached || max_count.is_some_and(|max| search.collected >= max);
-
-		return Ok(GrepResult {
-			matches,
-			total_matches: clamp_u32(search.match_count),
-			files_with_matches: 1,
-			files_searched: 1,
-			limit_reached: if limit_reached { Some(true) } else { None },
-		});
-	}
-
-	let entries = if use_cache {
-		let scan = fs_cache::get_or_scan(&search_path, include_hidden, true, &ct)?;
-		let mut entries =
-			collect_files(&search_path, &scan.entries, glob_set.as_ref(), type_filter.as_ref());
-		if entries.is_empty() && scan.cache_age_ms >= fs_cache::empty_recheck_ms() {
-			let fresh = fs_cache::force_rescan(&search_path, include_hidden, true, true, &ct)?;
-			entries = collect_files(&search_path, &fresh, glob_set.as_ref(), type_filter.as_ref());
+		entries
+	};
+	let entries = if let Some(exclude) = &exclude {
+		let exclude_params = SearchParams {
+			multiline,
+			invert_match: false,
+			context_before: 0,
+			context_after: 0,
+			max_columns: None,
+			mode: OutputMode::Count,
+			max_count: Some(1),
+			offset: 0,
+			encoding,
+		};
+		let entries = exclude_files(entries, exclude, exclude_params, search_compressed, &ct);
+		ct.heartbeat()?;
+		if entries.is_empty() {
+			return Ok(empty_result(0));
 		}
 		entries
 	} else {
-		let fresh = fs_cache::force_rescan(&search_path, include_hidden, true, false, &ct)?;
-		collect_files(&search_path, &fresh, glob_set.as_ref(), type_filter.as_ref())
+		entries
+	};
+
+	let sort = parse_sort_order(options.sort.as_deref());
+	let (entries, ranked) = if sort == SortOrder::Path {
+		(entries, None)
+	} else {
+		let count_params = SearchParams {
+			multiline,
+			invert_match,
+			context_before: 0,
+			context_after: 0,
+			max_columns,
+			mode: OutputMode::Count,
+			max_count: None,
+			offset: 0,
+			encoding,
+		};
+		let (ranked, files_searched, errors) =
+			rank_entries(entries, &patterns, count_params, search_compressed, sort, &ct);
+		ct.heartbeat()?;
+		(ranked, Some((files_searched, errors)))
 	};
-	// Check cancellation before heavy work
-	ct.heartbeat()?;
-	if entries.is_empty() {
-		return Ok(GrepResult {
-			matches:            Vec::new(),
-			total_matches:      0,
-			files_with_matches: 0,
-			files_searched:     0,
-			limit_reached:      None,
-		});
-	}
 
-	let allow_parallel = max_count.is_none() && offset == 0;
+	let allow_parallel = sort == SortOrder::Path && max_count.is_none() && offset == 0;
 	if allow_parallel {
-		let results = run_parallel_search(
-			&entries,
-			&matcher,
+		let params = SearchParams {
+			multiline,
+			invert_match,
 			context_before,
 			context_after,
 			max_columns,
-			output_mode,
-		);
+			mode: output_mode,
+			max_count: None,
+			offset: 0,
+			encoding,
+		};
+		let results = run_parallel_search(&entries, &patterns, params, search_compressed, &ct);
+		ct.heartbeat()?;
 		let mut matches = Vec::new();
 		let mut total_matches = 0u64;
 		let mut files_with_matches = 0u32;
-		let files_searched = clamp_u32(results.len() as u64);
+		let files_searched =
+			clamp_u32(results.iter().filter(|result| result.searched).count() as u64);
+		let mut errors = Vec::new();
 
-		for result in results {
-			if result.match_count == 0 {
+		for mut result in results {
+			errors.append(&mut result.errors);
+			if result.streams.iter().all(|stream| stream.match_count == 0) {
 				continue;
 			}
 			files_with_matches = files_with_matches.saturating_add(1);
-			total_matches = total_matches.saturating_add(result.match_count);
 
-			match output_mode {
-				OutputMode::Content => {
-					for matched in result.matches {
-						let grep_match = to_grep_match(&result.relative_path, matched);
+			for stream in result.streams {
+				if stream.match_count == 0 {
+					continue;
+				}
+				total_matches = total_matches.saturating_add(stream.match_count);
+
+				match output_mode {
+					OutputMode::Content => {
+						for matched in stream.matches {
+							let grep_match = to_grep_match(
+								&result.relative_path,
+								stream.member.as_deref(),
+								stream.encoding.as_deref(),
+								matched,
+							);
+							if let Some(callback) = on_match {
+								callback
+									.call(Ok(grep_match.clone()), ThreadsafeFunctionCallMode::NonBlocking);
+							}
+							matches.push(grep_match);
+						}
+					},
+					OutputMode::Count => {
+						let grep_match = GrepMatch {
+							path:           result.relative_path.clone(),
+							member:         stream.member,
+							encoding:       stream.encoding,
+							line_number:    0,
+							line:           String::new(),
+							submatches:     None,
+							pattern_index:  None,
+							context_before: None,
+							context_after:  None,
+							truncated:      None,
+							match_count:    Some(clamp_u32(stream.match_count)),
+						};
 						if let Some(callback) = on_match {
 							callback.call(Ok(grep_match.clone()), ThreadsafeFunctionCallMode::NonBlocking);
 						}
 						matches.push(grep_match);
-					}
-				},
-				OutputMode::Count => {
-					let grep_match = GrepMatch {
-						path:           result.relative_path.clone(),
-						line_number:    0,
-						line:           String::new(),
-						context_before: None,
-						context_after:  None,
-						truncated:      None,
-						match_count:    Some(clamp_u32(result.match_count)),
-					};
-					if let Some(callback) = on_match {
-						callback.call(Ok(grep_match.clone()), ThreadsafeFunctionCallMode::NonBlocking);
-					}
-					matches.push(grep_match);
-				},
+					},
+				}
 			}
 		}
 
@@ -982,18 +1584,28 @@ fn grep_sync(
 			files_with_matches,
 			files_searched,
 			limit_reached: None,
+			errors: (!errors.is_empty()).then_some(errors),
 		});
 	}
 
+	let params = SearchParams {
+		multiline,
+		invert_match,
+		context_before,
+		context_after,
+		max_columns,
+		mode: output_mode,
+		max_count,
+		offset,
+		encoding,
+	};
+	let mut errors = Vec::new();
 	let (matches, total_matches, files_with_matches, files_searched, limit_reached) =
-		run_sequential_search(&entries, &matcher, SearchParams {
-			context_before,
-			context_after,
-			max_columns,
-			mode: output_mode,
-			max_count,
-			offset,
-		});
+		run_sequential_search(&entries, &patterns, params, search_compressed, &mut errors, &ct);
+	ct.heartbeat()?;
+	// Ranked searches only revisit files that matched in the counting pass,
+	// which already saw every file.
+	let (files_searched, errors) = ranked.unwrap_or((files_searched, errors));
 
 	// Fire callbacks for sequential search results
 	if let Some(callback) = on_match {
@@ -1008,6 +1620,7 @@ fn grep_sync(
 		files_with_matches,
 		files_searched,
 		limit_reached: if limit_reached { Some(true) } else { None },
+		errors: (!errors.is_empty()).then_some(errors),
 	})
 }
 
@@ -1076,10 +1689,24 @@ pub fn has_match(
 		},
 	};
 
-	let matcher = build_matcher(pattern_ref, ignore_case, multiline)?;
+	let matcher =
+		build_matcher(&[pattern_ref], PatternFlags { ignore_case, multiline, ..Default::default() })?;
 	Ok(matcher.is_match(content_slice).unwrap_or(false))
 }
 
+/// List the file types accepted by the `type` option of `grep()` and
+/// `replace()`.
+///
+/// # Returns
+/// Ripgrep's default type definitions, sorted by name.
+#[napi(js_name = "listFileTypes")]
+pub fn list_file_types() -> Vec<FileTypeDefinition> {
+	default_definitions()
+		.into_iter()
+		.map(|def| FileTypeDefinition { name: def.name().to_string(), globs: def.globs().to_vec() })
+		.collect()
+}
+
 /// Search files for a regex pattern.
 ///
 /// # Arguments
@@ -1097,13 +1724,29 @@ pub fn grep(
 ) -> task::Async<GrepResult> {
 	let GrepOptions {
 		pattern,
+		patterns,
 		path,
 		glob,
 		type_filter,
+		type_add,
 		ignore_case,
 		multiline,
+		engine,
+		fixed_strings,
+		word_regexp,
+		line_regexp,
+		smart_case,
+		invert_match,
+		exclude_patterns,
 		hidden,
+		ignore_files,
+		overrides,
+		max_depth,
+		follow_symlinks,
 		cache,
+		index,
+		search_compressed,
+		encoding,
 		max_count,
 		offset,
 		context_before,
@@ -1111,19 +1754,36 @@ pub fn grep(
 		context,
 		max_columns,
 		mode,
+		sort,
 		timeout_ms,
 		signal,
 	} = options;
 
 	let config = GrepConfig {
 		pattern,
+		patterns,
 		path,
 		glob,
 		type_filter,
+		type_add,
 		ignore_case,
 		multiline,
+		engine,
+		fixed_strings,
+		word_regexp,
+		line_regexp,
+		smart_case,
+		invert_match,
+		exclude_patterns,
 		hidden,
+		ignore_files,
+		overrides,
+		max_depth,
+		follow_symlinks,
 		cache,
+		index,
+		search_compressed,
+		encoding,
 		max_count,
 		offset,
 		context_before,
@@ -1131,8 +1791,764 @@ pub fn grep(
 		context,
 		max_columns,
 		mode,
+		sort,
 	};
 
 	let ct = task::CancelToken::new(timeout_ms, signal);
 	task::blocking("grep", ct, move |ct| grep_sync(config, on_match.as_ref(), ct))
 }
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Replace
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Internal configuration for replace, extracted from options.
+struct ReplaceConfig {
+	pattern:       String,
+	replacement:   String,
+	path:          String,
+	glob:          Option<String>,
+	type_filter:   Option<Either<String, Vec<String>>>,
+	type_add:      Option<Vec<String>>,
+	ignore_case:   Option<bool>,
+	multiline:     Option<bool>,
+	engine:        Option<String>,
+	fixed_strings: Option<bool>,
+	word_regexp:   Option<bool>,
+	line_regexp:   Option<bool>,
+	smart_case:    Option<bool>,
+	hidden:        Option<bool>,
+	cache:         Option<bool>,
+	apply:         Option<bool>,
+}
+
+/// A file whose content changed after substitution.
+struct FileReplacement {
+	path:          PathBuf,
+	relative_path: String,
+	original:      Vec<u8>,
+	replaced:      Vec<u8>,
+	replacements:  u64,
+	/// Size and modification time when read, to detect later changes.
+	len:           u64,
+	modified:      Option<SystemTime>,
+}
+
+/// Replaces every match in `haystack`, expanding capture references in
+/// `replacement`. Returns the number of substitutions made.
+fn replace_all(
+	matcher: &SearchMatcher,
+	haystack: &[u8],
+	replacement: &[u8],
+	dst: &mut Vec<u8>,
+) -> io::Result<u64> {
+	let mut count = 0u64;
+	let mut caps = matcher.new_captures().map_err(io::Error::other)?;
+	matcher
+		.replace_with_captures(haystack, &mut caps, dst, |caps, dst| {
+			caps.interpolate(|name| matcher.capture_index(name), haystack, replacement, dst);
+			count += 1;
+			true
+		})
+		.map_err(io::Error::other)?;
+	Ok(count)
+}
+
+/// Applies the substitution to a whole file. Outside multiline mode matches
+/// are confined to single lines, mirroring how `grep()` reports them.
+fn replace_content(
+	matcher: &SearchMatcher,
+	content: &[u8],
+	replacement: &[u8],
+	multiline: bool,
+) -> io::Result<Option<(Vec<u8>, u64)>> {
+	if !matcher.is_match(content).map_err(io::Error::other)? {
+		return Ok(None);
+	}
+
+	let mut replaced = Vec::with_capacity(content.len());
+	let count = if multiline {
+		replace_all(matcher, content, replacement, &mut replaced)?
+	} else {
+		let mut count = 0u64;
+		for line in content.split_inclusive(|&b| b == b'\n') {
+			let mut body_len = line.len();
+			if line.ends_with(b"\n") {
+				body_len -= 1;
+				if line[..body_len].ends_with(b"\r") {
+					body_len -= 1;
+				}
+			}
+			let (body, terminator) = line.split_at(body_len);
+			count += replace_all(matcher, body, replacement, &mut replaced)?;
+			replaced.extend_from_slice(terminator);
+		}
+		count
+	};
+
+	if count == 0 || replaced == content {
+		return Ok(None);
+	}
+	Ok(Some((replaced, count)))
+}
+
+/// Reads a candidate file and computes its replacement, skipping oversized
+/// and binary files.
+fn replace_file(
+	entry: &FileEntry,
+	matcher: &SearchMatcher,
+	replacement: &[u8],
+	multiline: bool,
+) -> Option<FileReplacement> {
+	let metadata = std::fs::metadata(&entry.path).ok()?;
+	if metadata.len() > MAX_FILE_BYTES {
+		return None;
+	}
+	let original = std::fs::read(&entry.path).ok()?;
+	if original.contains(&b'\0') {
+		return None;
+	}
+	let (replaced, replacements) =
+		replace_content(matcher, &original, replacement, multiline).ok()??;
+	Some(FileReplacement {
+		path: entry.path.clone(),
+		relative_path: entry.relative_path.clone(),
+		original,
+		replaced,
+		replacements,
+		len: metadata.len(),
+		modified: metadata.modified().ok(),
+	})
+}
+
+fn unified_diff(relative_path: &str, original: &[u8], replaced: &[u8]) -> String {
+	let original = String::from_utf8_lossy(original);
+	let replaced = String::from_utf8_lossy(replaced);
+	similar::TextDiff::from_lines(original.as_ref(), replaced.as_ref())
+		.unified_diff()
+		.context_radius(3)
+		.header(&format!("a/{relative_path}"), &format!("b/{relative_path}"))
+		.to_string()
+}
+
+/// Writes `content` next to `path` and renames it into place, preserving the
+/// original permissions. Symlinks are resolved first so the link's target is
+/// rewritten and the link itself is kept.
+fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+	let path = &std::fs::canonicalize(path)?;
+	let parent = path.parent().unwrap_or_else(|| Path::new("."));
+	let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+	let temp_path = parent.join(format!(".{file_name}.{}.tmp", std::process::id()));
+
+	let result = (|| {
+		let mut file = File::create(&temp_path)?;
+		file.write_all(content)?;
+		file.sync_all()?;
+		if let Ok(metadata) = std::fs::metadata(path) {
+			std::fs::set_permissions(&temp_path, metadata.permissions())?;
+		}
+		std::fs::rename(&temp_path, path)
+	})();
+	if result.is_err() {
+		let _ = std::fs::remove_file(&temp_path);
+	}
+	result
+}
+
+/// Rewrites the file of `change`, unless its size or modification time
+/// changed since it was read.
+fn apply_replacement(change: &FileReplacement) -> io::Result<()> {
+	let metadata = std::fs::metadata(&change.path)?;
+	if metadata.len() != change.len || metadata.modified().ok() != change.modified {
+		return Err(io::Error::other("file changed since it was read"));
+	}
+	write_atomic(&change.path, &change.replaced)
+}
+
+fn replace_sync(config: ReplaceConfig, ct: task::CancelToken) -> Result<ReplaceResult> {
+	let search_path = resolve_search_path(&config.path)?;
+	let metadata = std::fs::metadata(&search_path)
+		.map_err(|err| Error::from_reason(format!("Path not found: {err}")))?;
+	let multiline = config.multiline.unwrap_or(false);
+	let apply = config.apply.unwrap_or(false);
+	let matcher = build_matcher(&[&config.pattern], PatternFlags {
+		ignore_case: config.ignore_case.unwrap_or(false),
+		smart_case: config.smart_case.unwrap_or(false),
+		multiline,
+		fixed_strings: config.fixed_strings.unwrap_or(false),
+		word: config.word_regexp.unwrap_or(false),
+		whole_line: config.line_regexp.unwrap_or(false),
+		engine: Engine::parse(config.engine.as_deref()),
+	})?;
+	let glob_set = compile_glob(config.glob.as_deref())?;
+	let type_filter = build_type_filter(config.type_filter, config.type_add.as_deref())?;
+
+	// A single-file search reports the path as given; its diff header uses the
+	// file name.
+	let single_file_name = metadata.is_file().then(|| {
+		search_path
+			.file_name()
+			.unwrap_or_default()
+			.to_string_lossy()
+			.to_string()
+	});
+	let entries = if metadata.is_file() {
+		if let Some(filter) = type_filter.as_ref()
+			&& !matches_type_filter(filter, &search_path)
+		{
+			Vec::new()
+		} else {
+			let relative_path = search_path.to_string_lossy().to_string();
+			vec![FileEntry { path: search_path, relative_path }]
+		}
+	} else {
+		scan_files(
+			&search_path,
+			&fs_cache::ScanOptions::new(config.hidden.unwrap_or(true), true),
+			config.cache.unwrap_or(false),
+			glob_set.as_ref(),
+			type_filter.as_ref(),
+			&ct,
+		)?
+	};
+	ct.heartbeat()?;
+
+	let replacement = config.replacement.as_bytes();
+	let mut changed: Vec<FileReplacement> = entries
+		.par_iter()
+		.filter_map(|entry| replace_file(entry, &matcher, replacement, multiline))
+		.collect();
+	changed.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+	// Once writing starts every file is attempted, so the result tells which
+	// ones were rewritten.
+	ct.heartbeat()?;
+	let mut files = Vec::with_capacity(changed.len());
+	let mut errors = Vec::new();
+	let mut total_replacements = 0u64;
+	for change in changed {
+		let diff = if apply {
+			if let Err(err) = apply_replacement(&change) {
+				errors.push(file_error(&change.relative_path, None, &err));
+				continue;
+			}
+			fs_cache::invalidate_path(&change.path);
+			None
+		} else {
+			let header = single_file_name.as_deref().unwrap_or(&change.relative_path);
+			Some(unified_diff(header, &change.original, &change.replaced))
+		};
+		total_replacements = total_replacements.saturating_add(change.replacements);
+		files.push(ReplaceFileResult {
+			path: change.relative_path,
+			replacements: clamp_u32(change.replacements),
+			diff,
+		});
+	}
+
+	Ok(ReplaceResult {
+		files,
+		total_replacements: clamp_u32(total_replacements),
+		files_searched: clamp_u32(entries.len() as u64),
+		applied: apply,
+		errors: (!errors.is_empty()).then_some(errors),
+	})
+}
+
+/// Search and replace a regex pattern across files.
+///
+/// Uses the same file selection as [`grep`]. In preview mode (the default)
+/// each changed file is reported with a unified diff; with `apply` the files
+/// are rewritten atomically and evicted from the scan cache.
+///
+/// # Arguments
+/// - `options`: Pattern, replacement, path, filters, and apply flag.
+///
+/// # Returns
+/// Per-file replacement counts (and diffs in preview mode).
+#[napi(js_name = "replace")]
+pub fn replace(options: ReplaceOptions<'_>) -> task::Async<ReplaceResult> {
+	let ReplaceOptions {
+		pattern,
+		replacement,
+		path,
+		glob,
+		type_filter,
+		type_add,
+		ignore_case,
+		multiline,
+		engine,
+		fixed_strings,
+		word_regexp,
+		line_regexp,
+		smart_case,
+		hidden,
+		cache,
+		apply,
+		signal,
+		timeout_ms,
+	} = options;
+
+	let config = ReplaceConfig {
+		pattern,
+		replacement,
+		path,
+		glob,
+		type_filter,
+		type_add,
+		ignore_case,
+		multiline,
+		engine,
+		fixed_strings,
+		word_regexp,
+		line_regexp,
+		smart_case,
+		hidden,
+		cache,
+		apply,
+	};
+
+	let ct = task::CancelToken::new(timeout_ms, signal);
+	task::blocking("replace", ct, move |ct| replace_sync(config, ct))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn search_options(pattern: &str) -> SearchOptions {
+		SearchOptions {
+			pattern:        Some(pattern.to_string()),
+			patterns:       None,
+			ignore_case:    None,
+			multiline:      None,
+			engine:         None,
+			fixed_strings:  None,
+			word_regexp:    None,
+			line_regexp:    None,
+			smart_case:     None,
+			invert_match:   None,
+			max_count:      None,
+			offset:         None,
+			context_before: None,
+			context_after:  None,
+			context:        None,
+			max_columns:    None,
+			mode:           None,
+		}
+	}
+
+	#[test]
+	fn test_submatch_offsets() {
+		let result = search_sync("const ä = foo(foo);\n".as_bytes(), search_options("foo"));
+		let submatches = result.matches[0].submatches.as_ref().unwrap();
+		assert_eq!(submatches.len(), 2);
+		assert_eq!(submatches[0].text, "foo");
+		assert_eq!((submatches[0].start, submatches[0].end), (11, 14));
+		assert_eq!((submatches[0].start_utf16, submatches[0].end_utf16), (10, 13));
+		assert_eq!((submatches[1].start_utf16, submatches[1].end_utf16), (14, 17));
+		assert!(submatches.iter().all(|m| m.truncated.is_none()));
+	}
+
+	#[test]
+	fn test_submatch_truncation() {
+		let mut options = search_options("needle");
+		options.max_columns = Some(12);
+		let result = search_sync(b"needle haystack needle\n", options);
+		let matched = &result.matches[0];
+		assert_eq!(matched.truncated, Some(true));
+		let submatches = matched.submatches.as_ref().unwrap();
+		assert_eq!(submatches[0].truncated, None);
+		assert_eq!(submatches[1].truncated, Some(true));
+	}
+
+	#[test]
+	fn test_auto_engine_supports_lookaround_and_backreferences() {
+		let result = search_sync(b"foobar\nfoobaz\nthe the\n", search_options(r"foo(?!bar)"));
+		assert_eq!(result.match_count, 1);
+		assert_eq!(result.matches[0].line_number, 2);
+
+		let result = search_sync(b"foobar\nfoobaz\nthe the\n", search_options(r"\b(\w+) \1\b"));
+		assert_eq!(result.match_count, 1);
+		assert_eq!(result.matches[0].line_number, 3);
+		assert_eq!(result.matches[0].submatches.as_ref().unwrap()[0].text, "the the");
+	}
+
+	#[test]
+	fn test_default_engine_rejects_lookaround() {
+		let mut options = search_options(r"foo(?!bar)");
+		options.engine = Some("default".to_string());
+		let result = search_sync(b"foobaz\n", options);
+		assert!(result.error.is_some());
+		assert_eq!(result.match_count, 0);
+	}
+
+	#[test]
+	fn test_fancy_engine_stays_within_lines() {
+		let mut options = search_options(r"a\s+b");
+		options.engine = Some("fancy".to_string());
+		options.context = Some(1);
+		let result = search_sync(b"a\nb\na  b\n", options);
+		assert_eq!(result.match_count, 1);
+		assert_eq!(result.matches[0].line_number, 3);
+		assert_eq!(result.matches[0].context_before.as_ref().unwrap().len(), 1);
+	}
+
+	fn line_numbers(result: &SearchResult) -> Vec<u32> {
+		result.matches.iter().map(|m| m.line_number).collect()
+	}
+
+	#[test]
+	fn test_fixed_strings() {
+		let content = b"call foo(1)\nfoo.bar\nfooxbar\n";
+		assert!(search_sync(content, search_options("foo(")).error.is_some());
+
+		let mut options = search_options("foo(");
+		options.fixed_strings = Some(true);
+		assert_eq!(line_numbers(&search_sync(content, options)), [1]);
+
+		let mut options = search_options("foo.bar");
+		options.fixed_strings = Some(true);
+		assert_eq!(line_numbers(&search_sync(content, options)), [2]);
+	}
+
+	#[test]
+	fn test_word_and_line_regexp() {
+		let content = b"foo\nfoobar\nx foo -2\n  foo\n";
+		let mut options = search_options("foo");
+		options.word_regexp = Some(true);
+		assert_eq!(line_numbers(&search_sync(content, options)), [1, 3, 4]);
+
+		let mut options = search_options("foo");
+		options.line_regexp = Some(true);
+		assert_eq!(line_numbers(&search_sync(content, options)), [1]);
+
+		for engine in ["default", "fancy"] {
+			let mut options = search_options("-2");
+			options.word_regexp = Some(true);
+			options.engine = Some(engine.to_string());
+			assert_eq!(line_numbers(&search_sync(content, options)), [3], "{engine}");
+		}
+	}
+
+	#[test]
+	fn test_smart_case() {
+		let content = b"FOO\nfoo\nFoo\n";
+		for engine in ["default", "fancy"] {
+			let mut options = search_options("foo");
+			options.smart_case = Some(true);
+			options.engine = Some(engine.to_string());
+			assert_eq!(line_numbers(&search_sync(content, options)), [1, 2, 3], "{engine}");
+
+			let mut options = search_options("Foo");
+			options.smart_case = Some(true);
+			options.engine = Some(engine.to_string());
+			assert_eq!(line_numbers(&search_sync(content, options)), [3], "{engine}");
+
+			let mut options = search_options(r"(?<Word>\w)oo");
+			options.smart_case = Some(true);
+			options.engine = Some(engine.to_string());
+			assert_eq!(line_numbers(&search_sync(content, options)), [1, 2, 3], "{engine}");
+		}
+
+		let mut options = search_options("Foo");
+		options.smart_case = Some(true);
+		options.ignore_case = Some(true);
+		assert_eq!(search_sync(content, options).match_count, 3);
+	}
+
+	#[test]
+	fn test_pattern_flags_with_multiline() {
+		let content = b"let a = (\n  b);\nfoo\nbar\nxfoo\nbar\n";
+
+		// Literal newlines only match across lines in multiline mode.
+		let mut options = search_options("(\n  b)");
+		options.fixed_strings = Some(true);
+		assert_eq!(search_sync(content, options).match_count, 0);
+		let mut options = search_options("(\n  b)");
+		options.fixed_strings = Some(true);
+		options.multiline = Some(true);
+		let result = search_sync(content, options);
+		assert_eq!(line_numbers(&result), [1]);
+		assert_eq!(result.matches[0].line, "let a = (\n  b);");
+
+		// Whole-line anchors apply to every line the match spans.
+		let mut options = search_options(r"foo\nbar");
+		options.line_regexp = Some(true);
+		options.multiline = Some(true);
+		assert_eq!(line_numbers(&search_sync(content, options)), [3]);
+
+		let mut options = search_options(r"foo\nbar");
+		options.word_regexp = Some(true);
+		options.multiline = Some(true);
+		assert_eq!(line_numbers(&search_sync(content, options)), [3]);
+	}
+
+	#[test]
+	fn test_multiple_patterns_report_pattern_index() {
+		let mut options = search_options("TODO");
+		options.patterns = Some(vec!["FIXME".to_string(), "XXX".to_string()]);
+		let result = search_sync(b"// FIXME\nplain\n// TODO XXX\n// XXX\n", options);
+		assert_eq!(line_numbers(&result), [1, 3, 4]);
+		let indices: Vec<_> = result.matches.iter().map(|m| m.pattern_index).collect();
+		assert_eq!(indices, [Some(1), Some(0), Some(2)]);
+		assert_eq!(result.matches[1].submatches.as_ref().unwrap().len(), 2);
+
+		let mut options = search_options("TODO");
+		options.pattern = None;
+		options.patterns = Some(vec!["a(".to_string(), "b[".to_string()]);
+		options.fixed_strings = Some(true);
+		let result = search_sync(b"a(\nb[\n", options);
+		assert_eq!(line_numbers(&result), [1, 2]);
+
+		let mut options = search_options("TODO");
+		options.pattern = None;
+		assert!(search_sync(b"TODO\n", options).error.is_some());
+
+		let result = search_sync(b"TODO\n", search_options("TODO"));
+		assert_eq!(result.matches[0].pattern_index, None);
+	}
+
+	#[test]
+	fn test_invert_match() {
+		let content = b"keep\n// TODO\nkeep too\n// FIXME\n";
+		let mut options = search_options("TODO");
+		options.patterns = Some(vec!["FIXME".to_string()]);
+		options.invert_match = Some(true);
+		let result = search_sync(content, options);
+		assert_eq!(line_numbers(&result), [1, 3]);
+		assert!(result.matches.iter().all(|m| m.pattern_index.is_none()));
+
+		let mut options = search_options("TODO");
+		options.invert_match = Some(true);
+		options.mode = Some("count".to_string());
+		let result = search_sync(content, options);
+		assert_eq!(result.match_count, 3);
+		assert!(result.matches.is_empty());
+
+		let mut options = search_options("keep|TODO|FIXME");
+		options.invert_match = Some(true);
+		assert_eq!(search_sync(content, options).match_count, 0);
+	}
+
+	#[test]
+	fn test_exclude_files_drops_files_matching_exclude_patterns() {
+		let dir = std::env::temp_dir().join(format!("pi-natives-exclude-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+		std::fs::write(dir.join("todo.ts"), "// TODO: implement\n").unwrap();
+		std::fs::write(dir.join("both.ts"), "// TODO: implement\n// FIXME: broken\n").unwrap();
+		let entries = ["todo.ts", "both.ts"]
+			.map(|name| FileEntry { path: dir.join(name), relative_path: name.to_string() })
+			.into();
+
+		// Files containing TODO but not FIXME.
+		let exclude = CompiledPatterns::new(&["FIXME".to_string()], PatternFlags::default()).unwrap();
+		let params = SearchParams {
+			multiline:      false,
+			invert_match:   false,
+			context_before: 0,
+			context_after:  0,
+			max_columns:    None,
+			mode:           OutputMode::Count,
+			max_count:      Some(1),
+			offset:         0,
+			encoding:       TextEncoding::Auto,
+		};
+		let kept = exclude_files(entries, &exclude, params, false, &task::CancelToken::default());
+		let patterns = CompiledPatterns::new(&["TODO".to_string()], PatternFlags::default()).unwrap();
+		let results =
+			run_parallel_search(&kept, &patterns, params, false, &task::CancelToken::default());
+		let paths: Vec<_> = results
+			.iter()
+			.map(|result| result.relative_path.as_str())
+			.collect();
+		assert_eq!(paths, ["todo.ts"]);
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn test_parallel_search_keeps_members_before_a_failure() {
+		use std::io::Write;
+
+		let dir =
+			std::env::temp_dir().join(format!("pi-natives-member-error-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+		let mut builder = tar::Builder::new(Vec::new());
+		let mut header = tar::Header::new_gnu();
+		header.set_size(5);
+		header.set_mode(0o644);
+		header.set_cksum();
+		builder
+			.append_data(&mut header, "a.txt", &b"TODO\n"[..])
+			.unwrap();
+		// Replace the end-of-archive blocks with a header that fails its
+		// checksum.
+		let mut tarball = builder.into_inner().unwrap();
+		tarball.truncate(tarball.len() - 1024);
+		tarball.extend([0xff; 512]);
+		let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+		encoder.write_all(&tarball).unwrap();
+		std::fs::write(dir.join("bundle.tar.gz"), encoder.finish().unwrap()).unwrap();
+		let entries = ["bundle.tar.gz", "missing.ts"]
+			.map(|name| FileEntry { path: dir.join(name), relative_path: name.to_string() });
+
+		let patterns = CompiledPatterns::new(&["TODO".to_string()], PatternFlags::default()).unwrap();
+		let params = SearchParams {
+			multiline:      false,
+			invert_match:   false,
+			context_before: 0,
+			context_after:  0,
+			max_columns:    None,
+			mode:           OutputMode::Count,
+			max_count:      None,
+			offset:         0,
+			encoding:       TextEncoding::Auto,
+		};
+		let results =
+			run_parallel_search(&entries, &patterns, params, true, &task::CancelToken::default());
+		assert!(results[0].searched);
+		assert_eq!(results[0].streams[0].member.as_deref(), Some("a.txt"));
+		assert_eq!(results[0].streams[0].match_count, 1);
+		assert!(!results[1].searched);
+		let failed: Vec<_> = results
+			.iter()
+			.flat_map(|result| &result.errors)
+			.map(|error| error.path.as_str())
+			.collect();
+		assert_eq!(failed, ["bundle.tar.gz", "missing.ts"]);
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn test_transcodes_utf16_content() {
+		let content: Vec<u8> = "first\r\nsecond caf\u{e9}\r\n"
+			.encode_utf16()
+			.flat_map(u16::to_le_bytes)
+			.collect();
+		let result = search_sync(&content, search_options("caf\u{e9}"));
+		assert_eq!(line_numbers(&result), [2]);
+		assert_eq!(result.matches[0].line, "second caf\u{e9}");
+
+		let mut with_bom = vec![0xfe, 0xff];
+		with_bom.extend("x\ncaf\u{e9}\n".encode_utf16().flat_map(u16::to_be_bytes));
+		assert_eq!(line_numbers(&search_sync(&with_bom, search_options("caf\u{e9}"))), [2]);
+	}
+
+	#[test]
+	fn test_relevance_prefers_dense_shallow_recent_files() {
+		let src = relevance_score(4, 2048, 1, 0.5);
+		let fixture = relevance_score(4, 64 * 1024, 4, 0.5);
+		let stale = relevance_score(4, 2048, 1, 365.0);
+		assert!(src > fixture);
+		assert!(src > stale);
+		assert!(relevance_score(1, 2048, 1, 0.5) < src);
+		assert_eq!(parse_sort_order(Some("matchCount")), SortOrder::MatchCount);
+		assert_eq!(parse_sort_order(Some("bogus")), SortOrder::Path);
+	}
+
+	#[test]
+	fn test_replace_content_expands_captures() {
+		let matcher = build_matcher(&[r"(?P<name>\w+)_(\d)"], PatternFlags::default()).unwrap();
+		let (replaced, count) =
+			replace_content(&matcher, b"foo_1 bar_2\r\nbaz\n", b"${name}-$2$$", false)
+				.unwrap()
+				.unwrap();
+		assert_eq!(replaced, b"foo-1$ bar-2$\r\nbaz\n");
+		assert_eq!(count, 2);
+	}
+
+	#[test]
+	fn test_replace_honors_pattern_flags() {
+		let dir =
+			std::env::temp_dir().join(format!("pi-natives-replace-flags-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+		std::fs::write(dir.join("a.txt"), "a.b axb a.bc A.B\n").unwrap();
+
+		let result = replace_sync(
+			ReplaceConfig {
+				pattern:       "a.b".to_string(),
+				replacement:   "x".to_string(),
+				path:          dir.to_string_lossy().to_string(),
+				glob:          None,
+				type_filter:   None,
+				type_add:      None,
+				ignore_case:   None,
+				multiline:     None,
+				engine:        None,
+				fixed_strings: Some(true),
+				word_regexp:   Some(true),
+				line_regexp:   None,
+				smart_case:    Some(true),
+				hidden:        None,
+				cache:         None,
+				apply:         None,
+			},
+			task::CancelToken::default(),
+		)
+		.unwrap();
+		assert_eq!(result.total_replacements, 2);
+		let diff = result.files[0].diff.as_deref().unwrap();
+		assert!(diff.contains("+x axb a.bc x"), "{diff}");
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_write_atomic_keeps_symlink() {
+		let dir = std::env::temp_dir().join(format!("pi-natives-replace-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+		let target = dir.join("target.txt");
+		let link = dir.join("link.txt");
+		std::fs::write(&target, "old\n").unwrap();
+		std::os::unix::fs::symlink(&target, &link).unwrap();
+
+		write_atomic(&link, b"new\n").unwrap();
+		assert!(std::fs::symlink_metadata(&link).unwrap().is_symlink());
+		assert_eq!(std::fs::read_to_string(&target).unwrap(), "new\n");
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn test_apply_replacement_skips_changed_file() {
+		let dir =
+			std::env::temp_dir().join(format!("pi-natives-replace-stale-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("a.txt");
+		std::fs::write(&path, "old\n").unwrap();
+		let entry = FileEntry { path: path.clone(), relative_path: "a.txt".to_string() };
+		let matcher = build_matcher(&["old"], PatternFlags::default()).unwrap();
+		let change = replace_file(&entry, &matcher, b"new", false).unwrap();
+
+		std::fs::write(&path, "old, edited\n").unwrap();
+		assert!(apply_replacement(&change).is_err());
+		assert_eq!(std::fs::read_to_string(&path).unwrap(), "old, edited\n");
+
+		let change = replace_file(&entry, &matcher, b"new", false).unwrap();
+		apply_replacement(&change).unwrap();
+		assert_eq!(std::fs::read_to_string(&path).unwrap(), "new, edited\n");
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn test_replace_content_confines_matches_to_lines() {
+		let matcher = build_matcher(&[r"a\s+b"], PatternFlags::default()).unwrap();
+		assert!(
+			replace_content(&matcher, b"a\nb\n", b"x", false)
+				.unwrap()
+				.is_none()
+		);
+
+		let matcher =
+			build_matcher(&[r"a\s+b"], PatternFlags { multiline: true, ..Default::default() })
+				.unwrap();
+		let (replaced, _) = replace_content(&matcher, b"a\nb\n", b"x", true)
+			.unwrap()
+			.unwrap();
+		assert_eq!(replaced, b"x\n");
+	}
+}
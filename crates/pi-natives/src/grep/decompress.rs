@@ -0,0 +1,210 @@
+//! Transparent decompression for `grep({ searchCompressed: true })`.
+//!
+//! Compression is detected from magic bytes rather than file extensions, so
+//! rotated logs such as `app.log.1` are handled as well. Decompressed tar
+//! streams and zip files are searched member by member.
+//!
+//! Decompressed streams are wrapped in [`HeartbeatReader`] so cancellation is
+//! observed while inflating large inputs, and each stream is capped at the
+//! caller's byte limit to bound work on decompression bombs.
+
+use std::{
+	fs::File,
+	io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
+};
+
+use crate::task;
+
+/// Size of a tar header block; the `ustar` magic sits at offset 257.
+const TAR_BLOCK: usize = 512;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+	Gzip,
+	Zstd,
+	Xz,
+	Bzip2,
+	Zip,
+}
+
+impl Format {
+	fn detect(header: &[u8]) -> Option<Self> {
+		if header.starts_with(&[0x1f, 0x8b]) {
+			Some(Self::Gzip)
+		} else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+			Some(Self::Zstd)
+		} else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+			Some(Self::Xz)
+		} else if header.starts_with(b"BZh") {
+			Some(Self::Bzip2)
+		} else if header.starts_with(b"PK\x03\x04") {
+			Some(Self::Zip)
+		} else {
+			None
+		}
+	}
+}
+
+/// Reader that checks the cancel token before every read.
+struct HeartbeatReader<'a, R> {
+	inner: R,
+	ct:    &'a task::CancelToken,
+}
+
+impl<R: Read> Read for HeartbeatReader<'_, R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self
+			.ct
+			.heartbeat()
+			.map_err(|err| io::Error::other(err.to_string()))?;
+		self.inner.read(buf)
+	}
+}
+
+/// Calls `visit` with the content of `file`: once for plain and singly
+/// compressed files (member `None`), or once per regular file inside a tar or
+/// zip archive. Each stream is limited to `max_bytes`. `visit` returns whether
+/// to continue with the next member.
+pub fn for_each_stream(
+	file: File,
+	max_bytes: u64,
+	ct: &task::CancelToken,
+	mut visit: impl FnMut(Option<String>, &mut dyn Read) -> io::Result<bool>,
+) -> io::Result<()> {
+	let mut reader = BufReader::new(file);
+	let format = Format::detect(reader.fill_buf()?);
+	let decoded: Box<dyn Read + '_> = match format {
+		None => {
+			visit(None, &mut reader.take(max_bytes))?;
+			return Ok(());
+		},
+		Some(Format::Zip) => {
+			let mut file = reader.into_inner();
+			file.seek(SeekFrom::Start(0))?;
+			return visit_zip(file, max_bytes, ct, visit);
+		},
+		Some(Format::Gzip) => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+		Some(Format::Zstd) => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+		Some(Format::Xz) => Box::new(liblzma::read::XzDecoder::new_multi_decoder(reader)),
+		Some(Format::Bzip2) => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+	};
+	let mut decoded = HeartbeatReader { inner: decoded, ct };
+
+	// Peek at the first block to tell a compressed tarball from a single file.
+	let mut head = Vec::with_capacity(TAR_BLOCK);
+	(&mut decoded)
+		.take(TAR_BLOCK as u64)
+		.read_to_end(&mut head)?;
+	let is_tar = head.len() == TAR_BLOCK && &head[257..262] == b"ustar";
+	let stream = Cursor::new(head).chain(decoded);
+	if !is_tar {
+		visit(None, &mut stream.take(max_bytes))?;
+		return Ok(());
+	}
+
+	let mut archive = tar::Archive::new(stream);
+	for entry in archive.entries()? {
+		let entry = entry?;
+		if !entry.header().entry_type().is_file() {
+			continue;
+		}
+		let member = entry.path()?.to_string_lossy().into_owned();
+		if !visit(Some(member), &mut entry.take(max_bytes))? {
+			break;
+		}
+	}
+	Ok(())
+}
+
+fn visit_zip(
+	file: File,
+	max_bytes: u64,
+	ct: &task::CancelToken,
+	mut visit: impl FnMut(Option<String>, &mut dyn Read) -> io::Result<bool>,
+) -> io::Result<()> {
+	let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+	for index in 0..archive.len() {
+		let entry = archive.by_index(index).map_err(io::Error::other)?;
+		if !entry.is_file() {
+			continue;
+		}
+		let member = entry.name().map_err(io::Error::other)?.into_owned();
+		let mut reader = HeartbeatReader { inner: entry, ct }.take(max_bytes);
+		if !visit(Some(member), &mut reader)? {
+			break;
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{io::Write, path::PathBuf};
+
+	use super::*;
+
+	fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+		let path =
+			std::env::temp_dir().join(format!("pi-natives-decompress-{}-{name}", std::process::id()));
+		std::fs::write(&path, content).unwrap();
+		path
+	}
+
+	fn streams(path: &PathBuf) -> Vec<(Option<String>, String)> {
+		let mut streams = Vec::new();
+		let file = File::open(path).unwrap();
+		for_each_stream(file, 1024, &task::CancelToken::default(), |member, reader| {
+			let mut content = String::new();
+			reader.read_to_string(&mut content)?;
+			streams.push((member, content));
+			Ok(true)
+		})
+		.unwrap();
+		std::fs::remove_file(path).unwrap();
+		streams
+	}
+
+	fn gzip(content: &[u8]) -> Vec<u8> {
+		let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+		encoder.write_all(content).unwrap();
+		encoder.finish().unwrap()
+	}
+
+	#[test]
+	fn test_single_stream_formats() {
+		let plain = temp_file("plain.log", b"plain\n");
+		assert_eq!(streams(&plain), [(None, "plain\n".to_string())]);
+
+		let gz = temp_file("app.log.1", &gzip(b"error: gz\n"));
+		assert_eq!(streams(&gz), [(None, "error: gz\n".to_string())]);
+
+		let zst = temp_file("app.log.zst", &zstd::encode_all(&b"error: zstd\n"[..], 1).unwrap());
+		assert_eq!(streams(&zst), [(None, "error: zstd\n".to_string())]);
+	}
+
+	#[test]
+	fn test_archive_members() {
+		let mut builder = tar::Builder::new(Vec::new());
+		for (name, content) in [("a.txt", &b"alpha\n"[..]), ("dir/b.txt", b"beta\n")] {
+			let mut header = tar::Header::new_gnu();
+			header.set_size(content.len() as u64);
+			header.set_mode(0o644);
+			header.set_cksum();
+			builder.append_data(&mut header, name, content).unwrap();
+		}
+		let tarball = temp_file("bundle.tar.gz", &gzip(&builder.into_inner().unwrap()));
+		assert_eq!(streams(&tarball), [
+			(Some("a.txt".to_string()), "alpha\n".to_string()),
+			(Some("dir/b.txt".to_string()), "beta\n".to_string()),
+		]);
+
+		let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+		let options = zip::write::SimpleFileOptions::default()
+			.compression_method(zip::CompressionMethod::Deflated);
+		writer.add_directory("docs/", options).unwrap();
+		writer.start_file("docs/readme.md", options).unwrap();
+		writer.write_all(b"# zip\n").unwrap();
+		let zip = temp_file("bundle.zip", &writer.finish().unwrap().into_inner());
+		assert_eq!(streams(&zip), [(Some("docs/readme.md".to_string()), "# zip\n".to_string())]);
+	}
+}
@@ -0,0 +1,121 @@
+//! Text encoding handling for grep.
+//!
+//! Files are transcoded to UTF-8 by the searcher before matching, so UTF-16
+//! files are no longer rejected as binary by their NUL bytes. In `auto` mode
+//! the encoding comes from a byte-order mark, or from the NUL layout of
+//! BOM-less UTF-16 text; anything else is searched as UTF-8. An explicit
+//! encoding applies to every file, except that a BOM still takes precedence.
+
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use napi::{Error, Result};
+
+/// Bytes inspected when detecting the encoding of a stream.
+pub const SNIFF_BYTES: usize = 512;
+
+/// Encoding requested by the caller.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextEncoding {
+	/// Detect from the BOM or the UTF-16 NUL layout, otherwise UTF-8.
+	#[default]
+	Auto,
+	/// Decode every stream without a BOM with this encoding.
+	Fixed(&'static Encoding),
+}
+
+impl TextEncoding {
+	/// Parses the `encoding` option: `auto` or any WHATWG label such as
+	/// `utf-16le`, `latin1` or `shift_jis`.
+	pub fn parse(label: Option<&str>) -> Result<Self> {
+		match label.map(str::trim) {
+			None | Some("" | "auto") => Ok(Self::Auto),
+			Some(label) => Encoding::for_label(label.as_bytes())
+				.map(Self::Fixed)
+				.ok_or_else(|| Error::from_reason(format!("Unknown encoding '{label}'"))),
+		}
+	}
+
+	/// Encoding to decode a stream starting with `head` with.
+	pub fn detect(self, head: &[u8]) -> &'static Encoding {
+		if let Some((encoding, _)) = Encoding::for_bom(head) {
+			return encoding;
+		}
+		match self {
+			Self::Fixed(encoding) => encoding,
+			Self::Auto => sniff_utf16(head).unwrap_or(UTF_8),
+		}
+	}
+}
+
+/// Recognizes BOM-less UTF-16 holding mostly ASCII text, where one byte of
+/// (nearly) every code unit is NUL and the other never is.
+fn sniff_utf16(head: &[u8]) -> Option<&'static Encoding> {
+	let units = head.len() / 2;
+	if units < 2 {
+		return None;
+	}
+	let (mut low_nuls, mut high_nuls) = (0usize, 0usize);
+	for [low, high] in head.as_chunks::<2>().0 {
+		low_nuls += usize::from(*low == 0);
+		high_nuls += usize::from(*high == 0);
+	}
+	if low_nuls == 0 && high_nuls * 4 >= units * 3 {
+		Some(UTF_16LE)
+	} else if high_nuls == 0 && low_nuls * 4 >= units * 3 {
+		Some(UTF_16BE)
+	} else {
+		None
+	}
+}
+
+/// Name reported to JS, e.g. `utf-16le`, `windows-1252` or `shift_jis`.
+pub fn display_name(encoding: &'static Encoding) -> String {
+	encoding.name().to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+		text
+			.encode_utf16()
+			.flat_map(|unit| {
+				if big_endian {
+					unit.to_be_bytes()
+				} else {
+					unit.to_le_bytes()
+				}
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_parse_labels() {
+		assert_eq!(TextEncoding::parse(None).unwrap(), TextEncoding::Auto);
+		assert_eq!(TextEncoding::parse(Some("auto")).unwrap(), TextEncoding::Auto);
+		assert_eq!(
+			TextEncoding::parse(Some("Shift_JIS")).unwrap(),
+			TextEncoding::Fixed(encoding_rs::SHIFT_JIS)
+		);
+		assert_eq!(
+			TextEncoding::parse(Some("latin1")).unwrap(),
+			TextEncoding::Fixed(encoding_rs::WINDOWS_1252)
+		);
+		assert!(TextEncoding::parse(Some("klingon")).is_err());
+	}
+
+	#[test]
+	fn test_detect() {
+		let auto = TextEncoding::Auto;
+		assert_eq!(auto.detect(b"plain text"), UTF_8);
+		assert_eq!(auto.detect(b"\xff\xfeh\0i\0"), UTF_16LE);
+		assert_eq!(auto.detect(&utf16("BEGIN\r\nEND", false)), UTF_16LE);
+		assert_eq!(auto.detect(&utf16("BEGIN\r\nEND", true)), UTF_16BE);
+		assert_eq!(auto.detect(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0"), UTF_8);
+
+		let latin1 = TextEncoding::parse(Some("latin1")).unwrap();
+		assert_eq!(latin1.detect(b"caf\xe9"), encoding_rs::WINDOWS_1252);
+		assert_eq!(latin1.detect(b"\xef\xbb\xbfcaf\xc3\xa9"), UTF_8);
+		assert_eq!(display_name(encoding_rs::SHIFT_JIS), "shift_jis");
+	}
+}
@@ -0,0 +1,163 @@
+//! File type filtering for grep, backed by ripgrep's type registry.
+//!
+//! The `type` option accepts any name from `ignore`'s default definitions
+//! (see `listFileTypes()`), `!name` to exclude a type, and `name:glob`
+//! additions via `typeAdd`. Unknown names are rejected rather than guessed,
+//! so a misspelled type fails loudly instead of matching nothing.
+
+use std::path::Path;
+
+use ignore::types::{FileTypeDef, Types, TypesBuilder};
+use napi::{Either, Error, Result};
+
+/// Common names that ripgrep spells differently (several were accepted by the
+/// previous built-in table).
+const ALIASES: &[(&str, &str)] = &[
+	("bash", "sh"),
+	("csharp", "cs"),
+	("cxx", "cpp"),
+	("dockerfile", "docker"),
+	("javascript", "js"),
+	("kt", "kotlin"),
+	("makefile", "make"),
+	("proto", "protobuf"),
+	("rb", "ruby"),
+	("rs", "rust"),
+	("terraform", "tf"),
+	("yml", "yaml"),
+];
+
+fn normalize_name(name: &str) -> String {
+	let name = name.trim_start_matches('.').to_lowercase();
+	ALIASES
+		.iter()
+		.find(|(alias, _)| *alias == name)
+		.map_or(name, |(_, target)| (*target).to_string())
+}
+
+/// Builds a type matcher from `type` selections and `name:glob` additions.
+///
+/// Returns `None` when no type is selected or negated.
+pub fn build_type_filter(
+	selections: Option<Either<String, Vec<String>>>,
+	additions: Option<&[String]>,
+) -> Result<Option<Types>> {
+	let selections = match selections {
+		Some(Either::A(name)) => vec![name],
+		Some(Either::B(names)) => names,
+		None => Vec::new(),
+	};
+	let mut selected = Vec::new();
+	let mut negated = Vec::new();
+	for selection in &selections {
+		let selection = selection.trim();
+		match selection.strip_prefix('!') {
+			Some(name) if !name.trim().is_empty() => negated.push(normalize_name(name.trim())),
+			Some(_) => {},
+			None if !selection.is_empty() => selected.push(normalize_name(selection)),
+			None => {},
+		}
+	}
+	if selected.is_empty() && negated.is_empty() {
+		return Ok(None);
+	}
+
+	let mut builder = TypesBuilder::new();
+	builder.add_defaults();
+	for def in additions.unwrap_or_default() {
+		builder
+			.add_def(def)
+			.map_err(|err| Error::from_reason(format!("Invalid type definition '{def}': {err}")))?;
+	}
+	let known = builder.definitions();
+	if let Some(name) = selected
+		.iter()
+		.chain(&negated)
+		.find(|name| !known.iter().any(|def| def.name() == name.as_str()))
+	{
+		return Err(Error::from_reason(format!(
+			"Unknown file type '{name}'; see listFileTypes() for known types, or define it with \
+			 typeAdd"
+		)));
+	}
+	// Later selections take precedence, so negations are applied last.
+	for name in &selected {
+		builder.select(name);
+	}
+	for name in &negated {
+		builder.negate(name);
+	}
+	builder
+		.build()
+		.map(Some)
+		.map_err(|err| Error::from_reason(format!("Failed to build type filter: {err}")))
+}
+
+/// Whether `path` passes the type filter.
+pub fn matches_type_filter(types: &Types, path: &Path) -> bool {
+	!types.matched(path, false).is_ignore()
+}
+
+/// Ripgrep's default type definitions, sorted by name.
+pub fn default_definitions() -> Vec<FileTypeDef> {
+	let mut builder = TypesBuilder::new();
+	builder.add_defaults();
+	builder.definitions()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn filter(types: &[&str], additions: &[&str]) -> Types {
+		let types = types.iter().map(ToString::to_string).collect();
+		let additions: Vec<String> = additions.iter().map(ToString::to_string).collect();
+		build_type_filter(Some(Either::B(types)), Some(&additions))
+			.unwrap()
+			.unwrap()
+	}
+
+	fn accepts(types: &Types, path: &str) -> bool {
+		matches_type_filter(types, Path::new(path))
+	}
+
+	#[test]
+	fn test_default_registry() {
+		let types = filter(&["proto", "cmake", "terraform", "vue"], &[]);
+		assert!(accepts(&types, "api/service.proto"));
+		assert!(accepts(&types, "CMakeLists.txt"));
+		assert!(accepts(&types, "infra/main.tf"));
+		assert!(accepts(&types, "src/App.vue"));
+		assert!(!accepts(&types, "src/main.rs"));
+	}
+
+	#[test]
+	fn test_negation_and_aliases() {
+		let types = filter(&["!js"], &[]);
+		assert!(!accepts(&types, "index.mjs"));
+		assert!(accepts(&types, "main.rs"));
+
+		let types = filter(&["rs", "TypeScript", "!ts"], &[]);
+		assert!(accepts(&types, "lib.rs"));
+		assert!(!accepts(&types, "index.ts"));
+	}
+
+	#[test]
+	fn test_additions_and_unknown_names() {
+		let types = filter(&["snap"], &["snap:*.snap", "snap:__snapshots__"]);
+		assert!(accepts(&types, "tests/__snapshots__"));
+		assert!(accepts(&types, "tests/app.snap"));
+
+		let err = build_type_filter(Some(Either::A("typscript".into())), None).unwrap_err();
+		assert!(err.reason.contains("Unknown file type 'typscript'"), "{}", err.reason);
+		assert!(err.reason.contains("listFileTypes()"));
+
+		let additions = vec!["bad name:*.x".to_string()];
+		assert!(build_type_filter(Some(Either::A("js".into())), Some(&additions)).is_err());
+		assert!(
+			build_type_filter(Some(Either::A("  ".into())), None)
+				.unwrap()
+				.is_none()
+		);
+	}
+}
@@ -0,0 +1,365 @@
+//! Regex engine selection for grep.
+//!
+//! [`SearchMatcher`] dispatches between ripgrep's `RegexMatcher` (finite
+//! automata, linear time) and a [`FancyMatcher`] backed by `fancy-regex`, which
+//! adds lookaround and backreferences at the cost of backtracking.
+//!
+//! `fancy-regex` only searches `&str`, so haystacks containing invalid UTF-8
+//! are searched one valid segment at a time; matches never span an invalid
+//! byte sequence.
+
+use std::{collections::HashMap, fmt};
+
+use grep_matcher::{ByteSet, Captures, LineMatchKind, LineTerminator, Match, Matcher};
+use grep_regex::{RegexCaptures, RegexMatcher};
+
+/// Regex engine requested by the caller.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Engine {
+	/// Rust regex only; lookaround and backreferences are rejected.
+	Default,
+	/// Always use the backtracking `fancy-regex` engine.
+	Fancy,
+	/// Rust regex, falling back to `fancy-regex` for patterns it rejects.
+	#[default]
+	Auto,
+}
+
+impl Engine {
+	pub fn parse(engine: Option<&str>) -> Self {
+		match engine {
+			Some("default" | "rust") => Self::Default,
+			Some("fancy" | "pcre") => Self::Fancy,
+			_ => Self::Auto,
+		}
+	}
+}
+
+/// Joins `patterns` into one alternation for the fancy engine, applying
+/// `fixed_strings`, `word` and `whole_line` the way `RegexMatcherBuilder` does.
+pub fn fancy_pattern<P: AsRef<str>>(
+	patterns: &[P],
+	fixed_strings: bool,
+	word: bool,
+	whole_line: bool,
+) -> String {
+	let alternation = patterns
+		.iter()
+		.map(|pattern| {
+			let pattern = pattern.as_ref();
+			if fixed_strings {
+				format!("(?:{})", fancy_regex::escape(pattern))
+			} else {
+				format!("(?:{pattern})")
+			}
+		})
+		.collect::<Vec<_>>()
+		.join("|");
+	if whole_line {
+		format!("(?m:^)(?:{alternation})(?m:$)")
+	} else if word {
+		format!(r"(?<!\w)(?:{alternation})(?!\w)")
+	} else {
+		alternation
+	}
+}
+
+/// Smart-case check for the fancy engine: whether any literal character in
+/// `pattern` is uppercase. Escape sequences, Unicode classes and group names
+/// are skipped so that `\W`, `\p{Lu}` or `(?<Name>...)` do not count.
+pub fn has_uppercase_literal(pattern: &str, fixed_strings: bool) -> bool {
+	if fixed_strings {
+		return pattern.chars().any(char::is_uppercase);
+	}
+	let mut chars = pattern.chars().peekable();
+	while let Some(ch) = chars.next() {
+		match ch {
+			'\\' => match chars.next() {
+				Some('p' | 'P') if chars.peek() == Some(&'{') => {
+					chars.by_ref().find(|&c| c == '}');
+				},
+				_ => {},
+			},
+			'(' if chars.peek() == Some(&'?') => {
+				chars.next();
+				if chars.peek() == Some(&'P') {
+					chars.next();
+				}
+				if chars.peek() == Some(&'<') {
+					chars.next();
+					if !matches!(chars.peek(), Some('=' | '!')) {
+						chars.by_ref().find(|&c| c == '>');
+					}
+				}
+			},
+			ch if ch.is_uppercase() => return true,
+			_ => {},
+		}
+	}
+	false
+}
+
+/// Error produced while matching (only the fancy engine can fail at search
+/// time, e.g. when its backtrack limit is exceeded).
+#[derive(Debug)]
+pub struct MatcherError(String);
+
+impl fmt::Display for MatcherError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+impl std::error::Error for MatcherError {}
+
+impl From<fancy_regex::Error> for MatcherError {
+	fn from(err: fancy_regex::Error) -> Self {
+		Self(err.to_string())
+	}
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// fancy-regex backend
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// `grep_matcher::Matcher` implementation on top of `fancy_regex::Regex`.
+pub struct FancyMatcher {
+	regex: fancy_regex::Regex,
+	names: HashMap<String, usize>,
+}
+
+/// Capture group offsets produced by [`FancyMatcher`].
+#[derive(Clone, Debug)]
+pub struct FancyCaptures {
+	groups: Vec<Option<Match>>,
+}
+
+impl Captures for FancyCaptures {
+	fn len(&self) -> usize {
+		self.groups.len()
+	}
+
+	fn get(&self, i: usize) -> Option<Match> {
+		self.groups.get(i).copied().flatten()
+	}
+}
+
+impl FancyMatcher {
+	pub fn new(
+		pattern: &str,
+		case_insensitive: bool,
+		multi_line: bool,
+	) -> Result<Self, MatcherError> {
+		let regex = fancy_regex::RegexBuilder::new(pattern)
+			.case_insensitive(case_insensitive)
+			.multi_line(multi_line)
+			.build()?;
+		let names = regex
+			.capture_names()
+			.enumerate()
+			.filter_map(|(index, name)| name.map(|name| (name.to_string(), index)))
+			.collect();
+		Ok(Self { regex, names })
+	}
+
+	/// Runs `search` starting at byte offset `at`, one valid UTF-8 segment at a
+	/// time. The closure receives a segment and a char-aligned start position
+	/// within it; the returned offsets are relative to that segment.
+	fn search_segments<T>(
+		haystack: &[u8],
+		at: usize,
+		mut search: impl FnMut(&str, usize) -> Result<Option<T>, MatcherError>,
+	) -> Result<Option<(usize, T)>, MatcherError> {
+		if let Ok(text) = std::str::from_utf8(haystack) {
+			let pos = text.ceil_char_boundary(at);
+			return Ok(search(text, pos)?.map(|found| (0, found)));
+		}
+
+		let mut offset = 0usize;
+		for chunk in haystack.utf8_chunks() {
+			let valid = chunk.valid();
+			let segment_end = offset + valid.len();
+			if segment_end >= at {
+				let pos = valid.ceil_char_boundary(at.saturating_sub(offset));
+				if let Some(found) = search(valid, pos)? {
+					return Ok(Some((offset, found)));
+				}
+			}
+			offset = segment_end + chunk.invalid().len();
+		}
+		Ok(None)
+	}
+}
+
+impl Matcher for FancyMatcher {
+	type Captures = FancyCaptures;
+	type Error = MatcherError;
+
+	fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, MatcherError> {
+		let found = Self::search_segments(haystack, at, |text, pos| {
+			Ok(self
+				.regex
+				.find_from_pos(text, pos)?
+				.map(|m| (m.start(), m.end())))
+		})?;
+		Ok(found.map(|(offset, (start, end))| Match::new(start + offset, end + offset)))
+	}
+
+	fn new_captures(&self) -> Result<FancyCaptures, MatcherError> {
+		Ok(FancyCaptures { groups: vec![None; self.regex.captures_len()] })
+	}
+
+	fn capture_count(&self) -> usize {
+		self.regex.captures_len()
+	}
+
+	fn capture_index(&self, name: &str) -> Option<usize> {
+		self.names.get(name).copied()
+	}
+
+	fn captures_at(
+		&self,
+		haystack: &[u8],
+		at: usize,
+		caps: &mut FancyCaptures,
+	) -> Result<bool, MatcherError> {
+		let found = Self::search_segments(haystack, at, |text, pos| {
+			Ok(self.regex.captures_from_pos(text, pos)?.map(|captures| {
+				captures
+					.iter()
+					.map(|group| group.map(|m| (m.start(), m.end())))
+					.collect::<Vec<_>>()
+			}))
+		})?;
+		caps.groups.fill(None);
+		let Some((offset, groups)) = found else {
+			return Ok(false);
+		};
+		for (slot, group) in caps.groups.iter_mut().zip(groups) {
+			*slot = group.map(|(start, end)| Match::new(start + offset, end + offset));
+		}
+		Ok(true)
+	}
+
+	/// Reports candidates only, so the searcher re-checks each line on its own
+	/// and patterns such as `\s` cannot match across a line terminator.
+	fn find_candidate_line(&self, haystack: &[u8]) -> Result<Option<LineMatchKind>, MatcherError> {
+		Ok(self
+			.find(haystack)?
+			.map(|m| LineMatchKind::Candidate(m.start())))
+	}
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Engine dispatch
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Matcher used by all grep entry points.
+pub enum SearchMatcher {
+	Regex(RegexMatcher),
+	Fancy(FancyMatcher),
+}
+
+/// Capture groups for either engine.
+pub enum SearchCaptures {
+	Regex(RegexCaptures),
+	Fancy(FancyCaptures),
+}
+
+impl Captures for SearchCaptures {
+	fn len(&self) -> usize {
+		match self {
+			Self::Regex(caps) => caps.len(),
+			Self::Fancy(caps) => caps.len(),
+		}
+	}
+
+	fn get(&self, i: usize) -> Option<Match> {
+		match self {
+			Self::Regex(caps) => caps.get(i),
+			Self::Fancy(caps) => caps.get(i),
+		}
+	}
+}
+
+impl Matcher for SearchMatcher {
+	type Captures = SearchCaptures;
+	type Error = MatcherError;
+
+	fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, MatcherError> {
+		match self {
+			Self::Regex(matcher) => Ok(matcher.find_at(haystack, at).unwrap_or_default()),
+			Self::Fancy(matcher) => matcher.find_at(haystack, at),
+		}
+	}
+
+	fn new_captures(&self) -> Result<SearchCaptures, MatcherError> {
+		match self {
+			Self::Regex(matcher) => Ok(SearchCaptures::Regex(
+				matcher
+					.new_captures()
+					.map_err(|_| MatcherError("failed to allocate captures".to_string()))?,
+			)),
+			Self::Fancy(matcher) => matcher.new_captures().map(SearchCaptures::Fancy),
+		}
+	}
+
+	fn capture_count(&self) -> usize {
+		match self {
+			Self::Regex(matcher) => matcher.capture_count(),
+			Self::Fancy(matcher) => matcher.capture_count(),
+		}
+	}
+
+	fn capture_index(&self, name: &str) -> Option<usize> {
+		match self {
+			Self::Regex(matcher) => matcher.capture_index(name),
+			Self::Fancy(matcher) => matcher.capture_index(name),
+		}
+	}
+
+	fn captures_at(
+		&self,
+		haystack: &[u8],
+		at: usize,
+		caps: &mut SearchCaptures,
+	) -> Result<bool, MatcherError> {
+		match (self, caps) {
+			(Self::Regex(matcher), SearchCaptures::Regex(caps)) => {
+				Ok(matcher.captures_at(haystack, at, caps).unwrap_or(false))
+			},
+			(Self::Fancy(matcher), SearchCaptures::Fancy(caps)) => {
+				matcher.captures_at(haystack, at, caps)
+			},
+			_ => Ok(false),
+		}
+	}
+
+	fn shortest_match_at(&self, haystack: &[u8], at: usize) -> Result<Option<usize>, MatcherError> {
+		match self {
+			Self::Regex(matcher) => Ok(matcher.shortest_match_at(haystack, at).unwrap_or_default()),
+			Self::Fancy(matcher) => matcher.shortest_match_at(haystack, at),
+		}
+	}
+
+	fn non_matching_bytes(&self) -> Option<&ByteSet> {
+		match self {
+			Self::Regex(matcher) => matcher.non_matching_bytes(),
+			Self::Fancy(_) => None,
+		}
+	}
+
+	fn line_terminator(&self) -> Option<LineTerminator> {
+		match self {
+			Self::Regex(matcher) => matcher.line_terminator(),
+			Self::Fancy(_) => None,
+		}
+	}
+
+	fn find_candidate_line(&self, haystack: &[u8]) -> Result<Option<LineMatchKind>, MatcherError> {
+		match self {
+			Self::Regex(matcher) => Ok(matcher.find_candidate_line(haystack).unwrap_or_default()),
+			Self::Fancy(matcher) => matcher.find_candidate_line(haystack),
+		}
+	}
+}
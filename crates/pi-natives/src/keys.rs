@@ -238,9 +238,7 @@ pub fn matches_kitty_sequence(
 		&& base == expected_codepoint
 	{
 		let cp = parsed.codepoint;
-		let is_ascii_letter = u8::try_from(cp)
-			.ok()
-			.is_some_and(|b| b.is_ascii_alphabetic());
+		let is_ascii_letter = u8::try_from(cp).is_ok_and(|b| b.is_ascii_alphabetic());
 		let is_known_symbol = is_symbol_key(cp);
 		if !is_ascii_letter && !is_known_symbol {
 			return true;
@@ -365,23 +363,17 @@ fn parse_key_id(key_id: &str) -> Option<ParsedKeyId<'_>> {
 		};
 
 		match c0 {
-			b'c' | b'C' => {
-				if p.eq_ignore_ascii_case("ctrl") {
-					modifier |= MOD_CTRL;
-					continue;
-				}
+			b'c' | b'C' if p.eq_ignore_ascii_case("ctrl") => {
+				modifier |= MOD_CTRL;
+				continue;
 			},
-			b's' | b'S' => {
-				if p.eq_ignore_ascii_case("shift") {
-					modifier |= MOD_SHIFT;
-					continue;
-				}
+			b's' | b'S' if p.eq_ignore_ascii_case("shift") => {
+				modifier |= MOD_SHIFT;
+				continue;
 			},
-			b'a' | b'A' => {
-				if p.eq_ignore_ascii_case("alt") {
-					modifier |= MOD_ALT;
-					continue;
-				}
+			b'a' | b'A' if p.eq_ignore_ascii_case("alt") => {
+				modifier |= MOD_ALT;
+				continue;
 			},
 			_ => {},
 		}
@@ -489,9 +481,8 @@ fn matches_key_inner(bytes: &[u8], key_id: &str, kitty_protocol_active: bool) ->
 		if let Some(base) = parsed_base
 			&& base == codepoint
 		{
-			let is_ascii_letter = u8::try_from(parsed_codepoint)
-				.ok()
-				.is_some_and(|b| b.is_ascii_alphabetic());
+			let is_ascii_letter =
+				u8::try_from(parsed_codepoint).is_ok_and(|b| b.is_ascii_alphabetic());
 			let is_known_symbol = is_symbol_key(parsed_codepoint);
 			if !is_ascii_letter && !is_known_symbol {
 				return true;
@@ -1180,9 +1171,7 @@ fn format_kitty_key(parsed: &ParsedKittySequence) -> Option<Cow<'static, str>> {
 	let effective_mod = parsed.modifier & !LOCK_MASK;
 	let effective_codepoint = {
 		let cp = parsed.codepoint;
-		let is_ascii_letter = u8::try_from(cp)
-			.ok()
-			.is_some_and(|b| b.is_ascii_alphabetic());
+		let is_ascii_letter = u8::try_from(cp).is_ok_and(|b| b.is_ascii_alphabetic());
 		let is_known_symbol = is_symbol_key(cp);
 		if is_ascii_letter || is_known_symbol {
 			cp
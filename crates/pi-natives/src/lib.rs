@@ -22,7 +22,9 @@
 #![allow(clippy::trivially_copy_pass_by_ref, reason = "napi env idiom")]
 
 pub mod clipboard;
+pub mod content_index;
 pub mod fd;
+pub mod frecency;
 pub mod fs_cache;
 pub mod glob;
 pub mod grep;
@@ -37,3 +39,4 @@ pub mod shell;
 pub mod system_info;
 pub mod task;
 pub mod text;
+pub mod tree;
@@ -2,7 +2,9 @@
 //!
 //! # Overview
 //! Provides a stateful PTY session that supports streaming output and stdin
-//! passthrough while a command is running.
+//! passthrough while a command is running. Output is also fed into a
+//! terminal screen model (see [`screen`]), so `snapshot()` can report the
+//! rendered screen of interactive programs and `expect()` can script them.
 
 use std::{
 	collections::HashMap,
@@ -12,6 +14,7 @@ use std::{
 	time::Duration,
 };
 
+use fancy_regex::Regex;
 use napi::{
 	bindgen_prelude::*,
 	threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
@@ -21,6 +24,12 @@ use portable_pty::{CommandBuilder, PtySize, native_pty_system};
 
 use crate::task;
 
+mod screen;
+mod terminal;
+
+use screen::Screen;
+use terminal::{SharedTerminal, Terminal};
+
 /// Options for running a command in a PTY session.
 #[napi(object)]
 pub struct PtyStartOptions<'env> {
@@ -39,6 +48,81 @@ pub struct PtyStartOptions<'env> {
 	pub cols:       Option<u16>,
 	/// PTY row count.
 	pub rows:       Option<u16>,
+	/// Lines of scrollback kept by the screen model (default: 1000).
+	pub scrollback: Option<u32>,
+}
+
+/// Options for `PtySession.snapshot`.
+#[napi(object)]
+pub struct PtySnapshotOptions {
+	/// Text format: "plain" (default) or "ansi" (with SGR color and style
+	/// codes).
+	pub format:     Option<String>,
+	/// Lines of scrollback to include above the screen (default: 0).
+	pub scrollback: Option<u32>,
+}
+
+/// Rendered terminal screen of a PTY session.
+#[napi(object)]
+pub struct PtySnapshot {
+	/// Screen rows (after any scrollback lines), joined with newlines.
+	pub text:             String,
+	/// Screen row count.
+	pub rows:             u16,
+	/// Screen column count.
+	pub cols:             u16,
+	/// Zero-based cursor row on the screen.
+	#[napi(js_name = "cursorRow")]
+	pub cursor_row:       u16,
+	/// Zero-based cursor column on the screen.
+	#[napi(js_name = "cursorCol")]
+	pub cursor_col:       u16,
+	/// Whether the program shows the cursor.
+	#[napi(js_name = "cursorVisible")]
+	pub cursor_visible:   bool,
+	/// Whether the alternate screen (used by full-screen programs such as
+	/// vim or htop) is active.
+	#[napi(js_name = "alternateScreen")]
+	pub alternate_screen: bool,
+	/// Zero-based screen rows that changed since the previous snapshot.
+	#[napi(js_name = "dirtyRows")]
+	pub dirty_rows:       Vec<u32>,
+	/// Number of scrollback lines at the start of `text`.
+	#[napi(js_name = "scrollbackLines")]
+	pub scrollback_lines: u32,
+}
+
+/// Options for `PtySession.expect`.
+#[napi(object)]
+pub struct PtyExpectOptions<'env> {
+	/// What to match: "output" (default) for the raw output not consumed by
+	/// earlier matches, or "screen" for the rendered screen text.
+	pub target: Option<String>,
+	/// Abort signal for cancelling the wait.
+	pub signal: Option<Unknown<'env>>,
+}
+
+/// Result of `PtySession.expect`.
+#[napi(object)]
+pub struct PtyExpectResult {
+	/// Whether the pattern matched.
+	pub matched:   bool,
+	/// Text before the match; without a match, the unconsumed output (or the
+	/// screen text).
+	pub before:    String,
+	/// Matched text.
+	#[napi(js_name = "match")]
+	pub match_:    Option<String>,
+	/// Capture groups of the match, `null` for groups that did not
+	/// participate.
+	pub groups:    Vec<Option<String>>,
+	/// Whether the command exited without a match.
+	pub eof:       bool,
+	/// Whether the wait was cancelled by abort signal.
+	pub cancelled: bool,
+	/// Whether the wait timed out.
+	#[napi(js_name = "timedOut")]
+	pub timed_out: bool,
 }
 
 /// Result of a PTY command run.
@@ -79,7 +163,9 @@ struct PtySessionCore {
 /// Stateful PTY session for interactive stdin/stdout passthrough.
 #[napi]
 pub struct PtySession {
-	core: Arc<Mutex<Option<PtySessionCore>>>,
+	core:     Arc<Mutex<Option<PtySessionCore>>>,
+	/// Output of the current or most recent command.
+	terminal: Arc<SharedTerminal>,
 }
 
 impl Default for PtySession {
@@ -92,7 +178,7 @@ impl Default for PtySession {
 impl PtySession {
 	#[napi(constructor)]
 	pub fn new() -> Self {
-		Self { core: Arc::new(Mutex::new(None)) }
+		Self { core: Arc::new(Mutex::new(None)), terminal: Arc::default() }
 	}
 
 	/// Start a PTY command and stream output chunks via callback.
@@ -112,8 +198,12 @@ impl PtySession {
 			cols:    options.cols.unwrap_or(120).clamp(20, 400),
 			rows:    options.rows.unwrap_or(40).clamp(5, 200),
 		};
+		let scrollback = options
+			.scrollback
+			.map_or(screen::DEFAULT_SCROLLBACK, |lines| lines as usize);
 		let ct = task::CancelToken::new(options.timeout_ms, options.signal);
 		let core = Arc::clone(&self.core);
+		let terminal = Arc::clone(&self.terminal);
 
 		// Register control channel synchronously so write()/kill() work immediately.
 		let (control_tx, control_rx) = mpsc::channel::<ControlMessage>();
@@ -126,10 +216,14 @@ impl PtySession {
 			}
 			*guard = Some(PtySessionCore { control_tx });
 		}
+		terminal.reset(Terminal::new(Screen::new(run_config.rows, run_config.cols, scrollback)))?;
 		task::future(env, "pty.start", async move {
-			let run_result =
-				tokio::task::spawn_blocking(move || run_pty_sync(run_config, on_chunk, control_rx, ct))
-					.await;
+			let run_result = tokio::task::spawn_blocking({
+				let terminal = Arc::clone(&terminal);
+				move || run_pty_sync(run_config, on_chunk, &terminal, control_rx, ct)
+			})
+			.await;
+			terminal.finish();
 
 			// Always clear core regardless of result
 			let mut guard = core
@@ -148,13 +242,27 @@ impl PtySession {
 	/// Write raw input bytes to PTY stdin.
 	#[napi]
 	pub fn write(&self, data: String) -> Result<()> {
-		self.send_control(ControlMessage::Input(data))
+		self.send_message(ControlMessage::Input(data))
+	}
+
+	/// Write a line of input, terminated by carriage return like the Enter key.
+	#[napi]
+	pub fn send_line(&self, data: String) -> Result<()> {
+		self.send_message(ControlMessage::Input(format!("{data}\r")))
+	}
+
+	/// Send a control character, e.g. "c" (or "^C") for Ctrl+C.
+	#[napi]
+	pub fn send_control(&self, key: String) -> Result<()> {
+		let control = control_char(&key)
+			.ok_or_else(|| Error::from_reason(format!("Unknown control key: {key}")))?;
+		self.send_message(ControlMessage::Input(control.to_string()))
 	}
 
 	/// Resize the active PTY.
 	#[napi]
 	pub fn resize(&self, cols: u16, rows: u16) -> Result<()> {
-		self.send_control(ControlMessage::Resize {
+		self.send_message(ControlMessage::Resize {
 			cols: cols.clamp(20, 400),
 			rows: rows.clamp(5, 200),
 		})
@@ -163,12 +271,62 @@ impl PtySession {
 	/// Force-kill the active PTY command.
 	#[napi]
 	pub fn kill(&self) -> Result<()> {
-		self.send_control(ControlMessage::Kill)
+		self.send_message(ControlMessage::Kill)
+	}
+
+	/// Render the terminal screen of the running (or last) command.
+	#[napi]
+	pub fn snapshot(&self, options: Option<PtySnapshotOptions>) -> Result<PtySnapshot> {
+		let (format, scrollback) =
+			options.map_or((None, None), |options| (options.format, options.scrollback));
+		let ansi = match format.as_deref() {
+			None | Some("plain") => false,
+			Some("ansi") => true,
+			Some(other) => {
+				return Err(Error::from_reason(format!("Unknown snapshot format: {other}")));
+			},
+		};
+		self.terminal.with(|terminal| {
+			terminal
+				.screen
+				.snapshot(ansi, scrollback.map_or(0, |lines| lines as usize))
+		})
+	}
+
+	/// Wait until the command output (or rendered screen) matches a regex.
+	///
+	/// Output matches consume the output up to the end of the match, so
+	/// successive calls walk through the output like `expect(1)`. Resolves
+	/// with `matched: false` when the command exits, the timeout elapses, or
+	/// the signal aborts first.
+	#[napi]
+	pub fn expect<'env>(
+		&self,
+		env: &'env Env,
+		pattern: String,
+		timeout_ms: Option<u32>,
+		options: Option<PtyExpectOptions<'env>>,
+	) -> Result<PromiseRaw<'env, PtyExpectResult>> {
+		let (target, signal) =
+			options.map_or((None, None), |options| (options.target, options.signal));
+		let on_screen = match target.as_deref() {
+			None | Some("output") => false,
+			Some("screen") => true,
+			Some(other) => {
+				return Err(Error::from_reason(format!("Unknown expect target: {other}")));
+			},
+		};
+		let regex = Regex::new(&pattern)
+			.map_err(|err| Error::from_reason(format!("Invalid expect pattern: {err}")))?;
+		self.terminal.with(|_| ())?;
+		let ct = task::CancelToken::new(timeout_ms, signal);
+		let terminal = Arc::clone(&self.terminal);
+		task::future(env, "pty.expect", async move { terminal.expect(&regex, on_screen, ct).await })
 	}
 }
 
 impl PtySession {
-	fn send_control(&self, message: ControlMessage) -> Result<()> {
+	fn send_message(&self, message: ControlMessage) -> Result<()> {
 		let guard = self
 			.core
 			.lock()
@@ -186,6 +344,7 @@ impl PtySession {
 fn run_pty_sync(
 	config: PtyRunConfig,
 	on_chunk: Option<ThreadsafeFunction<String>>,
+	terminal: &SharedTerminal,
 	control_rx: mpsc::Receiver<ControlMessage>,
 	ct: task::CancelToken,
 ) -> Result<PtyRunResult> {
@@ -307,6 +466,7 @@ fn run_pty_sync(
 				},
 				Ok(ControlMessage::Resize { cols, rows }) => {
 					let _ = master.resize(PtySize { rows, cols, pixel_width: 0, pixel_height: 0 });
+					terminal.resize(rows, cols);
 				},
 				Ok(ControlMessage::Kill) => {
 					cancelled = true;
@@ -319,7 +479,12 @@ fn run_pty_sync(
 
 		loop {
 			match reader_rx.try_recv() {
-				Ok(ReaderEvent::Chunk(chunk)) => emit_chunk(&chunk, on_chunk.as_ref()),
+				Ok(ReaderEvent::Chunk(chunk)) => {
+					// Update the terminal first, so snapshots taken from the chunk
+					// callback include the chunk.
+					terminal.push(&chunk);
+					emit_chunk(&chunk, on_chunk.as_ref());
+				},
 				Ok(ReaderEvent::Done) => {
 					reader_done = true;
 					break;
@@ -357,6 +522,20 @@ fn run_pty_sync(
 	Ok(PtyRunResult { exit_code, cancelled, timed_out })
 }
 
+/// Maps a control key such as "c", "^C" or "[" to its control character.
+fn control_char(key: &str) -> Option<char> {
+	let key = key.strip_prefix('^').unwrap_or(key);
+	let mut chars = key.chars();
+	let (Some(key), None) = (chars.next(), chars.next()) else {
+		return None;
+	};
+	match key.to_ascii_uppercase() {
+		'?' => Some('\x7f'),
+		key @ '@'..='_' => Some(char::from(key as u8 & 0x1f)),
+		_ => None,
+	}
+}
+
 fn emit_chunk(text: &str, callback: Option<&ThreadsafeFunction<String>>) {
 	if let Some(callback) = callback {
 		callback.call(Ok(text.to_string()), ThreadsafeFunctionCallMode::NonBlocking);
@@ -0,0 +1,133 @@
+//! Terminal screen model for PTY sessions.
+//!
+//! Feeds PTY output through a vt100/xterm parser so a session can report what
+//! an interactive program currently shows (a grid with scrollback, cursor and
+//! alternate screen) instead of the raw stream of cursor-movement codes.
+
+use super::PtySnapshot;
+
+/// Lines of scrollback kept above the screen unless configured otherwise.
+pub const DEFAULT_SCROLLBACK: usize = 1000;
+
+/// Resets attributes at the end of each ANSI row, so rows render
+/// independently.
+const RESET: &str = "\x1b[m";
+
+pub struct Screen {
+	parser:    vt100::Parser,
+	/// Formatted visible rows as of the previous snapshot.
+	last_rows: Vec<Vec<u8>>,
+}
+
+impl Screen {
+	pub fn new(rows: u16, cols: u16, scrollback: usize) -> Self {
+		Self { parser: vt100::Parser::new(rows, cols, scrollback), last_rows: Vec::new() }
+	}
+
+	pub fn process(&mut self, bytes: &[u8]) {
+		self.parser.process(bytes);
+	}
+
+	pub fn resize(&mut self, rows: u16, cols: u16) {
+		self.parser.screen_mut().set_size(rows, cols);
+	}
+
+	/// Visible screen as plain text, without trailing blank lines.
+	pub fn contents(&self) -> String {
+		self.parser.screen().contents()
+	}
+
+	/// Renders the visible screen, preceded by up to `scrollback` lines of
+	/// history, and marks the screen clean for the next snapshot's
+	/// `dirty_rows`.
+	pub fn snapshot(&mut self, ansi: bool, scrollback: usize) -> PtySnapshot {
+		let screen = self.parser.screen();
+		let (rows, cols) = screen.size();
+		let current: Vec<Vec<u8>> = screen.rows_formatted(0, cols).collect();
+		let dirty_rows = (0..current.len())
+			.filter(|&row| self.last_rows.get(row) != Some(&current[row]))
+			.map(|row| row as u32)
+			.collect();
+
+		let mut lines = history(screen, ansi, scrollback);
+		let scrollback_lines = lines.len() as u32;
+		lines.extend(render_rows(screen, ansi));
+		let (cursor_row, cursor_col) = screen.cursor_position();
+		let snapshot = PtySnapshot {
+			text: lines.join("\n"),
+			rows,
+			cols,
+			cursor_row,
+			cursor_col,
+			cursor_visible: !screen.hide_cursor(),
+			alternate_screen: screen.alternate_screen(),
+			dirty_rows,
+			scrollback_lines,
+		};
+		self.last_rows = current;
+		snapshot
+	}
+}
+
+/// Up to `limit` lines of scrollback, oldest first.
+fn history(screen: &vt100::Screen, ansi: bool, limit: usize) -> Vec<String> {
+	if limit == 0 {
+		return Vec::new();
+	}
+	let mut view = screen.clone();
+	view.set_scrollback(limit);
+	// Clamped to the available scrollback.
+	let mut offset = view.scrollback();
+	let rows = usize::from(view.size().0);
+	let mut lines = Vec::with_capacity(offset);
+	// Scrolled back by `offset`, the top rows of the view are the history
+	// lines starting `offset` lines above the screen.
+	while offset > 0 {
+		view.set_scrollback(offset);
+		let count = offset.min(rows);
+		lines.extend(render_rows(&view, ansi).into_iter().take(count));
+		offset -= count;
+	}
+	lines
+}
+
+fn render_rows(screen: &vt100::Screen, ansi: bool) -> Vec<String> {
+	let cols = screen.size().1;
+	if ansi {
+		screen
+			.rows_formatted(0, cols)
+			.map(|row| format!("{}{RESET}", String::from_utf8_lossy(&row)))
+			.collect()
+	} else {
+		screen.rows(0, cols).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_snapshot_tracks_screen_and_dirty_rows() {
+		let mut screen = Screen::new(3, 20, 10);
+		screen.process(b"one\r\ntwo\r\nthree\r\nfour");
+		let snapshot = screen.snapshot(false, 5);
+		assert_eq!(snapshot.text, "one\ntwo\nthree\nfour");
+		assert_eq!(snapshot.scrollback_lines, 1);
+		assert_eq!((snapshot.cursor_row, snapshot.cursor_col), (2, 4));
+		assert_eq!(snapshot.dirty_rows, [0, 1, 2]);
+
+		// Progress-bar style redraw of the last row.
+		screen.process(b"\r\x1b[2Kfour: 50%\r\x1b[2Kfour: done");
+		let snapshot = screen.snapshot(false, 0);
+		assert_eq!(snapshot.text, "two\nthree\nfour: done");
+		assert_eq!(snapshot.dirty_rows, [2]);
+
+		screen.process(b"\x1b[?1049h\x1b[H\x1b[1mtop\x1b[m");
+		let snapshot = screen.snapshot(true, 0);
+		assert!(snapshot.alternate_screen);
+		assert!(snapshot.text.starts_with("\x1b[1mtop"));
+		screen.process(b"\x1b[?1049l");
+		assert!(!screen.snapshot(false, 0).alternate_screen);
+	}
+}
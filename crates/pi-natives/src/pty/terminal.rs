@@ -0,0 +1,222 @@
+//! Output state shared between a PTY run loop and `PtySession` calls.
+//!
+//! The run loop feeds every chunk into a [`Terminal`], which keeps the screen
+//! model and the output not yet consumed by `expect()`, and wakes pending
+//! `expect()` calls so they can re-check their pattern.
+
+use std::{
+	pin::pin,
+	sync::{Mutex, MutexGuard},
+};
+
+use fancy_regex::Regex;
+use napi::{Error, Result};
+use tokio::sync::Notify;
+
+use super::{PtyExpectResult, screen::Screen};
+use crate::task::{AbortReason, CancelToken};
+
+/// Maximum bytes of unconsumed output kept for `expect()`; older output is
+/// dropped first.
+const MAX_PENDING: usize = 1 << 20;
+
+pub struct Terminal {
+	pub screen: Screen,
+	/// Output received since the last successful output match.
+	pending:    String,
+	/// Whether the command is still running.
+	running:    bool,
+}
+
+impl Terminal {
+	pub const fn new(screen: Screen) -> Self {
+		Self { screen, pending: String::new(), running: true }
+	}
+
+	fn push(&mut self, chunk: &str) {
+		self.screen.process(chunk.as_bytes());
+		self.pending.push_str(chunk);
+		if self.pending.len() > MAX_PENDING {
+			let mut cut = self.pending.len() - MAX_PENDING;
+			while !self.pending.is_char_boundary(cut) {
+				cut += 1;
+			}
+			self.pending.drain(..cut);
+		}
+	}
+
+	/// Matches `regex` against the pending output, consuming it through the
+	/// end of the match, or against the rendered screen.
+	fn find(&mut self, regex: &Regex, on_screen: bool) -> Result<Option<PtyExpectResult>> {
+		let screen_text;
+		let text = if on_screen {
+			screen_text = self.screen.contents();
+			&screen_text
+		} else {
+			&self.pending
+		};
+		let Some(captures) = regex
+			.captures(text)
+			.map_err(|err| Error::from_reason(format!("Expect pattern failed: {err}")))?
+		else {
+			return Ok(None);
+		};
+		let whole = captures.get(0).expect("capture group 0 is always present");
+		let end = whole.end();
+		let result = PtyExpectResult {
+			matched:   true,
+			before:    text[..whole.start()].to_string(),
+			match_:    Some(whole.as_str().to_string()),
+			groups:    captures
+				.iter()
+				.skip(1)
+				.map(|group| group.map(|group| group.as_str().to_string()))
+				.collect(),
+			eof:       false,
+			cancelled: false,
+			timed_out: false,
+		};
+		if !on_screen {
+			self.pending.drain(..end);
+		}
+		Ok(Some(result))
+	}
+
+	/// Result for an `expect()` that ended without a match; `before` holds the
+	/// unconsumed output (or the screen), which stays unconsumed.
+	fn unmatched(&self, on_screen: bool) -> PtyExpectResult {
+		PtyExpectResult {
+			matched:   false,
+			before:    if on_screen {
+				self.screen.contents()
+			} else {
+				self.pending.clone()
+			},
+			match_:    None,
+			groups:    Vec::new(),
+			eof:       !self.running,
+			cancelled: false,
+			timed_out: false,
+		}
+	}
+}
+
+/// Terminal of the current or most recent command, with change notification.
+#[derive(Default)]
+pub struct SharedTerminal {
+	state:   Mutex<Option<Terminal>>,
+	changed: Notify,
+}
+
+impl SharedTerminal {
+	/// Replaces the terminal for a new command.
+	pub fn reset(&self, terminal: Terminal) -> Result<()> {
+		*self.lock()? = Some(terminal);
+		Ok(())
+	}
+
+	/// Runs `read` on the terminal, failing if no command was started.
+	pub fn with<T>(&self, read: impl FnOnce(&mut Terminal) -> T) -> Result<T> {
+		let mut guard = self.lock()?;
+		let terminal = guard
+			.as_mut()
+			.ok_or_else(|| Error::from_reason("PTY session has not started"))?;
+		Ok(read(terminal))
+	}
+
+	/// Feeds a chunk of command output.
+	pub fn push(&self, chunk: &str) {
+		self.update(|terminal| terminal.push(chunk));
+	}
+
+	pub fn resize(&self, rows: u16, cols: u16) {
+		self.update(|terminal| terminal.screen.resize(rows, cols));
+	}
+
+	/// Marks the command as exited.
+	pub fn finish(&self) {
+		self.update(|terminal| terminal.running = false);
+	}
+
+	/// Waits until `regex` matches the output (or the screen), the command
+	/// exits, or `ct` is aborted.
+	pub async fn expect(
+		&self,
+		regex: &Regex,
+		on_screen: bool,
+		ct: CancelToken,
+	) -> Result<PtyExpectResult> {
+		let mut aborted = pin!(ct.wait());
+		loop {
+			// Register for wakeups before checking, so a chunk arriving in
+			// between is not missed.
+			let mut changed = pin!(self.changed.notified());
+			changed.as_mut().enable();
+			let unmatched = {
+				let mut guard = self.lock()?;
+				let terminal = guard
+					.as_mut()
+					.ok_or_else(|| Error::from_reason("PTY session has not started"))?;
+				if let Some(result) = terminal.find(regex, on_screen)? {
+					return Ok(result);
+				}
+				terminal.unmatched(on_screen)
+			};
+			if unmatched.eof {
+				return Ok(unmatched);
+			}
+			tokio::select! {
+				() = &mut changed => {},
+				reason = &mut aborted => {
+					let mut result = self.with(|terminal| terminal.unmatched(on_screen))?;
+					result.cancelled = !matches!(reason, AbortReason::Timeout);
+					result.timed_out = matches!(reason, AbortReason::Timeout);
+					return Ok(result);
+				},
+			}
+		}
+	}
+
+	fn update(&self, update: impl FnOnce(&mut Terminal)) {
+		if let Ok(mut guard) = self.state.lock()
+			&& let Some(terminal) = guard.as_mut()
+		{
+			update(terminal);
+			drop(guard);
+			self.changed.notify_waiters();
+		}
+	}
+
+	fn lock(&self) -> Result<MutexGuard<'_, Option<Terminal>>> {
+		self
+			.state
+			.lock()
+			.map_err(|_| Error::from_reason("PTY terminal lock poisoned"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_find_consumes_output_through_match() {
+		let mut terminal = Terminal::new(Screen::new(5, 40, 0));
+		terminal.push("Name: \x1b[1mfoo\x1b[m\r\nPassword: ");
+		let regex = Regex::new(r"(\w+): $").unwrap();
+		let result = terminal.find(&regex, false).unwrap().unwrap();
+		assert_eq!(result.match_.as_deref(), Some("Password: "));
+		assert_eq!(result.before, "Name: \x1b[1mfoo\x1b[m\r\n");
+		assert_eq!(result.groups, [Some("Password".to_string())]);
+		assert!(terminal.find(&regex, false).unwrap().is_none());
+
+		// Screen matches see rendered text and consume nothing.
+		let regex = Regex::new(r"Name: (\w+)").unwrap();
+		let result = terminal.find(&regex, true).unwrap().unwrap();
+		assert_eq!(result.groups, [Some("foo".to_string())]);
+		assert!(terminal.find(&regex, true).unwrap().is_some());
+
+		terminal.running = false;
+		assert!(terminal.unmatched(false).eof);
+	}
+}
@@ -2,7 +2,21 @@
 //!
 //! # Overview
 //! Executes shell commands in a non-interactive brush-core shell, streaming
-//! output back to JavaScript via a threadsafe callback.
+//! output back to JavaScript via a threadsafe callback. Stdout and stderr
+//! share one pipe unless `separateStreams` is set, in which case each chunk is
+//! tagged with its stream and a sequence number.
+//!
+//! Commands of a `Shell` session lead their own process groups. Cancelling a
+//! command kills its foreground process group and the jobs it started, but
+//! keeps the session (cwd, variables, functions, aliases) for the next command
+//! unless the interpreter fails to unwind. Results report the session's cwd
+//! and exported-variable changes after the command.
+//!
+//! Stdin is empty unless the `stdin` option provides data, or `keepStdinOpen`
+//! lets `writeStdin()` stream more until `closeStdin()`.
+//!
+//! `analyzeCommand()` parses a command without running it and reports the
+//! programs it invokes, its redirects and risk flags.
 //!
 //! # Example
 //! ```ignore
@@ -12,17 +26,19 @@
 //! });
 //! ```
 
-#[cfg(windows)]
-use std::collections::HashSet;
 use std::{
-	collections::HashMap,
+	collections::{BTreeMap, HashMap, HashSet},
 	fs,
 	io::{self, Write},
 	str,
-	sync::Arc,
+	sync::{
+		Arc,
+		atomic::{AtomicU32, AtomicU64, Ordering},
+	},
 	time::Duration,
 };
 
+mod analyze;
 #[cfg(windows)]
 mod windows;
 
@@ -45,6 +61,7 @@ use napi::{
 	},
 };
 use napi_derive::napi;
+use parking_lot::Mutex;
 use tokio::io::AsyncReadExt as _;
 use tokio_util::sync::CancellationToken;
 #[cfg(windows)]
@@ -75,38 +92,268 @@ pub struct ShellOptions {
 /// Options for running a shell command (internal, lifetime-free).
 struct ShellRunConfig {
 	/// Command string to execute in the shell.
-	command: String,
+	command:          String,
 	/// Working directory for the command.
-	cwd:     Option<String>,
+	cwd:              Option<String>,
 	/// Environment variables to apply for this command only.
-	env:     Option<HashMap<String, String>>,
+	env:              Option<HashMap<String, String>>,
+	/// Whether stdout and stderr are read from separate pipes.
+	separate_streams: bool,
+}
+
+/// Stdin chunks for a command, ending when the sender is dropped.
+type StdinReceiver = std::sync::mpsc::Receiver<Vec<u8>>;
+type StdinSender = std::sync::mpsc::Sender<Vec<u8>>;
+
+/// Stdin of a shell's unfinished `keepStdinOpen` runs by run ID, with whether
+/// the run has started.
+#[derive(Default)]
+struct OpenStdins(Mutex<BTreeMap<u64, (StdinSender, bool)>>);
+
+impl OpenStdins {
+	/// Registers the stdin of run `run_id` until the returned guard is dropped.
+	/// Writes are buffered until the run's command starts.
+	fn register(self: &Arc<Self>, run_id: u64, tx: StdinSender) -> OpenStdin {
+		self.0.lock().insert(run_id, (tx, false));
+		OpenStdin { stdins: self.clone(), run_id }
+	}
+
+	/// The run that `write` and `close` apply to: the started run, or else the
+	/// earliest queued one.
+	fn target(runs: &BTreeMap<u64, (StdinSender, bool)>) -> Option<u64> {
+		runs
+			.iter()
+			.find(|(_, (_, started))| *started)
+			.or_else(|| runs.iter().next())
+			.map(|(run_id, _)| *run_id)
+	}
+
+	/// Sends `bytes` to the target run's stdin, returning whether there is one
+	/// that still reads it.
+	fn write(&self, bytes: Vec<u8>) -> bool {
+		let runs = self.0.lock();
+		Self::target(&runs)
+			.and_then(|run_id| runs.get(&run_id))
+			.is_some_and(|(tx, _)| tx.send(bytes).is_ok())
+	}
+
+	/// Closes the target run's stdin.
+	fn close(&self) {
+		let mut runs = self.0.lock();
+		if let Some(run_id) = Self::target(&runs) {
+			runs.remove(&run_id);
+		}
+	}
+}
+
+/// Registration of a run's stdin in [`OpenStdins`], removed on drop.
+struct OpenStdin {
+	stdins: Arc<OpenStdins>,
+	run_id: u64,
+}
+
+impl OpenStdin {
+	/// Marks the run as started, making it the target of writes.
+	fn start(&self) {
+		if let Some((_, started)) = self.stdins.0.lock().get_mut(&self.run_id) {
+			*started = true;
+		}
+	}
+}
+
+impl Drop for OpenStdin {
+	fn drop(&mut self) {
+		self.stdins.0.lock().remove(&self.run_id);
+	}
+}
+
+/// Sets up a command's stdin: `data` is queued first, and with `keep_open`
+/// the returned sender streams more until dropped. Without either, stdin is
+/// bound to the null device.
+fn stdin_channel(
+	data: Option<Either<String, Uint8Array>>,
+	keep_open: bool,
+) -> (Option<StdinReceiver>, Option<StdinSender>) {
+	if data.is_none() && !keep_open {
+		return (None, None);
+	}
+	let (tx, rx) = std::sync::mpsc::channel();
+	if let Some(data) = data {
+		let _ = tx.send(stdin_bytes(data));
+	}
+	(Some(rx), keep_open.then_some(tx))
+}
+
+fn stdin_bytes(data: Either<String, Uint8Array>) -> Vec<u8> {
+	match data {
+		Either::A(text) => text.into_bytes(),
+		Either::B(bytes) => bytes.to_vec(),
+	}
+}
+
+/// Output chunk of a command run with `separateStreams`.
+#[napi(object)]
+pub struct ShellChunk {
+	/// Stream the chunk was read from: "stdout" or "stderr".
+	pub stream: String,
+	/// Sequence number across both streams, in the order chunks were read.
+	pub seq:    u32,
+	/// Chunk text.
+	pub text:   String,
+}
+
+/// Callback payload: plain text, or a tagged chunk with `separateStreams`.
+type ChunkCallback = ThreadsafeFunction<Either<String, ShellChunk>>;
+/// Receiver of the chunks delivered through an [`OutputSink`].
+type ChunkFn = dyn Fn(Either<String, ShellChunk>) + Send + Sync;
+
+#[derive(Clone, Copy)]
+enum OutputStream {
+	Stdout,
+	Stderr,
+}
+
+impl OutputStream {
+	const fn as_str(self) -> &'static str {
+		match self {
+			Self::Stdout => "stdout",
+			Self::Stderr => "stderr",
+		}
+	}
+}
+
+/// Output delivery shared by the readers of one command.
+#[derive(Clone)]
+struct OutputSink {
+	callback:     Option<Arc<ChunkFn>>,
+	/// Next chunk sequence number, shared by both streams.
+	seq:          Arc<AtomicU32>,
+	stdout_bytes: Arc<AtomicU64>,
+	stderr_bytes: Arc<AtomicU64>,
+}
+
+impl OutputSink {
+	fn new(callback: Option<ChunkCallback>) -> Self {
+		Self::with_fn(callback.map(|callback| -> Arc<ChunkFn> {
+			Arc::new(move |payload| {
+				callback.call(Ok(payload), ThreadsafeFunctionCallMode::NonBlocking);
+			})
+		}))
+	}
+
+	fn with_fn(callback: Option<Arc<ChunkFn>>) -> Self {
+		Self {
+			callback,
+			seq: Arc::default(),
+			stdout_bytes: Arc::default(),
+			stderr_bytes: Arc::default(),
+		}
+	}
+
+	fn count(&self, stream: OutputStream, bytes: usize) {
+		let counter = match stream {
+			OutputStream::Stdout => &self.stdout_bytes,
+			OutputStream::Stderr => &self.stderr_bytes,
+		};
+		counter.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+
+	fn emit(&self, stream: Option<OutputStream>, text: &str) {
+		let Some(callback) = &self.callback else {
+			return;
+		};
+		let payload = match stream {
+			None => Either::A(text.to_string()),
+			Some(stream) => Either::B(ShellChunk {
+				stream: stream.as_str().to_string(),
+				seq:    self.seq.fetch_add(1, Ordering::Relaxed),
+				text:   text.to_string(),
+			}),
+		};
+		callback(payload);
+	}
+
+	/// Byte counts per stream, known only when the streams are separate.
+	fn byte_counts(&self, separate: bool) -> (Option<f64>, Option<f64>) {
+		let stdout = self.stdout_bytes.load(Ordering::Relaxed) as f64;
+		let stderr = self.stderr_bytes.load(Ordering::Relaxed) as f64;
+		if separate {
+			(Some(stdout), Some(stderr))
+		} else {
+			(None, None)
+		}
+	}
 }
 
 /// Options for running a shell command.
 #[napi(object)]
 pub struct ShellRunOptions<'env> {
 	/// Command string to execute in the shell.
-	pub command:    String,
+	pub command:          String,
 	/// Working directory for the command.
-	pub cwd:        Option<String>,
+	pub cwd:              Option<String>,
 	/// Environment variables to apply for this command only.
-	pub env:        Option<HashMap<String, String>>,
+	pub env:              Option<HashMap<String, String>>,
 	/// Timeout in milliseconds before cancelling the command.
 	#[napi(js_name = "timeoutMs")]
-	pub timeout_ms: Option<u32>,
+	pub timeout_ms:       Option<u32>,
 	/// Abort signal for cancelling the operation.
-	pub signal:     Option<Unknown<'env>>,
+	pub signal:           Option<Unknown<'env>>,
+	/// Read stdout and stderr from separate pipes and pass `ShellChunk`s
+	/// tagged with their stream to the callback.
+	#[napi(js_name = "separateStreams")]
+	pub separate_streams: Option<bool>,
+	/// Data to feed to the command's stdin.
+	pub stdin:            Option<Either<String, Uint8Array>>,
+	/// Keep stdin open after `stdin` so `writeStdin()` can stream more input
+	/// until `closeStdin()` or the command exits.
+	#[napi(js_name = "keepStdinOpen")]
+	pub keep_stdin_open:  Option<bool>,
 }
 
 /// Result of running a shell command.
 #[napi(object)]
 pub struct ShellRunResult {
 	/// Exit code when the command completes normally.
-	pub exit_code: Option<i32>,
+	pub exit_code:     Option<i32>,
 	/// Whether the command was cancelled via abort.
-	pub cancelled: bool,
+	pub cancelled:     bool,
 	/// Whether the command timed out before completion.
-	pub timed_out: bool,
+	pub timed_out:     bool,
+	/// Bytes written to stdout (with `separateStreams`).
+	#[napi(js_name = "stdoutBytes")]
+	pub stdout_bytes:  Option<f64>,
+	/// Bytes written to stderr (with `separateStreams`).
+	#[napi(js_name = "stderrBytes")]
+	pub stderr_bytes:  Option<f64>,
+	/// Whether the session was discarded after this command, because the
+	/// command exited the shell or the interpreter did not unwind after
+	/// cancellation. The next command starts a fresh session.
+	#[napi(js_name = "sessionReset")]
+	pub session_reset: bool,
+	/// Working directory of the session after the command, unless the session
+	/// was discarded.
+	pub cwd:           Option<String>,
+	/// Exported variables the command added, changed or removed, when it ran
+	/// to completion.
+	#[napi(js_name = "envChanges")]
+	pub env_changes:   Option<ShellEnvChanges>,
+	/// Name of the signal that terminated the command (e.g. "SIGKILL").
+	pub signal:        Option<String>,
+	/// Whether the command dumped core when terminated by `signal`.
+	#[napi(js_name = "coreDumped")]
+	pub core_dumped:   Option<bool>,
+}
+
+/// Changes to a session's exported variables made by a command.
+#[napi(object)]
+pub struct ShellEnvChanges {
+	/// Newly exported variables and their values.
+	pub added:   HashMap<String, String>,
+	/// Exported variables with a new value.
+	pub changed: HashMap<String, String>,
+	/// Variables no longer exported.
+	pub removed: Vec<String>,
 }
 
 /// Persistent brush-core shell session.
@@ -114,6 +361,11 @@ pub struct ShellRunResult {
 pub struct Shell {
 	session: Arc<TokioMutex<Option<ShellSessionCore>>>,
 	config:  ShellConfig,
+	/// Stdin of the unfinished commands started with `keepStdinOpen`. Kept
+	/// outside the session, which is locked while a command runs.
+	stdin:   Arc<OpenStdins>,
+	/// ID of the next `run()` call.
+	run_ids: AtomicU64,
 }
 
 #[napi]
@@ -127,35 +379,73 @@ impl Shell {
 			|| ShellConfig { session_env: None, snapshot_path: None },
 			|opt| ShellConfig { session_env: opt.session_env, snapshot_path: opt.snapshot_path },
 		);
-		Self { session: Arc::new(TokioMutex::new(None)), config }
+		Self {
+			session: Arc::new(TokioMutex::new(None)),
+			config,
+			stdin: Arc::default(),
+			run_ids: AtomicU64::new(0),
+		}
 	}
 
 	/// Run a shell command using the provided options.
 	///
-	/// The `on_chunk` callback receives streamed stdout/stderr output. Returns
-	/// the exit code when the command completes, or flags when cancelled or
-	/// timed out.
+	/// The `on_chunk` callback receives streamed stdout/stderr output, as
+	/// `ShellChunk`s with `separateStreams`. Returns the exit code when the
+	/// command completes, or flags when cancelled or timed out.
 	#[napi]
 	pub fn run<'e>(
 		&self,
 		env: &'e Env,
 		options: ShellRunOptions<'e>,
-		#[napi(ts_arg_type = "((chunk: string) => void) | undefined | null")] on_chunk: Option<
-			ThreadsafeFunction<String>,
-		>,
+		#[napi(ts_arg_type = "((chunk: string | ShellChunk) => void) | undefined | null")]
+		on_chunk: Option<ChunkCallback>,
 	) -> Result<PromiseRaw<'e, ShellRunResult>> {
 		let ct = task::CancelToken::new(options.timeout_ms, options.signal);
 		let session = self.session.clone();
 		let config = self.config.clone();
 
-		let run_config =
-			ShellRunConfig { command: options.command, cwd: options.cwd, env: options.env };
+		let run_config = ShellRunConfig {
+			command:          options.command,
+			cwd:              options.cwd,
+			env:              options.env,
+			separate_streams: options.separate_streams.unwrap_or(false),
+		};
+		let keep_stdin_open = options.keep_stdin_open.unwrap_or(false);
+		let (stdin_rx, stdin_tx) = stdin_channel(options.stdin, keep_stdin_open);
+		let open_stdin = stdin_tx.map(|tx| {
+			self
+				.stdin
+				.register(self.run_ids.fetch_add(1, Ordering::Relaxed), tx)
+		});
 
 		task::future(env, "shell.run", async move {
-			run_shell_session(session, config, run_config, on_chunk, ct).await
+			let output = OutputSink::new(on_chunk);
+			run_shell_session(session, config, run_config, stdin_rx, open_stdin, output, ct).await
 		})
 	}
 
+	/// Write data to the stdin of the running command started with
+	/// `keepStdinOpen`.
+	///
+	/// Before that command starts, writes go to the earliest such command still
+	/// queued and are buffered until it runs.
+	#[napi]
+	pub fn write_stdin(&self, data: Either<String, Uint8Array>) -> Result<()> {
+		if self.stdin.write(stdin_bytes(data)) {
+			Ok(())
+		} else {
+			Err(Error::from_reason("No command has open stdin"))
+		}
+	}
+
+	/// Close the stdin that `writeStdin` writes to, signalling end of input.
+	///
+	/// Does nothing when no command has open stdin.
+	#[napi]
+	pub fn close_stdin(&self) {
+		self.stdin.close();
+	}
+
 	/// Abort all running commands for this shell session.
 	///
 	/// Returns `Ok(())` even when no commands are running.
@@ -175,15 +465,19 @@ async fn run_shell_session(
 	session: Arc<TokioMutex<Option<ShellSessionCore>>>,
 	config: ShellConfig,
 	run_config: ShellRunConfig,
-	on_chunk: Option<ThreadsafeFunction<String>>,
+	stdin: Option<StdinReceiver>,
+	open_stdin: Option<OpenStdin>,
+	output: OutputSink,
 	mut ct: task::CancelToken,
 ) -> Result<ShellRunResult> {
 	let tokio_cancel = CancellationToken::new();
+	let separate = run_config.separate_streams;
 
 	let mut run_task = tokio::spawn({
 		let session = session.clone();
 		let tokio_cancel = tokio_cancel.clone();
 		let at = ct.emplace_abort_token();
+		let output = output.clone();
 		async move {
 			let mut session_guard = session.lock().await;
 
@@ -192,95 +486,218 @@ async fn run_shell_session(
 				None => session_guard.insert(create_session(&config).await?),
 			};
 			session.current_abort = Some(at);
-			run_shell_command(session, &run_config, on_chunk, tokio_cancel).await
+			if let Some(open_stdin) = &open_stdin {
+				open_stdin.start();
+			}
+			let exported = exported_vars(&session.shell);
+			// Each command leads its own process group, so cancelling it kills
+			// its processes without ending the session.
+			let result = run_shell_command(
+				session,
+				&run_config,
+				stdin,
+				output,
+				ProcessGroupPolicy::IsolatedProcessGroup,
+				tokio_cancel,
+			)
+			.await?;
+			let env_changes = diff_exported(exported, exported_vars(&session.shell));
+			Ok((result, env_changes))
 		}
 	});
 
 	let res = tokio::select! {
 		res = &mut run_task => res,
 		reason = ct.wait() => {
+			// Cancelling kills the foreground process group and the command's
+			// jobs; the interpreter then unwinds with an interrupted result.
 			tokio_cancel.cancel();
-			let graceful = time::timeout(Duration::from_secs(2), &mut run_task).await;
-			if graceful.is_err() {
-				run_task.abort();
-				let _ = run_task.await;
-			}
-			*session.lock().await = None;
+			let reusable = match time::timeout(Duration::from_secs(2), &mut run_task).await {
+				Ok(Ok(Ok((result, _)))) => session_keepalive(&result),
+				Ok(Ok(Err(_))) => true,
+				Ok(Err(_)) => false,
+				Err(_) => {
+					// Wedged: the interpreter is stuck mid-command, so its state
+					// can't be trusted.
+					run_task.abort();
+					let _ = run_task.await;
+					false
+				},
+			};
+			let cwd = settle_session(&session, reusable).await;
+			let (stdout_bytes, stderr_bytes) = output.byte_counts(separate);
 			return Ok(ShellRunResult {
 				exit_code: None,
 				cancelled: matches!(reason, task::AbortReason::Signal),
 				timed_out: matches!(reason, task::AbortReason::Timeout),
+				stdout_bytes,
+				stderr_bytes,
+				session_reset: cwd.is_none(),
+				cwd,
+				env_changes: None,
+				signal: None,
+				core_dumped: None,
 			});
 		}
 	};
 	let res =
 		res.unwrap_or_else(|e| Err(Error::from_reason(format!("Shell execution task failed: {e}"))));
 
-	let keepalive = res.as_ref().is_ok_and(session_keepalive);
-	if keepalive {
-		// Clear abort token when command completes
-		if let Some(session_core) = session.lock().await.as_mut() {
-			session_core.current_abort = None;
-		}
+	let reusable = res
+		.as_ref()
+		.is_ok_and(|(result, _)| session_keepalive(result));
+	let cwd = settle_session(&session, reusable).await;
+	let (result, env_changes) = res?;
+	let (stdout_bytes, stderr_bytes) = output.byte_counts(separate);
+	let (signal, core_dumped) = exit_signal(&result);
+	Ok(ShellRunResult {
+		exit_code: Some(exit_code(&result)),
+		cancelled: false,
+		timed_out: false,
+		stdout_bytes,
+		stderr_bytes,
+		session_reset: cwd.is_none(),
+		cwd,
+		env_changes: Some(env_changes),
+		signal,
+		core_dumped,
+	})
+}
+
+/// Clears the finished command's abort token, or discards the session when it
+/// can't be reused. Returns the working directory of the kept session.
+async fn settle_session(
+	session: &TokioMutex<Option<ShellSessionCore>>,
+	reusable: bool,
+) -> Option<String> {
+	let mut guard = session.lock().await;
+	let core = guard.as_mut()?;
+	// An unbalanced call stack means the command unwound only partially.
+	if reusable && core.shell.function_call_stack().is_empty() {
+		core.current_abort = None;
+		Some(core.shell.working_dir().to_string_lossy().into_owned())
 	} else {
-		*session.lock().await = None;
+		*guard = None;
+		None
 	}
-	Ok(ShellRunResult { exit_code: Some(exit_code(&res?)), cancelled: false, timed_out: false })
+}
+
+/// Exported variables and their values, without the shell-managed ones.
+fn exported_vars(shell: &BrushShell) -> HashMap<String, String> {
+	shell
+		.env
+		.iter_exported()
+		.filter(|(name, _)| !should_skip_env_var(name))
+		.map(|(name, var)| (name.clone(), var.value().to_cow_str(shell).into_owned()))
+		.collect()
+}
+
+fn diff_exported(
+	mut before: HashMap<String, String>,
+	after: HashMap<String, String>,
+) -> ShellEnvChanges {
+	let mut changes =
+		ShellEnvChanges { added: HashMap::new(), changed: HashMap::new(), removed: Vec::new() };
+	for (name, value) in after {
+		match before.remove(&name) {
+			None => {
+				changes.added.insert(name, value);
+			},
+			Some(old) if old != value => {
+				changes.changed.insert(name, value);
+			},
+			Some(_) => {},
+		}
+	}
+	changes.removed = before.into_keys().collect();
+	changes.removed.sort_unstable();
+	changes
+}
+
+/// Name of the signal that terminated the command and whether it dumped core.
+fn exit_signal(result: &ExecutionResult) -> (Option<String>, Option<bool>) {
+	result.signal.map_or((None, None), |signal| {
+		let name = traps::TrapSignal::try_from(signal.number)
+			.map_or_else(|_| signal.number.to_string(), |name| name.as_str().to_string());
+		(Some(name), Some(signal.core_dumped))
+	})
 }
 
 /// Options for executing a shell command via brush-core.
 #[napi(object)]
 pub struct ShellExecuteOptions<'env> {
 	/// Command string to execute in the shell.
-	pub command:       String,
+	pub command:          String,
 	/// Working directory for the command.
-	pub cwd:           Option<String>,
+	pub cwd:              Option<String>,
 	/// Environment variables to apply for this command only.
-	pub env:           Option<HashMap<String, String>>,
+	pub env:              Option<HashMap<String, String>>,
 	/// Environment variables to apply once per session.
-	pub session_env:   Option<HashMap<String, String>>,
+	pub session_env:      Option<HashMap<String, String>>,
 	/// Timeout in milliseconds before cancelling the command.
 	#[napi(js_name = "timeoutMs")]
-	pub timeout_ms:    Option<u32>,
+	pub timeout_ms:       Option<u32>,
 	/// Optional snapshot file to source on session creation.
 	#[napi(js_name = "snapshotPath")]
-	pub snapshot_path: Option<String>,
+	pub snapshot_path:    Option<String>,
 	/// Abort signal for cancelling the operation.
-	pub signal:        Option<Unknown<'env>>,
+	pub signal:           Option<Unknown<'env>>,
+	/// Read stdout and stderr from separate pipes and pass `ShellChunk`s
+	/// tagged with their stream to the callback.
+	#[napi(js_name = "separateStreams")]
+	pub separate_streams: Option<bool>,
+	/// Data to feed to the command's stdin.
+	pub stdin:            Option<Either<String, Uint8Array>>,
 }
 
 /// Result of executing a shell command via brush-core.
 #[napi(object)]
 pub struct ShellExecuteResult {
 	/// Exit code when the command completes normally.
-	pub exit_code: Option<i32>,
+	pub exit_code:    Option<i32>,
 	/// Whether the command was cancelled via abort.
-	pub cancelled: bool,
+	pub cancelled:    bool,
 	/// Whether the command timed out before completion.
-	pub timed_out: bool,
+	pub timed_out:    bool,
+	/// Bytes written to stdout (with `separateStreams`).
+	#[napi(js_name = "stdoutBytes")]
+	pub stdout_bytes: Option<f64>,
+	/// Bytes written to stderr (with `separateStreams`).
+	#[napi(js_name = "stderrBytes")]
+	pub stderr_bytes: Option<f64>,
+	/// Name of the signal that terminated the command (e.g. "SIGKILL").
+	pub signal:       Option<String>,
+	/// Whether the command dumped core when terminated by `signal`.
+	#[napi(js_name = "coreDumped")]
+	pub core_dumped:  Option<bool>,
 }
 
 /// Execute a brush shell command.
 ///
 /// Creates a fresh session for each call. The `on_chunk` callback receives
-/// streamed stdout/stderr output. Returns the exit code when the command
-/// completes, or flags when cancelled or timed out.
+/// streamed stdout/stderr output, as `ShellChunk`s with `separateStreams`.
+/// Returns the exit code when the command completes, or flags when cancelled
+/// or timed out.
 #[napi(js_name = "executeShell")]
 pub fn execute_shell<'env>(
 	env: &'env Env,
 	options: ShellExecuteOptions<'env>,
-	#[napi(ts_arg_type = "((chunk: string) => void) | undefined | null")] on_chunk: Option<
-		ThreadsafeFunction<String>,
-	>,
+	#[napi(ts_arg_type = "((chunk: string | ShellChunk) => void) | undefined | null")]
+	on_chunk: Option<ChunkCallback>,
 ) -> Result<PromiseRaw<'env, ShellExecuteResult>> {
 	let config =
 		ShellConfig { session_env: options.session_env, snapshot_path: options.snapshot_path };
-	let run_config =
-		ShellRunConfig { command: options.command, cwd: options.cwd, env: options.env };
+	let run_config = ShellRunConfig {
+		command:          options.command,
+		cwd:              options.cwd,
+		env:              options.env,
+		separate_streams: options.separate_streams.unwrap_or(false),
+	};
+	let (stdin, _) = stdin_channel(options.stdin, false);
 
 	let ct = task::CancelToken::new(options.timeout_ms, options.signal);
 	task::future(env, "shell.execute", async move {
-		run_shell_oneshot(config, run_config, on_chunk, ct).await
+		run_shell_oneshot(config, run_config, stdin, on_chunk, ct).await
 	})
 }
 
@@ -288,16 +705,28 @@ pub fn execute_shell<'env>(
 async fn run_shell_oneshot(
 	config: ShellConfig,
 	run_config: ShellRunConfig,
-	on_chunk: Option<ThreadsafeFunction<String>>,
+	stdin: Option<StdinReceiver>,
+	on_chunk: Option<ChunkCallback>,
 	ct: task::CancelToken,
 ) -> Result<ShellExecuteResult> {
 	let tokio_cancel = CancellationToken::new();
+	let output = OutputSink::new(on_chunk);
+	let separate = run_config.separate_streams;
 
 	let mut task = tokio::spawn({
 		let tokio_cancel = tokio_cancel.clone();
+		let output = output.clone();
 		async move {
 			let mut session = create_session(&config).await?;
-			run_shell_command(&mut session, &run_config, on_chunk, tokio_cancel).await
+			run_shell_command(
+				&mut session,
+				&run_config,
+				stdin,
+				output,
+				ProcessGroupPolicy::NewProcessGroup,
+				tokio_cancel,
+			)
+			.await
 		}
 	});
 
@@ -310,10 +739,15 @@ async fn run_shell_oneshot(
 				task.abort();
 				let _ = task.await;
 			}
+			let (stdout_bytes, stderr_bytes) = output.byte_counts(separate);
 			return Ok(ShellExecuteResult {
 				exit_code: None,
 				cancelled: matches!(reason, task::AbortReason::Signal),
 				timed_out: matches!(reason, task::AbortReason::Timeout),
+				stdout_bytes,
+				stderr_bytes,
+				signal: None,
+				core_dumped: None,
 			})
 		},
 	};
@@ -321,7 +755,80 @@ async fn run_shell_oneshot(
 	let res = run_result
 		.unwrap_or_else(|e| Err(Error::from_reason(format!("Shell execution task failed: {e}"))));
 
-	Ok(ShellExecuteResult { exit_code: Some(exit_code(&res?)), cancelled: false, timed_out: false })
+	let result = res?;
+	let (stdout_bytes, stderr_bytes) = output.byte_counts(separate);
+	let (signal, core_dumped) = exit_signal(&result);
+	Ok(ShellExecuteResult {
+		exit_code: Some(exit_code(&result)),
+		cancelled: false,
+		timed_out: false,
+		stdout_bytes,
+		stderr_bytes,
+		signal,
+		core_dumped,
+	})
+}
+
+/// File redirection found by `analyzeCommand`.
+#[napi(object)]
+pub struct CommandRedirect {
+	/// Target path, as written if it contains expansions.
+	pub target: String,
+	/// "read", "write", "append" or "readWrite".
+	pub kind:   String,
+	/// Redirected file descriptor, if given explicitly.
+	pub fd:     Option<u32>,
+}
+
+/// Result of `analyzeCommand`.
+#[napi(object)]
+pub struct CommandAnalysis {
+	/// Programs the command invokes, in order of first use, as written.
+	pub programs:             Vec<String>,
+	/// File redirections, in order.
+	pub redirects:            Vec<CommandRedirect>,
+	/// Parameter expansions and substitutions whose value is only known at
+	/// run time, as written.
+	pub unresolved:           Vec<String>,
+	/// Every program is known to only read, with no writing redirects.
+	#[napi(js_name = "readOnly")]
+	pub read_only:            bool,
+	/// Writes files, through redirects or programs such as `cp` or `tee`.
+	#[napi(js_name = "writesFiles")]
+	pub writes_files:         bool,
+	/// Deletes or overwrites data (`rm`, `dd`, `find -delete`, `git clean`).
+	pub destructive:          bool,
+	/// Uses the network (`curl`, `ssh`, `git push`, package installs).
+	pub network:              bool,
+	/// Runs something as another user (`sudo`, `doas`, `su`).
+	#[napi(js_name = "privilegeEscalation")]
+	pub privilege_escalation: bool,
+	/// Executes downloaded code (`curl … | sh`, `eval "$(wget …)"`).
+	#[napi(js_name = "pipesToShell")]
+	pub pipes_to_shell:       bool,
+}
+
+/// Statically analyze a shell command without running it.
+///
+/// Parses `command` with brush and walks the syntax tree, following
+/// wrappers, `sh -c`, `eval` and command substitutions. Fails if the command
+/// does not parse.
+#[napi(js_name = "analyzeCommand")]
+pub fn analyze_command(env: &Env, command: String) -> Result<PromiseRaw<'_, CommandAnalysis>> {
+	task::future(env, "shell.analyze", async move {
+		let shell = BrushShell::new(CreateOptions {
+			no_profile: true,
+			no_rc: true,
+			do_not_inherit_env: true,
+			..Default::default()
+		})
+		.await
+		.map_err(|err| Error::from_reason(format!("Failed to initialize shell: {err}")))?;
+		let program = shell
+			.parse_string(command)
+			.map_err(|err| Error::from_reason(format!("Failed to parse command: {err}")))?;
+		Ok(analyze::Analyzer::new(&shell).analyze(&program))
+	})
 }
 
 fn null_file() -> Result<OpenFile> {
@@ -505,7 +1012,9 @@ async fn source_snapshot(shell: &mut BrushShell, snapshot_path: &str) -> Result<
 async fn run_shell_command(
 	session: &mut ShellSessionCore,
 	options: &ShellRunConfig,
-	on_chunk: Option<ThreadsafeFunction<String>>,
+	stdin: Option<StdinReceiver>,
+	output: OutputSink,
+	process_group_policy: ProcessGroupPolicy,
 	cancel_token: CancellationToken,
 ) -> Result<ExecutionResult> {
 	if let Some(cwd) = options.cwd.as_deref() {
@@ -515,20 +1024,50 @@ async fn run_shell_command(
 			.map_err(|err| Error::from_reason(format!("Failed to set cwd: {err}")))?;
 	}
 
-	let (reader_file, writer_file) = pipe_to_files("output")?;
+	// Readers paired with the stream they are tagged as (`None` when merged).
+	let mut readers = Vec::with_capacity(2);
+	let (stdout_file, stderr_file) = if options.separate_streams {
+		let (stdout_reader, stdout_writer) = pipe_to_files("stdout")?;
+		let (stderr_reader, stderr_writer) = pipe_to_files("stderr")?;
+		readers.push((stdout_reader, Some(OutputStream::Stdout)));
+		readers.push((stderr_reader, Some(OutputStream::Stderr)));
+		(OpenFile::from(stdout_writer), OpenFile::from(stderr_writer))
+	} else {
+		let (reader_file, writer_file) = pipe_to_files("output")?;
+		readers.push((reader_file, None));
+		let stdout_file = OpenFile::from(
+			writer_file
+				.try_clone()
+				.map_err(|err| Error::from_reason(format!("Failed to clone pipe: {err}")))?,
+		);
+		(stdout_file, OpenFile::from(writer_file))
+	};
 
-	let stdout_file = OpenFile::from(
-		writer_file
-			.try_clone()
-			.map_err(|err| Error::from_reason(format!("Failed to clone pipe: {err}")))?,
-	);
-	let stderr_file = OpenFile::from(writer_file);
+	// Jobs left from earlier commands survive cancelling this one.
+	let prior_jobs: HashSet<usize> = session.shell.jobs.jobs.iter().map(|job| job.id).collect();
+
+	let stdin_file = match stdin {
+		Some(chunks) => {
+			let (reader, mut writer) = pipe_to_files("stdin")?;
+			// Writes block while the pipe is full; they fail once the command
+			// (and anything it passed stdin to) exits.
+			tokio::task::spawn_blocking(move || {
+				for chunk in chunks {
+					if writer.write_all(&chunk).is_err() {
+						break;
+					}
+				}
+			});
+			OpenFile::from(reader)
+		},
+		None => null_file()?,
+	};
 
 	let mut params = session.shell.default_exec_params();
-	params.set_fd(OpenFiles::STDIN_FD, null_file()?);
+	params.set_fd(OpenFiles::STDIN_FD, stdin_file);
 	params.set_fd(OpenFiles::STDOUT_FD, stdout_file);
 	params.set_fd(OpenFiles::STDERR_FD, stderr_file);
-	params.process_group_policy = ProcessGroupPolicy::NewProcessGroup;
+	params.process_group_policy = process_group_policy;
 	params.set_cancel_token(cancel_token.clone());
 
 	let mut env_scope_pushed = false;
@@ -558,7 +1097,21 @@ async fn run_shell_command(
 	let mut reader_handle = tokio::spawn({
 		let reader_cancel = reader_cancel.clone();
 		async move {
-			read_output(reader_file, on_chunk, reader_cancel, activity_tx).await;
+			let reads = readers
+				.into_iter()
+				.map(|(reader, stream)| {
+					tokio::spawn(read_output(
+						reader,
+						stream,
+						output.clone(),
+						reader_cancel.clone(),
+						activity_tx.clone(),
+					))
+				})
+				.collect::<Vec<_>>();
+			for read in reads {
+				let _ = read.await;
+			}
 			Result::<()>::Ok(())
 		}
 	});
@@ -576,7 +1129,7 @@ async fn run_shell_command(
 		.await;
 
 	if cancel_token.is_cancelled() {
-		terminate_background_jobs(&session.shell);
+		terminate_background_jobs(&session.shell, &prior_jobs);
 	}
 
 	if env_scope_pushed {
@@ -590,7 +1143,7 @@ async fn run_shell_command(
 	drop(params);
 
 	// The foreground command can complete while background jobs keep the
-	// stdout/stderr pipe open. Don't hang forever waiting for EOF; drain output
+	// stdout/stderr pipes open. Don't hang forever waiting for EOF; drain output
 	// for a short period, then cancel.
 	const POST_EXIT_IDLE: Duration = Duration::from_millis(250);
 	const POST_EXIT_MAX: Duration = Duration::from_secs(2);
@@ -627,45 +1180,63 @@ async fn run_shell_command(
 	result.map_err(|err| Error::from_reason(format!("Shell execution failed: {err}")))
 }
 
+/// Sends TERM, then KILL after a grace period, to the process groups of jobs
+/// not in `prior_jobs`.
 #[cfg(unix)]
-fn terminate_background_jobs(shell: &BrushShell) {
-	if shell.jobs.jobs.is_empty() {
-		return;
-	}
+fn terminate_background_jobs(shell: &BrushShell, prior_jobs: &HashSet<usize>) {
 	let Ok(signal) = "TERM".parse::<traps::TrapSignal>() else {
 		return;
 	};
-	let mut pgids = Vec::new();
-	for job in &shell.jobs.jobs {
-		if let Some(pid) = job.process_group_id().or_else(|| job.representative_pid()) {
-			let _ = sys::signal::kill_process(pid, signal);
-			pgids.push(pid);
+	// Job processes paired with whether they are process group IDs.
+	let mut targets = Vec::new();
+	for job in shell
+		.jobs
+		.jobs
+		.iter()
+		.filter(|job| !prior_jobs.contains(&job.id))
+	{
+		if let Some(pgid) = job.process_group_id() {
+			targets.push((pgid, true));
+		} else if let Some(pid) = job.representative_pid() {
+			targets.push((pid, false));
 		}
 	}
-	if pgids.is_empty() {
+	if targets.is_empty() {
 		return;
 	}
+	signal_jobs(&targets, signal);
 	tokio::spawn(async move {
 		time::sleep(Duration::from_millis(500)).await;
 		let Ok(signal) = "KILL".parse::<traps::TrapSignal>() else {
 			return;
 		};
-		for pid in pgids {
-			let _ = sys::signal::kill_process(pid, signal);
-		}
+		signal_jobs(&targets, signal);
 	});
 }
 
-#[cfg(windows)]
-fn terminate_background_jobs(shell: &BrushShell) {
-	if shell.jobs.jobs.is_empty() {
-		return;
+#[cfg(unix)]
+fn signal_jobs(targets: &[(i32, bool)], signal: traps::TrapSignal) {
+	for &(id, group) in targets {
+		let _ = if group {
+			sys::signal::kill_process_group(id, signal)
+		} else {
+			sys::signal::kill_process(id, signal)
+		};
 	}
+}
+
+#[cfg(windows)]
+fn terminate_background_jobs(shell: &BrushShell, prior_jobs: &HashSet<usize>) {
 	let Ok(signal) = "TERM".parse::<traps::TrapSignal>() else {
 		return;
 	};
 	let mut pids = Vec::new();
-	for job in &shell.jobs.jobs {
+	for job in shell
+		.jobs
+		.jobs
+		.iter()
+		.filter(|job| !prior_jobs.contains(&job.id))
+	{
 		if let Some(pid) = job.process_group_id().or_else(|| job.representative_pid()) {
 			let _ = sys::signal::kill_process(pid, signal);
 			pids.push(pid);
@@ -755,7 +1326,8 @@ const fn session_keepalive(result: &ExecutionResult) -> bool {
 
 async fn read_output(
 	reader: fs::File,
-	on_chunk: Option<ThreadsafeFunction<String>>,
+	stream: Option<OutputStream>,
+	output: OutputSink,
 	cancel_token: CancellationToken,
 	activity: mpsc::Sender<()>,
 ) {
@@ -781,6 +1353,9 @@ async fn read_output(
 		};
 		if n > 0 {
 			let _ = activity.try_send(());
+			if let Some(stream) = stream {
+				output.count(stream, n);
+			}
 		}
 		it += n;
 
@@ -789,7 +1364,7 @@ async fn read_output(
 			let pending = &buf[..it];
 			match str::from_utf8(pending) {
 				Ok(text) => {
-					emit_chunk(text, on_chunk.as_ref());
+					output.emit(stream, text);
 					it = 0;
 					break;
 				},
@@ -798,7 +1373,7 @@ async fn read_output(
 					if p > 0 {
 						// SAFETY: [..p] is guaranteed valid UTF-8 by valid_up_to().
 						let text = unsafe { str::from_utf8_unchecked(&pending[..p]) };
-						emit_chunk(text, on_chunk.as_ref());
+						output.emit(stream, text);
 						// copy p..it to the beginning of the buffer
 						buf.copy_within(p..it, 0);
 						it -= p;
@@ -807,7 +1382,7 @@ async fn read_output(
 					match err.error_len() {
 						Some(p) => {
 							// Invalid byte sequence: emit replacement and drop those bytes.
-							emit_chunk(REPLACEMENT, on_chunk.as_ref());
+							output.emit(stream, REPLACEMENT);
 							// copy p..it to the beginning of the buffer
 							buf.copy_within(p..it, 0);
 							it -= p;
@@ -828,20 +1403,14 @@ async fn read_output(
 	for chunk in buf[..it].utf8_chunks() {
 		let valid = chunk.valid();
 		if !valid.is_empty() {
-			emit_chunk(valid, on_chunk.as_ref());
+			output.emit(stream, valid);
 		}
 		if !chunk.invalid().is_empty() {
-			emit_chunk(REPLACEMENT, on_chunk.as_ref());
+			output.emit(stream, REPLACEMENT);
 		}
 	}
 }
 
-fn emit_chunk(text: &str, callback: Option<&ThreadsafeFunction<String>>) {
-	if let Some(callback) = callback {
-		callback.call(Ok(text.to_string()), ThreadsafeFunctionCallMode::NonBlocking);
-	}
-}
-
 fn pipe_to_files(label: &str) -> Result<(fs::File, fs::File)> {
 	let (r, w) = os_pipe::pipe()
 		.map_err(|err| Error::from_reason(format!("Failed to create {label} pipe: {err}")))?;
@@ -1022,3 +1591,246 @@ fn quote_arg(arg: &str) -> String {
 	let escaped = arg.replace('\'', "'\"'\"'");
 	format!("'{escaped}'")
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+	use std::{
+		path::Path,
+		process::{Command, Stdio},
+	};
+
+	use super::*;
+
+	async fn run(
+		session: &Arc<TokioMutex<Option<ShellSessionCore>>>,
+		command: &str,
+		timeout_ms: Option<u32>,
+		stdin: Option<StdinReceiver>,
+		open_stdin: Option<OpenStdin>,
+	) -> ShellRunResult {
+		let config = ShellConfig { session_env: None, snapshot_path: None };
+		let run_config = ShellRunConfig {
+			command:          command.to_string(),
+			cwd:              None,
+			env:              None,
+			separate_streams: false,
+		};
+		let ct = task::CancelToken::new(timeout_ms, None);
+		let output = OutputSink::new(None);
+		run_shell_session(session.clone(), config, run_config, stdin, open_stdin, output, ct)
+			.await
+			.unwrap()
+	}
+
+	/// Sends `signal` to the process whose ID is stored in `pid_file`, returning
+	/// whether it was delivered.
+	fn signal_pid_file(pid_file: &Path, signal: &str) -> bool {
+		let pid = fs::read_to_string(pid_file).unwrap();
+		Command::new("kill")
+			.args([signal, pid.trim()])
+			.stderr(Stdio::null())
+			.status()
+			.unwrap()
+			.success()
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_separate_streams() {
+		let chunks = Arc::new(Mutex::new(Vec::new()));
+		let output = OutputSink::with_fn(Some(Arc::new({
+			let chunks = chunks.clone();
+			move |payload| {
+				if let Either::B(ShellChunk { stream, seq, text }) = payload {
+					chunks.lock().push((seq, stream, text));
+				}
+			}
+		})));
+		let config = ShellConfig { session_env: None, snapshot_path: None };
+		let command = "printf 'out\\n'; sleep 0.2; printf 'err\\n' >&2; sleep 0.2; printf 'more\\n'";
+		let run_config = ShellRunConfig {
+			command:          command.to_string(),
+			cwd:              None,
+			env:              None,
+			separate_streams: true,
+		};
+		let ct = task::CancelToken::default();
+		let result = run_shell_session(Arc::default(), config, run_config, None, None, output, ct)
+			.await
+			.unwrap();
+		assert_eq!(result.exit_code, Some(0));
+		assert_eq!(result.stdout_bytes, Some(9.0));
+		assert_eq!(result.stderr_bytes, Some(4.0));
+
+		// Chunk boundaries vary, so check the order of runs of one stream.
+		let chunks = chunks.lock();
+		let seqs: Vec<u32> = chunks.iter().map(|(seq, ..)| *seq).collect();
+		assert_eq!(seqs, (0..chunks.len() as u32).collect::<Vec<_>>());
+		let mut runs: Vec<(&str, String)> = Vec::new();
+		for (_, stream, text) in chunks.iter() {
+			match runs.last_mut() {
+				Some((last, run)) if last == stream => run.push_str(text),
+				_ => runs.push((stream, text.clone())),
+			}
+		}
+		assert_eq!(runs, [
+			("stdout", "out\n".to_string()),
+			("stderr", "err\n".to_string()),
+			("stdout", "more\n".to_string())
+		]);
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_only_session_commands_lead_process_groups() {
+		let leads_group = "sh -c 'test \"$(ps -o pgid= -p $$ | tr -d \" \")\" = $$'";
+		let session = Arc::default();
+		assert_eq!(run(&session, leads_group, None, None, None).await.exit_code, Some(0));
+
+		let config = ShellConfig { session_env: None, snapshot_path: None };
+		let run_config = ShellRunConfig {
+			command:          leads_group.to_string(),
+			cwd:              None,
+			env:              None,
+			separate_streams: false,
+		};
+		let ct = task::CancelToken::default();
+		let oneshot = run_shell_oneshot(config, run_config, None, None, ct)
+			.await
+			.unwrap();
+		assert_eq!(oneshot.exit_code, Some(1));
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_env_changes_and_signal() {
+		let session = Arc::default();
+		let setup = run(&session, "export KEEP=1 CHANGE=old DROP=1", None, None, None).await;
+		let setup = setup.env_changes.unwrap();
+		assert_eq!(setup.added.get("CHANGE").map(String::as_str), Some("old"));
+
+		let result = run(&session, "export ADD=new CHANGE=new; unset DROP", None, None, None).await;
+		let changes = result.env_changes.unwrap();
+		assert_eq!(changes.added, HashMap::from([("ADD".to_string(), "new".to_string())]));
+		assert_eq!(changes.changed, HashMap::from([("CHANGE".to_string(), "new".to_string())]));
+		assert_eq!(changes.removed, ["DROP"]);
+		assert_eq!(result.signal, None);
+
+		let killed = run(&session, "sh -c 'kill -KILL $$'", None, None, None).await;
+		assert_eq!(killed.exit_code, Some(137));
+		assert_eq!(killed.signal.as_deref(), Some("SIGKILL"));
+		assert_eq!(killed.core_dumped, Some(false));
+
+		let inverted = run(&session, "! sh -c 'kill -KILL $$'", None, None, None).await;
+		assert_eq!(inverted.exit_code, Some(0));
+		assert_eq!(inverted.signal, None);
+		assert_eq!(inverted.core_dumped, None);
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_timeout_keeps_session() {
+		let dir =
+			std::env::temp_dir().join(format!("pi-natives-shell-timeout-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		let dir = dir.canonicalize().unwrap();
+		let cwd = dir.to_str().unwrap();
+		let session = Arc::default();
+
+		let prior_pid = dir.join("prior.pid");
+		let job_pid = dir.join("job.pid");
+		let setup = format!(
+			"cd {}; export MARKER=kept; sh -c 'echo $$ > \"$0\"; exec sleep 30' {} &",
+			quote_arg(cwd),
+			quote_arg(prior_pid.to_str().unwrap())
+		);
+		assert_eq!(run(&session, &setup, None, None, None).await.exit_code, Some(0));
+
+		let job = format!(
+			"sh -c 'echo $$ > \"$0\"; exec sleep 30' {} & sleep 30",
+			quote_arg(job_pid.to_str().unwrap())
+		);
+		let timed_out = run(&session, &job, Some(500), None, None).await;
+		assert!(timed_out.timed_out);
+		assert!(!timed_out.session_reset);
+		assert_eq!(timed_out.cwd.as_deref(), Some(cwd));
+
+		let check = run(&session, "test \"$MARKER\" = kept", None, None, None).await;
+		assert_eq!(check.exit_code, Some(0));
+		assert_eq!(check.cwd.as_deref(), Some(cwd));
+
+		// The timed-out command's job gets TERM, then KILL after a grace
+		// period; the job started by the earlier command keeps running.
+		time::sleep(Duration::from_secs(1)).await;
+		assert!(!signal_pid_file(&job_pid, "-0"));
+		assert!(signal_pid_file(&prior_pid, "-0"));
+
+		signal_pid_file(&prior_pid, "-KILL");
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	/// Registers a `keepStdinOpen` run of `stdins` fed with `data`.
+	fn keep_open(
+		stdins: &Arc<OpenStdins>,
+		run_id: u64,
+		data: Option<&str>,
+	) -> (Option<StdinReceiver>, Option<OpenStdin>) {
+		let (rx, tx) = stdin_channel(data.map(|data| Either::A(data.to_string())), true);
+		(rx, tx.map(|tx| stdins.register(run_id, tx)))
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_stdin_reaches_command() {
+		let dir = std::env::temp_dir().join(format!("pi-natives-shell-stdin-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		let out = dir.join("stdin.txt");
+		let cat = format!("cat > {}", quote_arg(out.to_str().unwrap()));
+		let session = Arc::default();
+
+		let (stdin, _) = stdin_channel(Some(Either::A("data\n".to_string())), false);
+		assert_eq!(run(&session, &cat, None, stdin, None).await.exit_code, Some(0));
+		assert_eq!(fs::read_to_string(&out).unwrap(), "data\n");
+
+		// Writes right after the run is queued are buffered until it starts.
+		let stdins = Arc::<OpenStdins>::default();
+		let (stdin, open_stdin) = keep_open(&stdins, 1, Some("first\n"));
+		let running = run(&session, &cat, None, stdin, open_stdin);
+		assert!(stdins.write(b"second\n".to_vec()));
+		let feed = async {
+			time::sleep(Duration::from_millis(200)).await;
+			assert!(stdins.write(b"third\n".to_vec()));
+			stdins.close();
+		};
+		let (result, ()) =
+			time::timeout(Duration::from_secs(10), async { tokio::join!(running, feed) })
+				.await
+				.unwrap();
+		assert_eq!(result.exit_code, Some(0));
+		assert_eq!(fs::read_to_string(&out).unwrap(), "first\nsecond\nthird\n");
+		assert!(!stdins.write(b"late\n".to_vec()));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_overlapping_runs_keep_their_stdin() {
+		let session = Arc::default();
+		let stdins = Arc::<OpenStdins>::default();
+		let (stdin, open_stdin) = keep_open(&stdins, 1, None);
+		let first = run(&session, "grep -qx first", None, stdin, open_stdin);
+		let (stdin, open_stdin) = keep_open(&stdins, 2, None);
+		let second = run(&session, "grep -qx second", None, stdin, open_stdin);
+		// Neither run has started, so input goes to the earlier one until its
+		// stdin is closed.
+		assert!(stdins.write(b"first\n".to_vec()));
+		stdins.close();
+		assert!(stdins.write(b"second\n".to_vec()));
+		stdins.close();
+
+		let (first, second) =
+			time::timeout(Duration::from_secs(10), async { tokio::join!(first, second) })
+				.await
+				.unwrap();
+		assert_eq!(first.exit_code, Some(0));
+		assert_eq!(second.exit_code, Some(0));
+		assert!(stdins.0.lock().is_empty());
+	}
+}
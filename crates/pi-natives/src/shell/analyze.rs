@@ -0,0 +1,842 @@
+//! Static risk analysis of shell commands.
+//!
+//! Walks the brush AST of a command without running it, collecting the
+//! programs it would invoke, its file redirections and the expansions whose
+//! value is only known at run time. Programs are classified from a fixed
+//! table; wrappers (`sudo`, `env`, `xargs`, ...), `sh -c`, `eval`,
+//! `find -exec` and command substitutions are followed into the commands they
+//! run.
+
+use std::mem;
+
+use brush_core::Shell as BrushShell;
+use brush_parser::{
+	ast,
+	word::{self, WordPiece, WordPieceWithSource},
+};
+use phf::phf_map;
+
+use super::{CommandAnalysis, CommandRedirect};
+
+#[derive(Clone, Copy)]
+enum Kind {
+	/// Only reads files or prints.
+	ReadOnly,
+	Writes,
+	Destructive,
+	Network,
+	/// Runs the command given after its options (and `positionals` fixed
+	/// arguments).
+	Wrapper {
+		value_opts:  &'static [&'static str],
+		positionals: usize,
+		privileged:  bool,
+	},
+	/// Shell interpreter; `-c` scripts are analyzed.
+	Shell,
+	/// Other interpreter; code is opaque.
+	Interpreter,
+	/// `su`: privileged, `-c` scripts are analyzed.
+	Su,
+	Eval,
+	Source,
+	Find,
+	Sed,
+	/// `sort`; `-o` writes the output to a file.
+	Sort,
+	/// `uniq`; a second operand is the output file.
+	Uniq,
+	Curl,
+	Wget,
+	Git,
+	/// Package manager; installing, fetching and publishing use the network.
+	Package,
+}
+
+static PROGRAMS: phf::Map<&'static str, Kind> = phf_map! {
+	"cat" => Kind::ReadOnly, "ls" => Kind::ReadOnly, "pwd" => Kind::ReadOnly,
+	"echo" => Kind::ReadOnly, "printf" => Kind::ReadOnly, "grep" => Kind::ReadOnly,
+	"egrep" => Kind::ReadOnly, "fgrep" => Kind::ReadOnly, "rg" => Kind::ReadOnly,
+	"head" => Kind::ReadOnly, "tail" => Kind::ReadOnly, "less" => Kind::ReadOnly,
+	"more" => Kind::ReadOnly, "wc" => Kind::ReadOnly,
+	"cut" => Kind::ReadOnly, "tr" => Kind::ReadOnly,
+	"diff" => Kind::ReadOnly, "cmp" => Kind::ReadOnly, "comm" => Kind::ReadOnly,
+	"file" => Kind::ReadOnly, "stat" => Kind::ReadOnly, "fd" => Kind::ReadOnly,
+	"tree" => Kind::ReadOnly, "which" => Kind::ReadOnly, "type" => Kind::ReadOnly,
+	"whoami" => Kind::ReadOnly, "id" => Kind::ReadOnly, "date" => Kind::ReadOnly,
+	"printenv" => Kind::ReadOnly, "uname" => Kind::ReadOnly, "hostname" => Kind::ReadOnly,
+	"du" => Kind::ReadOnly, "df" => Kind::ReadOnly, "ps" => Kind::ReadOnly,
+	"pgrep" => Kind::ReadOnly, "basename" => Kind::ReadOnly, "dirname" => Kind::ReadOnly,
+	"realpath" => Kind::ReadOnly, "readlink" => Kind::ReadOnly, "test" => Kind::ReadOnly,
+	"[" => Kind::ReadOnly, "true" => Kind::ReadOnly, "false" => Kind::ReadOnly,
+	":" => Kind::ReadOnly, "cd" => Kind::ReadOnly, "pushd" => Kind::ReadOnly,
+	"popd" => Kind::ReadOnly, "export" => Kind::ReadOnly, "unset" => Kind::ReadOnly,
+	"set" => Kind::ReadOnly, "shift" => Kind::ReadOnly, "read" => Kind::ReadOnly,
+	"local" => Kind::ReadOnly, "declare" => Kind::ReadOnly, "exit" => Kind::ReadOnly,
+	"return" => Kind::ReadOnly, "jq" => Kind::ReadOnly, "sleep" => Kind::ReadOnly,
+	"column" => Kind::ReadOnly, "nl" => Kind::ReadOnly, "od" => Kind::ReadOnly,
+	"xxd" => Kind::ReadOnly, "hexdump" => Kind::ReadOnly, "strings" => Kind::ReadOnly,
+	"md5sum" => Kind::ReadOnly, "sha1sum" => Kind::ReadOnly, "sha256sum" => Kind::ReadOnly,
+	"seq" => Kind::ReadOnly, "nproc" => Kind::ReadOnly, "uptime" => Kind::ReadOnly,
+
+	"cp" => Kind::Writes, "mv" => Kind::Writes, "install" => Kind::Writes,
+	"tee" => Kind::Writes, "touch" => Kind::Writes, "mkdir" => Kind::Writes,
+	"ln" => Kind::Writes, "chmod" => Kind::Writes, "chown" => Kind::Writes,
+	"chgrp" => Kind::Writes, "patch" => Kind::Writes, "tar" => Kind::Writes,
+	"unzip" => Kind::Writes, "gzip" => Kind::Writes, "gunzip" => Kind::Writes,
+	"zip" => Kind::Writes, "make" => Kind::Writes,
+
+	"rm" => Kind::Destructive, "rmdir" => Kind::Destructive, "unlink" => Kind::Destructive,
+	"shred" => Kind::Destructive, "dd" => Kind::Destructive, "truncate" => Kind::Destructive,
+	"mkfs" => Kind::Destructive, "wipefs" => Kind::Destructive, "fdisk" => Kind::Destructive,
+	"sfdisk" => Kind::Destructive, "parted" => Kind::Destructive, "mkswap" => Kind::Destructive,
+
+	"ssh" => Kind::Network, "scp" => Kind::Network, "sftp" => Kind::Network,
+	"rsync" => Kind::Network, "nc" => Kind::Network, "ncat" => Kind::Network,
+	"netcat" => Kind::Network, "socat" => Kind::Network, "telnet" => Kind::Network,
+	"ftp" => Kind::Network, "ping" => Kind::Network, "dig" => Kind::Network,
+	"nslookup" => Kind::Network, "host" => Kind::Network, "http" => Kind::Network,
+	"aria2c" => Kind::Network, "gh" => Kind::Network, "npx" => Kind::Network,
+	"bunx" => Kind::Network, "pnpx" => Kind::Network,
+	"curl" => Kind::Curl, "wget" => Kind::Wget, "git" => Kind::Git,
+
+	"sudo" => Kind::Wrapper {
+		value_opts:  &["-u", "-g", "-h", "-p", "-C", "-D", "-R", "-T", "-U", "-r", "-t"],
+		positionals: 0,
+		privileged:  true,
+	},
+	"doas" => Kind::Wrapper { value_opts: &["-u", "-C"], positionals: 0, privileged: true },
+	"pkexec" => Kind::Wrapper { value_opts: &["--user"], positionals: 0, privileged: true },
+	"runuser" => Kind::Wrapper { value_opts: &["-u", "-g", "-G"], positionals: 0, privileged: true },
+	"su" => Kind::Su,
+	"env" => Kind::Wrapper { value_opts: &["-u", "-C", "-S"], positionals: 0, privileged: false },
+	"nice" => Kind::Wrapper { value_opts: &["-n"], positionals: 0, privileged: false },
+	"ionice" => Kind::Wrapper { value_opts: &["-c", "-n"], positionals: 0, privileged: false },
+	"nohup" => Kind::Wrapper { value_opts: &[], positionals: 0, privileged: false },
+	"time" => Kind::Wrapper { value_opts: &["-f", "-o"], positionals: 0, privileged: false },
+	"timeout" => Kind::Wrapper { value_opts: &["-s", "-k"], positionals: 1, privileged: false },
+	"stdbuf" => Kind::Wrapper { value_opts: &["-i", "-o", "-e"], positionals: 0, privileged: false },
+	"setsid" => Kind::Wrapper { value_opts: &[], positionals: 0, privileged: false },
+	"watch" => Kind::Wrapper { value_opts: &["-n", "-d"], positionals: 0, privileged: false },
+	"flock" => Kind::Wrapper { value_opts: &["-w", "-E"], positionals: 1, privileged: false },
+	"command" => Kind::Wrapper { value_opts: &[], positionals: 0, privileged: false },
+	"builtin" => Kind::Wrapper { value_opts: &[], positionals: 0, privileged: false },
+	"exec" => Kind::Wrapper { value_opts: &["-a"], positionals: 0, privileged: false },
+	"xargs" => Kind::Wrapper {
+		value_opts:  &["-I", "-n", "-P", "-L", "-d", "-E", "-s", "-a"],
+		positionals: 0,
+		privileged:  false,
+	},
+
+	"sh" => Kind::Shell, "bash" => Kind::Shell, "zsh" => Kind::Shell,
+	"dash" => Kind::Shell, "ksh" => Kind::Shell, "fish" => Kind::Shell,
+	"python" => Kind::Interpreter, "python3" => Kind::Interpreter, "perl" => Kind::Interpreter,
+	"ruby" => Kind::Interpreter, "node" => Kind::Interpreter, "deno" => Kind::Interpreter,
+	"eval" => Kind::Eval, "source" => Kind::Source, "." => Kind::Source,
+	"find" => Kind::Find, "sed" => Kind::Sed, "sort" => Kind::Sort,
+	"uniq" => Kind::Uniq,
+
+	"npm" => Kind::Package, "pnpm" => Kind::Package, "yarn" => Kind::Package,
+	"bun" => Kind::Package, "pip" => Kind::Package, "pip3" => Kind::Package,
+	"uv" => Kind::Package, "cargo" => Kind::Package, "go" => Kind::Package,
+	"gem" => Kind::Package, "apt" => Kind::Package, "apt-get" => Kind::Package,
+	"dnf" => Kind::Package, "yum" => Kind::Package, "brew" => Kind::Package,
+	"pacman" => Kind::Package, "apk" => Kind::Package,
+};
+
+/// Package manager subcommands that download or upload packages.
+const PACKAGE_NETWORK: &[&str] =
+	&["install", "i", "add", "ci", "update", "upgrade", "fetch", "publish", "get", "-S", "-Syu"];
+
+/// Redirect targets that are not files.
+const DEVICES: &[&str] = &["/dev/null", "/dev/stdout", "/dev/stderr", "/dev/tty"];
+
+/// A command argument: its static text, if it has one.
+struct Arg {
+	text:    Option<String>,
+	/// Produced by a substitution that uses the network.
+	network: bool,
+}
+
+/// What a pipeline stage does with the data flowing through it.
+#[derive(Default, Clone, Copy)]
+struct Stage {
+	/// Output may come from the network.
+	network:   bool,
+	/// Executes code read from stdin.
+	runs_code: bool,
+}
+
+pub struct Analyzer<'a> {
+	shell:   &'a BrushShell,
+	result:  CommandAnalysis,
+	/// A program with unknown effects runs (unlisted or dynamic name, a
+	/// script file, an opaque interpreter).
+	unknown: bool,
+	stage:   Stage,
+}
+
+impl<'a> Analyzer<'a> {
+	pub const fn new(shell: &'a BrushShell) -> Self {
+		Self {
+			shell,
+			result: CommandAnalysis {
+				programs:             Vec::new(),
+				redirects:            Vec::new(),
+				unresolved:           Vec::new(),
+				read_only:            false,
+				writes_files:         false,
+				destructive:          false,
+				network:              false,
+				privilege_escalation: false,
+				pipes_to_shell:       false,
+			},
+			unknown: false,
+			stage: Stage { network: false, runs_code: false },
+		}
+	}
+
+	pub fn analyze(mut self, program: &ast::Program) -> CommandAnalysis {
+		self.program(program);
+		let result = &mut self.result;
+		result.read_only = !(self.unknown
+			|| result.writes_files
+			|| result.destructive
+			|| result.network
+			|| result.privilege_escalation
+			|| result.pipes_to_shell);
+		self.result
+	}
+
+	fn program(&mut self, program: &ast::Program) {
+		for command in &program.complete_commands {
+			self.list(command);
+		}
+	}
+
+	/// Parses and analyzes a nested script (`eval`, `sh -c`, substitutions).
+	fn script(&mut self, script: &str) {
+		match self.shell.parse_string(script) {
+			Ok(program) => self.program(&program),
+			Err(_) => self.unknown = true,
+		}
+	}
+
+	fn list(&mut self, list: &ast::CompoundList) {
+		for ast::CompoundListItem(and_or, _) in &list.0 {
+			self.pipeline(&and_or.first);
+			for next in &and_or.additional {
+				let (ast::AndOr::And(pipeline) | ast::AndOr::Or(pipeline)) = next;
+				self.pipeline(pipeline);
+			}
+		}
+	}
+
+	fn pipeline(&mut self, pipeline: &ast::Pipeline) {
+		let outer = mem::take(&mut self.stage);
+		let mut merged = Stage::default();
+		for command in &pipeline.seq {
+			self.stage = Stage::default();
+			self.command(command);
+			if merged.network && self.stage.runs_code {
+				self.result.pipes_to_shell = true;
+			}
+			merged.network |= self.stage.network;
+			merged.runs_code |= self.stage.runs_code;
+		}
+		self.stage = Stage {
+			network:   outer.network || merged.network,
+			runs_code: outer.runs_code || merged.runs_code,
+		};
+	}
+
+	fn command(&mut self, command: &ast::Command) {
+		match command {
+			ast::Command::Simple(simple) => self.simple(simple),
+			ast::Command::Compound(compound, redirects) => {
+				self.compound(compound);
+				self.redirects(redirects.as_ref());
+			},
+			ast::Command::Function(function) => {
+				self.compound(&function.body.0);
+				self.redirects(function.body.1.as_ref());
+			},
+			ast::Command::ExtendedTest(test) => self.test_expr(&test.expr),
+		}
+	}
+
+	fn compound(&mut self, compound: &ast::CompoundCommand) {
+		match compound {
+			ast::CompoundCommand::Arithmetic(_) => {},
+			ast::CompoundCommand::ArithmeticForClause(clause) => self.list(&clause.body.list),
+			ast::CompoundCommand::BraceGroup(group) => self.list(&group.list),
+			ast::CompoundCommand::Subshell(subshell) => self.list(&subshell.list),
+			ast::CompoundCommand::ForClause(clause) => {
+				for value in clause.values.iter().flatten() {
+					self.word(value);
+				}
+				self.list(&clause.body.list);
+			},
+			ast::CompoundCommand::CaseClause(clause) => {
+				self.word(&clause.value);
+				for case in &clause.cases {
+					if let Some(cmd) = &case.cmd {
+						self.list(cmd);
+					}
+				}
+			},
+			ast::CompoundCommand::IfClause(clause) => {
+				self.list(&clause.condition);
+				self.list(&clause.then);
+				for branch in clause.elses.iter().flatten() {
+					if let Some(condition) = &branch.condition {
+						self.list(condition);
+					}
+					self.list(&branch.body);
+				}
+			},
+			ast::CompoundCommand::WhileClause(clause) | ast::CompoundCommand::UntilClause(clause) => {
+				self.list(&clause.0);
+				self.list(&clause.1.list);
+			},
+		}
+	}
+
+	fn test_expr(&mut self, expr: &ast::ExtendedTestExpr) {
+		match expr {
+			ast::ExtendedTestExpr::And(left, right) | ast::ExtendedTestExpr::Or(left, right) => {
+				self.test_expr(left);
+				self.test_expr(right);
+			},
+			ast::ExtendedTestExpr::Not(inner) | ast::ExtendedTestExpr::Parenthesized(inner) => {
+				self.test_expr(inner);
+			},
+			ast::ExtendedTestExpr::UnaryTest(_, word) => {
+				self.word(word);
+			},
+			ast::ExtendedTestExpr::BinaryTest(_, left, right) => {
+				self.word(left);
+				self.word(right);
+			},
+		}
+	}
+
+	fn simple(&mut self, simple: &ast::SimpleCommand) {
+		let mut args = Vec::new();
+		let prefix = simple.prefix.iter().flat_map(|prefix| &prefix.0);
+		for item in prefix {
+			match item {
+				// Assignments before the command only set its environment.
+				ast::CommandPrefixOrSuffixItem::AssignmentWord(_, word) => {
+					self.word(word);
+				},
+				_ => self.item(item, &mut args),
+			}
+		}
+		if let Some(name) = &simple.word_or_name {
+			args.push(self.word(name));
+		}
+		for item in simple.suffix.iter().flat_map(|suffix| &suffix.0) {
+			self.item(item, &mut args);
+		}
+		self.invoke(&args);
+	}
+
+	fn item(&mut self, item: &ast::CommandPrefixOrSuffixItem, args: &mut Vec<Arg>) {
+		match item {
+			ast::CommandPrefixOrSuffixItem::IoRedirect(redirect) => self.redirect(redirect),
+			ast::CommandPrefixOrSuffixItem::Word(word)
+			| ast::CommandPrefixOrSuffixItem::AssignmentWord(_, word) => args.push(self.word(word)),
+			ast::CommandPrefixOrSuffixItem::ProcessSubstitution(_, subshell) => {
+				let network = self.nested(|this| this.list(&subshell.list));
+				args.push(Arg { text: None, network });
+			},
+		}
+	}
+
+	/// Analyzes a substitution, returning whether its output may come from
+	/// the network.
+	fn nested(&mut self, walk: impl FnOnce(&mut Self)) -> bool {
+		let outer = mem::take(&mut self.stage);
+		walk(self);
+		let network = self.stage.network;
+		self.stage = Stage { network: outer.network || network, ..outer };
+		network
+	}
+
+	fn redirects(&mut self, redirects: Option<&ast::RedirectList>) {
+		for redirect in redirects.iter().flat_map(|list| &list.0) {
+			self.redirect(redirect);
+		}
+	}
+
+	fn redirect(&mut self, redirect: &ast::IoRedirect) {
+		use ast::{IoFileRedirectKind as K, IoFileRedirectTarget as T};
+
+		match redirect {
+			ast::IoRedirect::File(fd, kind, target) => {
+				let word = match target {
+					T::Filename(word) => word,
+					// `>&word` writes to a file unless the word is a descriptor.
+					T::Duplicate(word) => {
+						let arg = self.word(word);
+						if arg
+							.text
+							.is_none_or(|text| text == "-" || text.parse::<u32>().is_ok())
+						{
+							return;
+						}
+						word
+					},
+					T::ProcessSubstitution(_, subshell) => {
+						self.nested(|this| this.list(&subshell.list));
+						return;
+					},
+					T::Fd(_) => return,
+				};
+				let kind = match kind {
+					K::Read | K::DuplicateInput => "read",
+					K::Write | K::Clobber | K::DuplicateOutput => "write",
+					K::Append => "append",
+					K::ReadAndWrite => "readWrite",
+				};
+				self.file_redirect(word, kind, fd.and_then(|fd| u32::try_from(fd).ok()));
+			},
+			ast::IoRedirect::OutputAndError(word, append) => {
+				self.file_redirect(word, if *append { "append" } else { "write" }, None);
+			},
+			ast::IoRedirect::HereString(_, word) => {
+				self.word(word);
+			},
+			// An unquoted delimiter expands the body when the command runs.
+			ast::IoRedirect::HereDocument(_, here) => {
+				if here.requires_expansion {
+					self.word(&here.doc);
+				}
+			},
+		}
+	}
+
+	fn file_redirect(&mut self, word: &ast::Word, kind: &str, fd: Option<u32>) {
+		let target = self.word(word).text.unwrap_or_else(|| word.value.clone());
+		let device = DEVICES.contains(&target.as_str()) || target.starts_with("/dev/fd/");
+		if kind != "read" && !device {
+			self.result.writes_files = true;
+		}
+		self
+			.result
+			.redirects
+			.push(CommandRedirect { target, kind: kind.to_string(), fd });
+	}
+
+	/// Resolves a word to its static text, recording dynamic expansions and
+	/// analyzing command substitutions.
+	fn word(&mut self, word: &ast::Word) -> Arg {
+		let mut arg = Arg { text: Some(String::new()), network: false };
+		if let Ok(pieces) = word::parse(&word.value, &self.shell.parser_options()) {
+			self.pieces(&word.value, &pieces, &mut arg);
+		} else {
+			self.unresolve(&word.value);
+			arg.text = None;
+		}
+		arg
+	}
+
+	fn pieces(&mut self, source: &str, pieces: &[WordPieceWithSource], arg: &mut Arg) {
+		for piece in pieces {
+			let literal = match &piece.piece {
+				WordPiece::Text(text)
+				| WordPiece::SingleQuotedText(text)
+				| WordPiece::AnsiCQuotedText(text) => text.clone(),
+				WordPiece::EscapeSequence(text) => text.strip_prefix('\\').unwrap_or(text).to_string(),
+				WordPiece::TildePrefix(user) => format!("~{user}"),
+				WordPiece::DoubleQuotedSequence(inner)
+				| WordPiece::GettextDoubleQuotedSequence(inner) => {
+					self.pieces(source, inner, arg);
+					continue;
+				},
+				WordPiece::CommandSubstitution(script)
+				| WordPiece::BackquotedCommandSubstitution(script) => {
+					self.unresolve(piece_source(source, piece));
+					arg.network |= self.nested(|this| this.script(script));
+					arg.text = None;
+					continue;
+				},
+				WordPiece::ParameterExpansion(_) | WordPiece::ArithmeticExpression(_) => {
+					self.unresolve(piece_source(source, piece));
+					arg.text = None;
+					continue;
+				},
+			};
+			if let Some(text) = &mut arg.text {
+				text.push_str(&literal);
+			}
+		}
+	}
+
+	fn unresolve(&mut self, expansion: &str) {
+		if !self.result.unresolved.iter().any(|seen| seen == expansion) {
+			self.result.unresolved.push(expansion.to_string());
+		}
+	}
+
+	/// Classifies a simple command from its program and arguments.
+	fn invoke(&mut self, args: &[Arg]) {
+		let Some(first) = args.first() else {
+			return;
+		};
+		let Some(name) = &first.text else {
+			self.unknown = true;
+			return;
+		};
+		if !self.result.programs.contains(name) {
+			self.result.programs.push(name.clone());
+		}
+		let program = name.rsplit('/').next().unwrap_or(name);
+		let rest = &args[1..];
+		let texts: Vec<&str> = rest.iter().filter_map(|arg| arg.text.as_deref()).collect();
+		let has = |flag: &str| texts.contains(&flag);
+		let any_network = rest.iter().any(|arg| arg.network);
+
+		let kind = if program.starts_with("mkfs.") {
+			Kind::Destructive
+		} else if let Some(kind) = PROGRAMS.get(program) {
+			*kind
+		} else {
+			self.unknown = true;
+			return;
+		};
+		match kind {
+			Kind::ReadOnly => {},
+			Kind::Writes => self.result.writes_files = true,
+			Kind::Destructive => self.result.destructive = true,
+			Kind::Network => self.use_network(),
+			Kind::Wrapper { value_opts, positionals, privileged } => {
+				self.result.privilege_escalation |= privileged;
+				// `command -v` only looks the program up.
+				if program == "command" && (has("-v") || has("-V")) {
+					return;
+				}
+				let start = wrapped_start(rest, value_opts) + positionals;
+				if start < rest.len() {
+					self.invoke(&rest[start..]);
+				} else if privileged {
+					// Interactive root shell.
+					self.unknown = true;
+				}
+			},
+			Kind::Su => {
+				self.result.privilege_escalation = true;
+				self.shell_args(rest);
+			},
+			Kind::Shell => self.shell_args(rest),
+			Kind::Interpreter => {
+				self.unknown = true;
+				self.result.pipes_to_shell |= any_network;
+				self.stage.runs_code |= reads_stdin(rest);
+			},
+			Kind::Eval => {
+				self.result.pipes_to_shell |= any_network;
+				if rest.iter().all(|arg| arg.text.is_some()) {
+					self.script(&texts.join(" "));
+				} else {
+					self.unknown = true;
+				}
+			},
+			Kind::Source => {
+				self.unknown = true;
+				self.result.pipes_to_shell |= any_network;
+			},
+			Kind::Find => self.find(rest),
+			Kind::Sed => {
+				// `-i` takes an optional backup suffix (`-i.bak`).
+				let in_place = texts
+					.iter()
+					.any(|text| text.starts_with("--in-place") || sets_short_flag(text, 'i', "efl"));
+				self.result.writes_files |= in_place;
+			},
+			Kind::Sort => {
+				self.result.writes_files |= texts
+					.iter()
+					.any(|text| text.starts_with("--output") || sets_short_flag(text, 'o', "ktST"));
+			},
+			Kind::Uniq => self.uniq(rest),
+			Kind::Curl => {
+				self.use_network();
+				self.result.writes_files |= texts.iter().any(|text| {
+					text.starts_with("--output")
+						|| text.starts_with("--remote-name")
+						|| (is_short_flags(text) && (text.contains('o') || text.contains('O')))
+				});
+			},
+			Kind::Wget => {
+				self.use_network();
+				let to_stdout = texts
+					.windows(2)
+					.any(|pair| pair[0].ends_with('O') && pair[1] == "-")
+					|| texts
+						.iter()
+						.any(|text| text.ends_with("O-") || *text == "--output-document=-");
+				self.result.writes_files |= !to_stdout;
+			},
+			Kind::Git => self.git(&texts),
+			Kind::Package => {
+				if texts.iter().any(|text| PACKAGE_NETWORK.contains(text)) {
+					self.use_network();
+					self.result.writes_files = true;
+				} else {
+					// Builds and package scripts run arbitrary code.
+					self.unknown = true;
+				}
+			},
+		}
+	}
+
+	const fn use_network(&mut self) {
+		self.result.network = true;
+		self.stage.network = true;
+	}
+
+	/// Shell invocation: analyzes a `-c` script, otherwise the shell runs a
+	/// script file or stdin.
+	fn shell_args(&mut self, args: &[Arg]) {
+		let script = args
+			.iter()
+			.position(|arg| {
+				arg.text
+					.as_deref()
+					.is_some_and(|text| is_short_flags(text) && text.contains('c'))
+			})
+			.and_then(|index| args.get(index + 1));
+		if let Some(script) = script {
+			self.result.pipes_to_shell |= script.network;
+			match &script.text {
+				Some(text) => self.script(text),
+				None => self.unknown = true,
+			}
+			return;
+		}
+		self.unknown = true;
+		self.result.pipes_to_shell |= args.iter().any(|arg| arg.network);
+		self.stage.runs_code |= reads_stdin(args);
+	}
+
+	fn find(&mut self, args: &[Arg]) {
+		let mut index = 0;
+		while let Some(arg) = args.get(index) {
+			index += 1;
+			match arg.text.as_deref() {
+				Some("-delete") => self.result.destructive = true,
+				Some("-fprint" | "-fprint0" | "-fprintf" | "-fls") => {
+					self.result.writes_files = true;
+				},
+				Some("-exec" | "-execdir" | "-ok" | "-okdir") => {
+					let end = args[index..]
+						.iter()
+						.position(|arg| matches!(arg.text.as_deref(), Some(";" | "+")))
+						.map_or(args.len(), |offset| index + offset);
+					self.invoke(&args[index..end]);
+					index = end + 1;
+				},
+				_ => {},
+			}
+		}
+	}
+
+	/// `uniq [INPUT [OUTPUT]]`: an output operand other than `-` is written.
+	fn uniq(&mut self, args: &[Arg]) {
+		let mut operands = Vec::new();
+		let mut options_done = false;
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			match arg.text.as_deref() {
+				Some("--") if !options_done => options_done = true,
+				// `-f`, `-s` and `-w` take a value.
+				Some("-f" | "-s" | "-w") if !options_done => {
+					args.next();
+				},
+				Some(text) if !options_done && text.len() > 1 && text.starts_with('-') => {},
+				text => operands.push(text),
+			}
+		}
+		self.result.writes_files |= operands.get(1).is_some_and(|output| *output != Some("-"));
+	}
+
+	fn git(&mut self, args: &[&str]) {
+		// Skip global options; `-C` and `-c` take a value.
+		let mut index = 0;
+		while let Some(arg) = args.get(index)
+			&& arg.starts_with('-')
+		{
+			index += if matches!(*arg, "-C" | "-c") { 2 } else { 1 };
+		}
+		let Some(subcommand) = args.get(index) else {
+			return;
+		};
+		let options = &args[index + 1..];
+		let has = |flag: &str| options.contains(&flag);
+		match *subcommand {
+			"status" | "log" | "diff" | "show" | "blame" | "grep" | "ls-files" | "ls-tree"
+			| "rev-parse" | "describe" | "shortlog" | "cat-file" | "show-ref" => {},
+			"push" => {
+				self.use_network();
+				self.result.destructive |= has("-f") || has("--force") || has("--force-with-lease");
+			},
+			"ls-remote" => self.use_network(),
+			"clone" | "fetch" | "pull" | "submodule" => {
+				self.use_network();
+				self.result.writes_files = true;
+			},
+			"clean" => self.result.destructive = true,
+			"reset" if has("--hard") => self.result.destructive = true,
+			"branch" if has("-D") => self.result.destructive = true,
+			_ => self.result.writes_files = true,
+		}
+	}
+}
+
+/// Index of the wrapped command in a wrapper's arguments, after its options
+/// and `NAME=value` assignments.
+fn wrapped_start(args: &[Arg], value_opts: &[&str]) -> usize {
+	let mut index = 0;
+	while let Some(text) = args.get(index).and_then(|arg| arg.text.as_deref()) {
+		if text == "--" {
+			return index + 1;
+		}
+		if text.len() > 1 && text.starts_with('-') {
+			index += if value_opts.contains(&text) { 2 } else { 1 };
+		} else if text.split_once('=').is_some_and(|(name, _)| is_name(name)) {
+			index += 1;
+		} else {
+			break;
+		}
+	}
+	index
+}
+
+/// Whether an interpreter reads its program from stdin: no script argument,
+/// or `-s`/`-`.
+fn reads_stdin(args: &[Arg]) -> bool {
+	let mut script = false;
+	for arg in args {
+		match arg.text.as_deref() {
+			Some("-s" | "-") => return true,
+			Some(text) if text.starts_with('-') => {},
+			_ => script = true,
+		}
+	}
+	!script
+}
+
+/// A cluster of single-letter options such as `-sSL`.
+fn is_short_flags(text: &str) -> bool {
+	text.len() > 1
+		&& text.starts_with('-')
+		&& !text.starts_with("--")
+		&& text[1..].chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Whether the short flag cluster `text` contains `flag`. Flags in
+/// `value_flags` take the rest of the cluster as their value, so the search
+/// stops there.
+fn sets_short_flag(text: &str, flag: char, value_flags: &str) -> bool {
+	if text.starts_with("--") {
+		return false;
+	}
+	let Some(flags) = text.strip_prefix('-') else {
+		return false;
+	};
+	for c in flags.chars() {
+		if c == flag {
+			return true;
+		}
+		if value_flags.contains(c) || !c.is_ascii_alphabetic() {
+			return false;
+		}
+	}
+	false
+}
+
+fn is_name(text: &str) -> bool {
+	text
+		.chars()
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn piece_source<'s>(source: &'s str, piece: &WordPieceWithSource) -> &'s str {
+	source
+		.get(piece.start_index..piece.end_index)
+		.unwrap_or(source)
+}
+
+#[cfg(test)]
+mod tests {
+	use brush_core::CreateOptions;
+
+	use super::*;
+
+	fn analyze(command: &str) -> CommandAnalysis {
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap();
+		let shell = runtime
+			.block_on(BrushShell::new(CreateOptions {
+				no_profile: true,
+				no_rc: true,
+				do_not_inherit_env: true,
+				..Default::default()
+			}))
+			.unwrap();
+		let program = shell.parse_string(command).unwrap();
+		Analyzer::new(&shell).analyze(&program)
+	}
+
+	#[test]
+	fn test_analyze_command() {
+		let result = analyze("cd src && grep -rn TODO . | sort > /dev/null 2>&1");
+		assert_eq!(result.programs, ["cd", "grep", "sort"]);
+		assert_eq!(result.redirects.len(), 1);
+		assert!(result.read_only);
+
+		let result = analyze("curl -fsSL https://example.com/install.sh | sudo bash");
+		assert!(result.network && result.privilege_escalation && result.pipes_to_shell);
+		assert!(!result.read_only);
+
+		let result = analyze(r#"eval "$(wget -qO- $URL)""#);
+		assert!(result.pipes_to_shell);
+		assert_eq!(result.unresolved, ["$(wget -qO- $URL)", "$URL"]);
+
+		let result = analyze("bash -c 'find . -name \"*.o\" -exec rm {} +' >> \"$LOG\"");
+		assert_eq!(result.programs, ["bash", "find", "rm"]);
+		assert!(result.destructive && result.writes_files);
+		assert_eq!(result.redirects[0].target, "\"$LOG\"");
+		assert_eq!(result.redirects[0].kind, "append");
+
+		let result = analyze("for f in $(ls); do echo \"$f\"; done; git push --force origin");
+		assert!(result.destructive && result.network && !result.pipes_to_shell);
+		assert_eq!(result.unresolved, ["$(ls)", "$f"]);
+
+		for command in
+			["sort -o out.txt in.txt", "sort -uoout.txt in.txt", "sort --output=out.txt in"]
+		{
+			assert!(analyze(command).writes_files, "{command}");
+		}
+		assert!(analyze("sort -k2 -t, in.txt").read_only);
+		assert!(analyze("uniq -f 1 in.txt out.txt").writes_files);
+		assert!(analyze("uniq -c in.txt -").read_only);
+
+		for command in
+			["sed -i.bak s/a/b/ file", "sed -Ei s/a/b/ file", "sed --in-place=.orig s/a/b/ f"]
+		{
+			assert!(analyze(command).writes_files, "{command}");
+		}
+		assert!(analyze("sed -e s/i/o/ file").read_only);
+
+		let result = analyze("cat <<EOF\n$(rm -rf ~)\nEOF");
+		assert_eq!(result.programs, ["rm", "cat"]);
+		assert!(result.destructive && !result.read_only);
+		assert!(analyze("cat <<'EOF'\n$(rm -rf ~)\nEOF").read_only);
+	}
+}
@@ -0,0 +1,628 @@
+//! Directory tree outlines rendered from cached scans.
+//!
+//! # Overview
+//! Builds a tree from the entries of [`fs_cache::get_or_scan`], aggregates
+//! file counts and sizes per directory, and renders it with box-drawing (or
+//! ASCII) connectors. Output is bounded three ways:
+//! - `maxDepth`: directories at the limit are rendered collapsed.
+//! - `maxChildren`: entries past the cap are summarized as `… N more files`.
+//! - `maxTokens`: directories are collapsed until the outline fits. Build
+//!   output, vendored code, and caches go first, then the largest directories
+//!   whose subdirectories are already collapsed, so the outline shrinks from
+//!   the leaves towards the root.
+//!
+//! A collapsed directory is rendered as `name/ (12 files, 3.4 KB)`.
+//!
+//! # Example
+//! ```ignore
+//! // JS: await native.tree({ path: ".", maxDepth: 4, maxTokens: 2000 })
+//! ```
+
+use std::{
+	cmp::Reverse,
+	collections::{BinaryHeap, HashMap},
+	path::Path,
+};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::{
+	fs_cache::{self, FileType, GlobMatch},
+	task,
+};
+
+/// Entries listed per directory when `maxChildren` is not set.
+const DEFAULT_MAX_CHILDREN: usize = 50;
+
+/// Rough characters-per-token ratio used for the token budget.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Directory names collapsed before any other when over the token budget.
+const LOW_INTEREST_DIRS: [&str; 18] = [
+	".cache",
+	".next",
+	".turbo",
+	".venv",
+	"__pycache__",
+	"__snapshots__",
+	"bower_components",
+	"build",
+	"coverage",
+	"dist",
+	"fixtures",
+	"generated",
+	"node_modules",
+	"out",
+	"target",
+	"third_party",
+	"vendor",
+	"venv",
+];
+
+/// Options for rendering a directory tree.
+#[napi(object)]
+pub struct TreeOptions<'env> {
+	/// Directory to render.
+	pub path:                 String,
+	/// Depth at which directories are collapsed; 1 lists only the root's
+	/// children (default: unlimited).
+	#[napi(js_name = "maxDepth")]
+	pub max_depth:            Option<u32>,
+	/// Maximum entries listed per directory (default: 50).
+	#[napi(js_name = "maxChildren")]
+	pub max_children:         Option<u32>,
+	/// Approximate token budget for the outline, at about 4 characters per
+	/// token (default: unlimited).
+	#[napi(js_name = "maxTokens")]
+	pub max_tokens:           Option<u32>,
+	/// Annotate every directory with its file count and size, not only
+	/// collapsed ones (default: false).
+	pub summaries:            Option<bool>,
+	/// Use ASCII connectors instead of box-drawing characters (default:
+	/// false).
+	pub ascii:                Option<bool>,
+	/// Include hidden files (default: false).
+	pub hidden:               Option<bool>,
+	/// Respect .gitignore files (default: true).
+	pub gitignore:            Option<bool>,
+	/// Include `node_modules` directories (default: false).
+	#[napi(js_name = "includeNodeModules")]
+	pub include_node_modules: Option<bool>,
+	/// Abort signal for cancelling the operation.
+	pub signal:               Option<Unknown<'env>>,
+	/// Timeout in milliseconds for the operation.
+	#[napi(js_name = "timeoutMs")]
+	pub timeout_ms:           Option<u32>,
+}
+
+/// Rendered directory tree.
+#[napi(object)]
+pub struct TreeResult {
+	/// Outline text, one entry per line.
+	pub text:        String,
+	/// Files (including symlinks) below the root.
+	#[napi(js_name = "totalFiles")]
+	pub total_files: u32,
+	/// Directories below the root.
+	#[napi(js_name = "totalDirs")]
+	pub total_dirs:  u32,
+	/// Combined size of all files in bytes.
+	#[napi(js_name = "totalSize")]
+	pub total_size:  f64,
+	/// Estimated token count of `text`.
+	pub tokens:      u32,
+	/// Whether the depth limit, child cap, or token budget hid any entries.
+	pub truncated:   bool,
+}
+
+/// Connector strings; every connector is four characters wide.
+struct Glyphs {
+	branch: &'static str,
+	last:   &'static str,
+	pipe:   &'static str,
+	blank:  &'static str,
+	more:   &'static str,
+}
+
+const UNICODE: Glyphs =
+	Glyphs { branch: "├── ", last: "└── ", pipe: "│   ", blank: "    ", more: "… " };
+
+const ASCII: Glyphs =
+	Glyphs { branch: "|-- ", last: "`-- ", pipe: "|   ", blank: "    ", more: "... " };
+
+struct Node {
+	name:      String,
+	is_dir:    bool,
+	depth:     usize,
+	parent:    usize,
+	children:  Vec<usize>,
+	/// Files below this node (1 for a file).
+	files:     u64,
+	/// Directories below this node.
+	dirs:      u64,
+	/// Bytes below this node.
+	size:      f64,
+	collapsed: bool,
+}
+
+/// Tree of scan entries in an arena; children always follow their parent.
+struct Outline {
+	nodes:        Vec<Node>,
+	max_children: usize,
+	summaries:    bool,
+	glyphs:       &'static Glyphs,
+}
+
+impl Outline {
+	/// Builds the tree from scan entries in walker order. Entries whose parent
+	/// directory was filtered out are dropped with it.
+	fn new<'a>(root_name: String, entries: impl IntoIterator<Item = &'a GlobMatch>) -> Self {
+		let mut nodes = vec![Node {
+			name:      root_name,
+			is_dir:    true,
+			depth:     0,
+			parent:    0,
+			children:  Vec::new(),
+			files:     0,
+			dirs:      0,
+			size:      0.0,
+			collapsed: false,
+		}];
+		let mut dirs: HashMap<&str, usize> = HashMap::from([("", 0)]);
+		for entry in entries {
+			let (parent_path, name) = entry.path.rsplit_once('/').unwrap_or(("", &entry.path));
+			let Some(&parent) = dirs.get(parent_path) else {
+				continue;
+			};
+			let idx = nodes.len();
+			let is_dir = entry.file_type == FileType::Dir;
+			if is_dir {
+				dirs.insert(&entry.path, idx);
+			}
+			let depth = nodes[parent].depth + 1;
+			nodes[parent].children.push(idx);
+			nodes.push(Node {
+				name: name.to_string(),
+				is_dir,
+				depth,
+				parent,
+				children: Vec::new(),
+				files: u64::from(!is_dir),
+				dirs: 0,
+				size: if is_dir {
+					0.0
+				} else {
+					entry.size.unwrap_or(0.0)
+				},
+				collapsed: false,
+			});
+		}
+		for idx in (1..nodes.len()).rev() {
+			let Node { parent, files, dirs, size, is_dir, .. } = nodes[idx];
+			nodes[parent].files += files;
+			nodes[parent].dirs += dirs + u64::from(is_dir);
+			nodes[parent].size += size;
+		}
+		Self { nodes, max_children: DEFAULT_MAX_CHILDREN, summaries: false, glyphs: &UNICODE }
+	}
+
+	/// Collapses every directory at `max_depth` or deeper.
+	fn limit_depth(&mut self, max_depth: usize) {
+		for node in &mut self.nodes {
+			if node.is_dir && node.depth >= max_depth {
+				node.collapsed = true;
+			}
+		}
+	}
+
+	/// Children rendered under `idx`.
+	fn shown(&self, idx: usize) -> &[usize] {
+		let node = &self.nodes[idx];
+		if node.collapsed {
+			return &[];
+		}
+		&node.children[..node.children.len().min(self.max_children)]
+	}
+
+	/// Whether `idx` is rendered with children that collapsing would hide.
+	fn is_open(&self, idx: usize) -> bool {
+		let node = &self.nodes[idx];
+		node.is_dir && !node.collapsed && !node.children.is_empty()
+	}
+
+	fn summary(&self, idx: usize) -> Option<String> {
+		let node = &self.nodes[idx];
+		if !node.is_dir || !(idx == 0 || node.collapsed || self.summaries) {
+			return None;
+		}
+		Some(match node.files {
+			0 => "(empty)".to_string(),
+			1 => format!("(1 file, {})", format_size(node.size)),
+			files => format!("({files} files, {})", format_size(node.size)),
+		})
+	}
+
+	/// Summary of the children past the cap, e.g. `3 more dirs and 12 more
+	/// files`.
+	fn more(&self, idx: usize) -> Option<String> {
+		let node = &self.nodes[idx];
+		if node.collapsed || node.children.len() <= self.max_children {
+			return None;
+		}
+		let hidden = &node.children[self.max_children..];
+		let dirs = hidden
+			.iter()
+			.filter(|&&child| self.nodes[child].is_dir)
+			.count();
+		let count = |n: usize, noun: &str| {
+			if n == 1 {
+				format!("1 more {noun}")
+			} else {
+				format!("{n} more {noun}s")
+			}
+		};
+		Some(match (dirs, hidden.len() - dirs) {
+			(0, files) => count(files, "file"),
+			(dirs, 0) => count(dirs, "dir"),
+			(dirs, files) => format!("{} and {}", count(dirs, "dir"), count(files, "file")),
+		})
+	}
+
+	fn label(&self, idx: usize) -> String {
+		let node = &self.nodes[idx];
+		let slash = if node.is_dir { "/" } else { "" };
+		match self.summary(idx) {
+			Some(summary) => format!("{}{slash} {summary}", node.name),
+			None => format!("{}{slash}", node.name),
+		}
+	}
+
+	/// Rendered characters of the line of `idx`, including its newline.
+	fn line_len(&self, idx: usize) -> usize {
+		4 * self.nodes[idx].depth + self.label(idx).chars().count() + 1
+	}
+
+	/// Rendered characters of the "more" line under `idx`, if any.
+	fn more_len(&self, idx: usize) -> usize {
+		self.more(idx).map_or(0, |more| {
+			4 * (self.nodes[idx].depth + 1)
+				+ self.glyphs.more.chars().count()
+				+ more.chars().count()
+				+ 1
+		})
+	}
+
+	/// Rendered characters of each subtree.
+	fn costs(&self) -> Vec<usize> {
+		let mut costs = vec![0; self.nodes.len()];
+		for idx in (0..self.nodes.len()).rev() {
+			let children: usize = self.shown(idx).iter().map(|&child| costs[child]).sum();
+			costs[idx] = self.line_len(idx) + children + self.more_len(idx);
+		}
+		costs
+	}
+
+	fn visible(&self) -> Vec<bool> {
+		let mut visible = vec![false; self.nodes.len()];
+		visible[0] = true;
+		for idx in 0..self.nodes.len() {
+			if visible[idx] {
+				for &child in self.shown(idx) {
+					visible[child] = true;
+				}
+			}
+		}
+		visible
+	}
+
+	fn has_collapsed_ancestor(&self, mut idx: usize) -> bool {
+		while idx != 0 {
+			idx = self.nodes[idx].parent;
+			if self.nodes[idx].collapsed {
+				return true;
+			}
+		}
+		false
+	}
+
+	/// Collapses directories until the outline fits in `budget` characters.
+	fn fit(&mut self, budget: usize) {
+		let mut costs = self.costs();
+		if costs[0] <= budget {
+			return;
+		}
+		let visible = self.visible();
+		let mut open_children = vec![0usize; self.nodes.len()];
+		for idx in 1..self.nodes.len() {
+			if visible[idx] && self.is_open(idx) {
+				open_children[self.nodes[idx].parent] += 1;
+			}
+		}
+		let low_interest = |node: &Node| LOW_INTEREST_DIRS.contains(&node.name.as_str());
+		let mut heap = BinaryHeap::new();
+		for idx in 1..self.nodes.len() {
+			let node = &self.nodes[idx];
+			if visible[idx] && self.is_open(idx) && (open_children[idx] == 0 || low_interest(node)) {
+				heap.push((low_interest(node), costs[idx], Reverse(idx)));
+			}
+		}
+
+		let mut done = vec![false; self.nodes.len()];
+		while costs[0] > budget
+			&& let Some((_, _, Reverse(idx))) = heap.pop()
+		{
+			if done[idx] || self.has_collapsed_ancestor(idx) {
+				continue;
+			}
+			done[idx] = true;
+			self.nodes[idx].collapsed = true;
+			let collapsed = self.line_len(idx);
+			if collapsed < costs[idx] {
+				let saved = costs[idx] - collapsed;
+				let mut ancestor = idx;
+				loop {
+					costs[ancestor] -= saved;
+					if ancestor == 0 {
+						break;
+					}
+					ancestor = self.nodes[ancestor].parent;
+				}
+			} else {
+				// The summary is longer than what it would replace.
+				self.nodes[idx].collapsed = false;
+			}
+			let parent = self.nodes[idx].parent;
+			if parent != 0 {
+				open_children[parent] -= 1;
+				if open_children[parent] == 0 {
+					heap.push((low_interest(&self.nodes[parent]), costs[parent], Reverse(parent)));
+				}
+			}
+		}
+	}
+
+	/// Whether any entry below a visible directory is not rendered.
+	fn is_truncated(&self) -> bool {
+		let visible = self.visible();
+		(0..self.nodes.len()).any(|idx| {
+			let node = &self.nodes[idx];
+			visible[idx]
+				&& node.is_dir
+				&& ((node.collapsed && !node.children.is_empty())
+					|| node.children.len() > self.max_children)
+		})
+	}
+
+	fn render(&self) -> String {
+		let mut out = String::new();
+		out.push_str(&self.label(0));
+		out.push('\n');
+		self.render_children(0, &mut String::new(), &mut out);
+		out
+	}
+
+	fn render_children(&self, idx: usize, prefix: &mut String, out: &mut String) {
+		let shown = self.shown(idx);
+		let more = self.more(idx);
+		for (position, &child) in shown.iter().enumerate() {
+			let last = position + 1 == shown.len() && more.is_none();
+			out.push_str(prefix);
+			out.push_str(if last {
+				self.glyphs.last
+			} else {
+				self.glyphs.branch
+			});
+			out.push_str(&self.label(child));
+			out.push('\n');
+			let len = prefix.len();
+			prefix.push_str(if last {
+				self.glyphs.blank
+			} else {
+				self.glyphs.pipe
+			});
+			self.render_children(child, prefix, out);
+			prefix.truncate(len);
+		}
+		if let Some(more) = more {
+			out.push_str(prefix);
+			out.push_str(self.glyphs.last);
+			out.push_str(self.glyphs.more);
+			out.push_str(&more);
+			out.push('\n');
+		}
+	}
+
+	fn into_result(self) -> TreeResult {
+		let text = self.render();
+		let root = &self.nodes[0];
+		TreeResult {
+			tokens: text
+				.chars()
+				.count()
+				.div_ceil(CHARS_PER_TOKEN)
+				.min(u32::MAX as usize) as u32,
+			total_files: root.files.min(u64::from(u32::MAX)) as u32,
+			total_dirs: root.dirs.min(u64::from(u32::MAX)) as u32,
+			total_size: root.size,
+			truncated: self.is_truncated(),
+			text,
+		}
+	}
+}
+
+/// Formats a byte count with a binary unit, e.g. `3.4 KB`.
+fn format_size(bytes: f64) -> String {
+	const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+	if bytes < 1024.0 {
+		return format!("{bytes} B");
+	}
+	let mut value = bytes / 1024.0;
+	let mut unit = 0;
+	while value >= 1024.0 && unit + 1 < UNITS.len() {
+		value /= 1024.0;
+		unit += 1;
+	}
+	format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Render the directory tree below a path.
+///
+/// Entries come from the shared scan cache, so repeated calls (and calls
+/// after `glob` or `fuzzyFind` on the same root) reuse one scan.
+///
+/// # Errors
+/// Returns an error when the path cannot be resolved or is not a directory,
+/// or on cancellation/timeout.
+#[napi(js_name = "tree")]
+pub fn tree(options: TreeOptions<'_>) -> task::Async<TreeResult> {
+	let TreeOptions {
+		path,
+		max_depth,
+		max_children,
+		max_tokens,
+		summaries,
+		ascii,
+		hidden,
+		gitignore,
+		include_node_modules,
+		signal,
+		timeout_ms,
+	} = options;
+	let ct = task::CancelToken::new(timeout_ms, signal);
+
+	task::blocking("tree", ct, move |ct| {
+		let root = fs_cache::resolve_search_path(&path)?;
+		let options = fs_cache::ScanOptions::new(hidden.unwrap_or(false), gitignore.unwrap_or(true));
+		let scan = fs_cache::get_or_scan(&root, &options, &ct)?;
+		let include_node_modules = include_node_modules.unwrap_or(false);
+		let root_name = root.file_name().map_or_else(
+			|| root.to_string_lossy().into_owned(),
+			|name| name.to_string_lossy().into_owned(),
+		);
+		let mut outline = Outline::new(
+			root_name,
+			scan.entries.iter().filter(|entry| {
+				!fs_cache::should_skip_path(Path::new(&entry.path), include_node_modules)
+			}),
+		);
+		outline.max_children = max_children.map_or(DEFAULT_MAX_CHILDREN, |max| max as usize);
+		outline.summaries = summaries.unwrap_or(false);
+		if ascii.unwrap_or(false) {
+			outline.glyphs = &ASCII;
+		}
+		if let Some(max_depth) = max_depth {
+			outline.limit_depth(max_depth as usize);
+		}
+		ct.heartbeat()?;
+		if let Some(max_tokens) = max_tokens {
+			outline.fit((max_tokens as usize).saturating_mul(CHARS_PER_TOKEN));
+		}
+		Ok(outline.into_result())
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entries(specs: &[(&str, u32)]) -> Vec<GlobMatch> {
+		specs
+			.iter()
+			.map(|&(path, size)| GlobMatch {
+				path: path.trim_end_matches('/').to_string(),
+				file_type: if path.ends_with('/') {
+					FileType::Dir
+				} else {
+					FileType::File
+				},
+				size: Some(f64::from(size)),
+				..GlobMatch::default()
+			})
+			.collect()
+	}
+
+	fn fixture() -> Vec<GlobMatch> {
+		entries(&[
+			("Cargo.toml", 100),
+			("src/", 0),
+			("src/lib.rs", 2048),
+			("src/util/", 0),
+			("src/util/a.rs", 10),
+			("src/util/b.rs", 10),
+			("target/", 0),
+			("target/debug/", 0),
+			("target/debug/app", 1_048_576),
+		])
+	}
+
+	#[test]
+	fn test_render_with_depth_and_cap() {
+		let mut outline = Outline::new("proj".to_string(), &fixture());
+		assert_eq!(
+			outline.render(),
+			"\
+proj/ (5 files, 1.0 MB)
+├── Cargo.toml
+├── src/
+│   ├── lib.rs
+│   └── util/
+│       ├── a.rs
+│       └── b.rs
+└── target/
+    └── debug/
+        └── app
+"
+		);
+		outline.max_children = 1;
+		outline.limit_depth(2);
+		outline.glyphs = &ASCII;
+		let result = outline.into_result();
+		assert_eq!(
+			result.text,
+			"\
+proj/ (5 files, 1.0 MB)
+|-- Cargo.toml
+`-- ... 2 more dirs
+"
+		);
+		assert_eq!((result.total_files, result.total_dirs), (5, 4));
+		assert!(result.truncated);
+	}
+
+	#[test]
+	fn test_fit_collapses_low_interest_then_leaves() {
+		let mut outline = Outline::new("proj".to_string(), &fixture());
+		outline.fit(140);
+		assert_eq!(
+			outline.render(),
+			"\
+proj/ (5 files, 1.0 MB)
+├── Cargo.toml
+├── src/
+│   ├── lib.rs
+│   └── util/
+│       ├── a.rs
+│       └── b.rs
+└── target/ (1 file, 1.0 MB)
+"
+		);
+		outline.fit(125);
+		assert_eq!(
+			outline.render(),
+			"\
+proj/ (5 files, 1.0 MB)
+├── Cargo.toml
+├── src/
+│   ├── lib.rs
+│   └── util/ (2 files, 20 B)
+└── target/ (1 file, 1.0 MB)
+"
+		);
+		assert!(
+			!Outline::new("proj".to_string(), &fixture())
+				.into_result()
+				.truncated
+		);
+	}
+}
@@ -14,6 +14,18 @@ export interface Cancellable {
 	signal?: AbortSignal;
 }
 
+/** Directory walker options shared by glob, grep and fuzzyFind. Scans with different options are cached separately. */
+export interface WalkOptions {
+	/** Extra ignore filenames with gitignore syntax, honored like `.ignore` (default: `.ompignore`, `.piignore`). */
+	ignoreFiles?: string[];
+	/** Override globs as for ripgrep's `--glob`, taking precedence over ignore files: `!glob` excludes, positive globs restrict files to matches. */
+	overrides?: string[];
+	/** Maximum directory depth to descend (1 lists only direct children). */
+	maxDepth?: number;
+	/** Follow symbolic links to directories (default: false). */
+	followSymlinks?: boolean;
+}
+
 /**
  * Native bindings interface.
  * Extended by each module via declaration merging.
@@ -0,0 +1,9 @@
+/**
+ * Frecency tracking of recently used files powered by native bindings.
+ */
+
+import { native } from "../native";
+
+export type { FileAccessKind, ListRecentFilesOptions, RecentFile, RecordFileAccessOptions } from "./types";
+
+export const { recordFileAccess, listRecentFiles } = native;
@@ -0,0 +1,46 @@
+/**
+ * Types for the frecency store.
+ */
+
+/** Kind of a recorded file access. Edits weigh twice as much as opens. */
+export type FileAccessKind = "open" | "edit";
+
+/** Options for recording a file access. */
+export interface RecordFileAccessOptions {
+	/** Project root whose state directory (`.omp`) holds the store. */
+	root: string;
+	/** Accessed file, absolute or relative to `root`. */
+	path: string;
+	/** Kind of access (default: "open"). */
+	kind?: FileAccessKind;
+}
+
+/** Options for listing recently used files. */
+export interface ListRecentFilesOptions {
+	/** Project root whose state directory (`.omp`) holds the store. */
+	root: string;
+	/** Maximum number of files to return (default: 20). */
+	limit?: number;
+}
+
+/** A recently used file. */
+export interface RecentFile {
+	/** Path relative to the project root (uses `/` separators). */
+	path: string;
+	/** Decayed frecency score (higher is better). */
+	score: number;
+	/** Time of the last access in milliseconds since the Unix epoch. */
+	lastAccess: number;
+	/** Number of recorded accesses. */
+	count: number;
+}
+
+declare module "../bindings" {
+	/** Native bindings for the frecency store. */
+	interface NativeBindings {
+		/** Record that a file was opened or edited. */
+		recordFileAccess(options: RecordFileAccessOptions): void;
+		/** List recently used files of a project, most frecent first. */
+		listRecentFiles(options: ListRecentFilesOptions): RecentFile[];
+	}
+}
@@ -4,9 +4,19 @@
 
 import * as path from "node:path";
 import { native } from "../native";
-import type { GlobMatch, GlobOptions, GlobResult } from "./types";
+import type { FsChangeEvent, FsWatcher, FsWatchOptions, GlobMatch, GlobOptions, GlobResult } from "./types";
 
-export type { GlobMatch, GlobOptions, GlobResult } from "./types";
+export type {
+	FsChange,
+	FsChangeEvent,
+	FsChangeKind,
+	FsWatcher,
+	FsWatchOptions,
+	GitFileStatus,
+	GlobMatch,
+	GlobOptions,
+	GlobResult,
+} from "./types";
 export { FileType } from "./types";
 
 /**
@@ -15,7 +25,6 @@ export { FileType } from "./types";
  */
 export async function glob(options: GlobOptions, onMatch?: (match: GlobMatch) => void): Promise<GlobResult> {
 	const searchPath = path.resolve(options.path);
-	const pattern = options.pattern || "*";
 	// napi-rs ThreadsafeFunction passes (error, value) - skip callback on error
 	const cb = onMatch ? (err: Error | null, m: GlobMatch) => !err && onMatch(m) : undefined;
 
@@ -23,7 +32,6 @@ export async function glob(options: GlobOptions, onMatch?: (match: GlobMatch) =>
 		{
 			...options,
 			path: searchPath,
-			pattern,
 			hidden: options.hidden ?? false,
 			gitignore: options.gitignore ?? true,
 			recursive: options.recursive ?? true,
@@ -35,9 +43,27 @@ export async function glob(options: GlobOptions, onMatch?: (match: GlobMatch) =>
 /**
  * Invalidate the filesystem scan cache.
  *
- * When called with a path, removes entries for roots containing that path.
+ * When called with a path, marks the directory containing that path (or its
+ * subtree, for a directory) for a refresh on the next scan of every cached scan.
  * When called without a path, clears the entire cache.
  */
 export function invalidateFsScanCache(path?: string): void {
 	native.invalidateFsScanCache(path);
 }
+
+/**
+ * Watch a directory for changes.
+ *
+ * While open, the directory's scan cache entry is kept current from filesystem
+ * events instead of expiring, and `onChange` receives add/remove/modify deltas.
+ * Call `close()` on the returned watcher to stop.
+ */
+export function watchFs(options: FsWatchOptions, onChange: (event: FsChangeEvent) => void): Promise<FsWatcher> {
+	return native.watchFs(
+		{
+			...options,
+			path: path.resolve(options.path),
+		},
+		(err, event) => !err && onChange(event),
+	);
+}
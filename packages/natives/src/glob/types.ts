@@ -2,7 +2,7 @@
  * Types for native find API.
  */
 
-import type { Cancellable, TsFunc } from "../bindings";
+import type { Cancellable, TsFunc, WalkOptions } from "../bindings";
 
 export const enum FileType {
 	/** A regular file. */
@@ -14,9 +14,13 @@ export const enum FileType {
 }
 
 /** Options for discovering files and directories. */
-export interface GlobOptions extends Cancellable {
-	/** Glob pattern to match (e.g., `*.ts`). */
-	pattern: string;
+export interface GlobOptions extends Cancellable, WalkOptions {
+	/** Glob pattern to match (e.g., `*.ts`). Defaults to `*` when neither `pattern` nor `patterns` is given. */
+	pattern?: string;
+	/** Additional glob patterns; entries matching any of the patterns are returned. */
+	patterns?: string[];
+	/** Glob patterns to exclude. An excluded directory excludes everything below it. */
+	exclude?: string[];
 	/** Directory to search. */
 	path: string;
 	/** Filter by file type: "file", "dir", or "symlink". Symlinks match file/dir filters when their target type matches. */
@@ -35,8 +39,21 @@ export interface GlobOptions extends Cancellable {
 	sortByMtime?: boolean;
 	/** Include node_modules entries even when pattern does not mention node_modules. */
 	includeNodeModules?: boolean;
+	/** Only match files of at least this many bytes. Excludes directories. */
+	minSize?: number;
+	/** Only match files of at most this many bytes. Excludes directories. */
+	maxSize?: number;
+	/** Only match entries modified within this many milliseconds. */
+	changedWithin?: number;
+	/** Only match entries modified more than this many milliseconds ago. */
+	changedBefore?: number;
+	/** Report size, mode, symlink target, inode/device, and git status for each match (default: false). */
+	withMetadata?: boolean;
 }
 
+/** Git working-tree status of a match. */
+export type GitFileStatus = "modified" | "added" | "renamed" | "untracked" | "ignored" | "conflicted";
+
 /** A single filesystem match. */
 export interface GlobMatch {
 	/** Relative path from the search root. */
@@ -45,6 +62,57 @@ export interface GlobMatch {
 	fileType: FileType;
 	/** Modification time in milliseconds since epoch, if available. */
 	mtime?: number;
+	/** Size in bytes of files and symlinks (with `withMetadata`). */
+	size?: number;
+	/** Unix permission bits, including the file type bits (with `withMetadata`). */
+	mode?: number;
+	/** Whether any execute bit is set on a file (with `withMetadata`). */
+	executable?: boolean;
+	/** Raw target of a symlink (with `withMetadata`). */
+	symlinkTarget?: string;
+	/** Inode number; together with `dev`, identifies hardlinks (with `withMetadata`). */
+	ino?: number;
+	/** Device number of the filesystem holding the entry (with `withMetadata`). */
+	dev?: number;
+	/** Git working-tree status; absent for clean files and outside a repository (with `withMetadata`; costs one `git status` pass per call). */
+	gitStatus?: GitFileStatus;
+}
+
+/** Options for watching a directory for changes. */
+export interface FsWatchOptions {
+	/** Directory to watch. */
+	path: string;
+	/** Include hidden files (default: false). */
+	hidden?: boolean;
+	/** Respect .gitignore (default: true). */
+	gitignore?: boolean;
+}
+
+/** Kind of a filesystem change. */
+export type FsChangeKind = "add" | "remove" | "modify";
+
+/** A single change to a watched directory. */
+export interface FsChange {
+	/** Relative path from the watched root, using forward slashes. */
+	path: string;
+	/** Kind of change. */
+	kind: FsChangeKind;
+	/** Filesystem type of the entry (before removal for `remove`). */
+	fileType: FileType;
+}
+
+/** Changes to a watched directory, delivered in debounced batches. */
+export interface FsChangeEvent {
+	/** Changed entries, in the order they were applied. */
+	changes: FsChange[];
+	/** Whether the batch comes from a full rescan (event overflow, ignore file changes, or explicit invalidation). */
+	rescan: boolean;
+}
+
+/** Subscription to changes of a watched directory. */
+export interface FsWatcher {
+	/** Stop delivering changes. Idempotent. */
+	close(): void;
 }
 
 /** Result of a find operation. */
@@ -65,5 +133,11 @@ declare module "../bindings" {
 		glob(options: GlobOptions, onMatch?: TsFunc<GlobMatch>): Promise<GlobResult>;
 		/** Invalidate the filesystem scan cache for the given path (or all caches if omitted). */
 		invalidateFsScanCache(path?: string): void;
+		/**
+		 * Watch a directory, keeping its scan cache entry current and reporting changes.
+		 * @param options Directory and visibility rules.
+		 * @param onChange Callback for each debounced batch of changes.
+		 */
+		watchFs(options: FsWatchOptions, onChange: TsFunc<FsChangeEvent>): Promise<FsWatcher>;
 	}
 }
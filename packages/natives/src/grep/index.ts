@@ -5,28 +5,40 @@
 import { native } from "../native";
 import type {
 	ContextLine,
+	FileTypeDefinition,
 	FuzzyFindMatch,
 	FuzzyFindOptions,
 	FuzzyFindResult,
+	GrepFileError,
 	GrepMatch,
 	GrepOptions,
 	GrepResult,
 	GrepSummary,
+	ReplaceFileResult,
+	ReplaceOptions,
+	ReplaceResult,
 	SearchOptions,
 	SearchResult,
+	Submatch,
 } from "./types";
 
 export type {
 	ContextLine,
+	FileTypeDefinition,
 	FuzzyFindMatch,
 	FuzzyFindOptions,
 	FuzzyFindResult,
+	GrepFileError,
 	GrepMatch,
 	GrepOptions,
 	GrepResult,
 	GrepSummary,
+	ReplaceFileResult,
+	ReplaceOptions,
+	ReplaceResult,
 	SearchOptions,
 	SearchResult,
+	Submatch,
 };
 
 /**
@@ -38,6 +50,16 @@ export async function grep(options: GrepOptions, onMatch?: (match: GrepMatch) =>
 	return native.grep(options, cb);
 }
 
+/**
+ * Search and replace a regex pattern across files.
+ *
+ * Previews changes as unified diffs by default; set `apply` to rewrite the
+ * files atomically.
+ */
+export async function replace(options: ReplaceOptions): Promise<ReplaceResult> {
+	return native.replace(options);
+}
+
 /**
  * Search a single file's content for a pattern.
  * Lower-level API for when you already have file content.
@@ -48,6 +70,13 @@ export function searchContent(content: string | Uint8Array, options: SearchOptio
 	return native.search(content, options);
 }
 
+/**
+ * List the file types accepted by the `type` option of `grep()` and `replace()`.
+ */
+export function listFileTypes(): FileTypeDefinition[] {
+	return native.listFileTypes();
+}
+
 /**
  * Quick check if content contains a pattern match.
  *
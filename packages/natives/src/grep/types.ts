@@ -2,26 +2,50 @@
  * Types for grep/search operations.
  */
 
-import type { Cancellable, TsFunc } from "../bindings";
+import type { Cancellable, TsFunc, WalkOptions } from "../bindings";
 
 /** Options for searching files. */
-export interface GrepOptions extends Cancellable {
+export interface GrepOptions extends Cancellable, WalkOptions {
 	/** Regex pattern to search for */
-	pattern: string;
+	pattern?: string;
+	/** Additional patterns; a line matches if any pattern matches */
+	patterns?: string[];
 	/** Directory or file to search */
 	path: string;
 	/** Glob filter for filenames (e.g., "*.ts") */
 	glob?: string;
-	/** Filter by file type (e.g., "js", "py", "rust") */
-	type?: string;
+	/** Filter by file type (e.g., "js", "py", "rust"); pass several to combine them and `!name` to exclude one. See `listFileTypes()`. */
+	type?: string | string[];
+	/** Extra file type definitions as `name:glob` (e.g., "proto:*.proto") */
+	typeAdd?: string[];
 	/** Case-insensitive search */
 	ignoreCase?: boolean;
-	/** Enable multiline matching */
+	/** Enable multiline matching (patterns may span lines) */
 	multiline?: boolean;
+	/** Regex engine: "default" (linear-time, no lookaround), "fancy" (backtracking, supports lookaround and backreferences), or "auto" (default, falls back to fancy when needed) */
+	engine?: "default" | "fancy" | "auto";
+	/** Treat the pattern as a literal string instead of a regex */
+	fixedStrings?: boolean;
+	/** Only match whole words */
+	wordRegexp?: boolean;
+	/** Only match whole lines */
+	lineRegexp?: boolean;
+	/** Case-insensitive unless the pattern contains an uppercase character */
+	smartCase?: boolean;
+	/** Report lines that match no pattern; counts and filesWithMatches then refer to non-matching lines (like `grep -v`) */
+	invertMatch?: boolean;
+	/** Skip files in which any of these patterns matches (files without match, like `grep -L`), with the same pattern flags */
+	excludePatterns?: string[];
+	/** Decompress gzip/zstd/xz/bzip2 files and search tar and zip archive members (detected by content, not extension) */
+	searchCompressed?: boolean;
+	/** Text encoding: "auto" (default; BOM or UTF-16 detection, else UTF-8) or a label such as "utf-16le", "utf-16be", "latin1", "shift_jis". A byte-order mark always wins. */
+	encoding?: string;
 	/** Include hidden files (default: true) */
 	hidden?: boolean;
 	/** Enable shared filesystem scan cache (default: false). */
 	cache?: boolean;
+	/** Skip files that cannot match using a persistent trigram index, updated incrementally by mtime (default: false). Stored under `FS_CONTENT_INDEX_DIR` or the user cache dir; cleared by `invalidateFsScanCache`. */
+	index?: boolean;
 	/** Maximum number of matches to return */
 	maxCount?: number;
 	/** Skip first N matches */
@@ -36,6 +60,8 @@ export interface GrepOptions extends Cancellable {
 	maxColumns?: number;
 	/** Output mode */
 	mode?: "content" | "filesWithMatches" | "count";
+	/** File order: "path" (default), "mtime" (newest first), "matchCount" (most matches first), or "relevance" (match density, depth from the root, and recency). `offset`/`maxCount` follow this order. */
+	sort?: "path" | "mtime" | "matchCount" | "relevance";
 }
 
 /** A context line returned around a match. */
@@ -46,14 +72,38 @@ export interface ContextLine {
 	line: string;
 }
 
+/** A matched span within a reported line. */
+export interface Submatch {
+	/** The matched text. */
+	text: string;
+	/** Start offset in UTF-8 bytes, relative to the untruncated line. */
+	start: number;
+	/** End offset (exclusive) in UTF-8 bytes. */
+	end: number;
+	/** Start offset in UTF-16 code units (usable with `String.prototype.slice`). */
+	startUtf16: number;
+	/** End offset (exclusive) in UTF-16 code units. */
+	endUtf16: number;
+	/** Whether the span was cut (partially or fully) by `maxColumns`. */
+	truncated?: boolean;
+}
+
 /** A single grep match or per-file count entry. */
 export interface GrepMatch {
 	/** File path for the match (relative for directory searches). */
 	path: string;
+	/** Archive member containing the match (`searchCompressed` only). */
+	member?: string;
+	/** Encoding the file was decoded from (e.g. "utf-16le", "windows-1252"); omitted for UTF-8. */
+	encoding?: string;
 	/** 1-indexed line number (0 for count-only entries). */
 	lineNumber: number;
 	/** Matched line content (empty for count-only entries). */
 	line: string;
+	/** Matched spans within the line (content mode only). */
+	submatches?: Submatch[];
+	/** Index of the first matching pattern, counting `pattern` (if set) before `patterns`; only set with several patterns */
+	patternIndex?: number;
 	/** Context lines before the match. */
 	contextBefore?: ContextLine[];
 	/** Context lines after the match. */
@@ -80,16 +130,110 @@ export interface GrepSummary {
 export interface GrepResult extends GrepSummary {
 	/** Matches or per-file counts, depending on mode. */
 	matches: GrepMatch[];
+	/** Files (or archive members) that could not be read, in path order. */
+	errors?: GrepFileError[];
+}
+
+/** A file or archive member that could not be searched. */
+export interface GrepFileError {
+	/** File path (relative for directory searches). */
+	path: string;
+	/** Archive member that failed (`searchCompressed` only). */
+	member?: string;
+	/** Error message. */
+	message: string;
+}
+
+/** Options for search-and-replace across files. */
+export interface ReplaceOptions extends Cancellable {
+	/** Regex pattern to search for */
+	pattern: string;
+	/** Replacement text; supports `$1`, `${name}` and `$$` (literal `$`) */
+	replacement: string;
+	/** Directory or file to search */
+	path: string;
+	/** Glob filter for filenames (e.g., "*.ts") */
+	glob?: string;
+	/** Filter by file type (e.g., "js", "py", "rust"); pass several to combine them and `!name` to exclude one. See `listFileTypes()`. */
+	type?: string | string[];
+	/** Extra file type definitions as `name:glob` (e.g., "proto:*.proto") */
+	typeAdd?: string[];
+	/** Case-insensitive search */
+	ignoreCase?: boolean;
+	/** Enable multiline matching (replacements may span lines) */
+	multiline?: boolean;
+	/** Regex engine: "default" (linear-time, no lookaround), "fancy" (backtracking, supports lookaround and backreferences), or "auto" (default, falls back to fancy when needed) */
+	engine?: "default" | "fancy" | "auto";
+	/** Treat the pattern as a literal string instead of a regex */
+	fixedStrings?: boolean;
+	/** Only match whole words */
+	wordRegexp?: boolean;
+	/** Only match whole lines */
+	lineRegexp?: boolean;
+	/** Case-insensitive unless the pattern contains an uppercase character */
+	smartCase?: boolean;
+	/** Include hidden files (default: true) */
+	hidden?: boolean;
+	/** Enable shared filesystem scan cache (default: false). */
+	cache?: boolean;
+	/** Write changes to disk (default: false, preview only) */
+	apply?: boolean;
+}
+
+/** Per-file outcome of a replace operation. */
+export interface ReplaceFileResult {
+	/** File path (relative for directory searches). */
+	path: string;
+	/** Number of replaced matches in this file. */
+	replacements: number;
+	/** Unified diff of the change (preview mode only). */
+	diff?: string;
+}
+
+/** Result of a replace operation. */
+export interface ReplaceResult {
+	/** Files with at least one replacement, sorted by path. With `apply`, only the files that were rewritten. */
+	files: ReplaceFileResult[];
+	/** Total replacements across all files. */
+	totalReplacements: number;
+	/** Number of files searched. */
+	filesSearched: number;
+	/** Whether changes were written to disk. */
+	applied: boolean;
+	/** Files that could not be rewritten, including those modified since they were read (`apply` only). */
+	errors?: GrepFileError[];
+}
+
+/** A file type known to the `type` filter. */
+export interface FileTypeDefinition {
+	/** Type name accepted by the `type` option. */
+	name: string;
+	/** Globs matched against file names. */
+	globs: string[];
 }
 
 /** Options for searching in-memory content. */
 export interface SearchOptions {
 	/** Regex pattern to search for */
-	pattern: string;
+	pattern?: string;
+	/** Additional patterns; a line matches if any pattern matches */
+	patterns?: string[];
 	/** Case-insensitive search */
 	ignoreCase?: boolean;
-	/** Enable multiline matching */
+	/** Enable multiline matching (patterns may span lines) */
 	multiline?: boolean;
+	/** Regex engine: "default" (linear-time, no lookaround), "fancy" (backtracking, supports lookaround and backreferences), or "auto" (default, falls back to fancy when needed) */
+	engine?: "default" | "fancy" | "auto";
+	/** Treat the pattern as a literal string instead of a regex */
+	fixedStrings?: boolean;
+	/** Only match whole words */
+	wordRegexp?: boolean;
+	/** Only match whole lines */
+	lineRegexp?: boolean;
+	/** Case-insensitive unless the pattern contains an uppercase character */
+	smartCase?: boolean;
+	/** Report lines that match no pattern; counts and filesWithMatches then refer to non-matching lines (like `grep -v`) */
+	invertMatch?: boolean;
 	/** Maximum number of matches to return */
 	maxCount?: number;
 	/** Skip first N matches */
@@ -112,6 +256,10 @@ export interface SearchMatch {
 	lineNumber: number;
 	/** Matched line content. */
 	line: string;
+	/** Matched spans within the line. */
+	submatches?: Submatch[];
+	/** Index of the first matching pattern, counting `pattern` (if set) before `patterns`; only set with several patterns */
+	patternIndex?: number;
 	/** Context lines before the match. */
 	contextBefore?: ContextLine[];
 	/** Context lines after the match. */
@@ -133,7 +281,7 @@ export interface SearchResult {
 }
 
 /** Options for fuzzy file path search. */
-export interface FuzzyFindOptions extends Cancellable {
+export interface FuzzyFindOptions extends Cancellable, WalkOptions {
 	/** Fuzzy query to match against file paths (case-insensitive). */
 	query: string;
 	/** Directory to search. */
@@ -146,6 +294,8 @@ export interface FuzzyFindOptions extends Cancellable {
 	cache?: boolean;
 	/** Maximum number of matches to return (default: 100). */
 	maxResults?: number;
+	/** Boost recently and frequently used files, as recorded by `recordFileAccess()` (default: false). */
+	frecency?: boolean;
 }
 
 /** A single match in fuzzy find results. */
@@ -156,6 +306,8 @@ export interface FuzzyFindMatch {
 	isDirectory: boolean;
 	/** Match quality score (higher is better). */
 	score: number;
+	/** Offsets of the matched characters in `path`, in UTF-16 code units (ascending; empty for an empty query). */
+	positions: number[];
 }
 
 /** Result of fuzzy file path search. */
@@ -172,8 +324,12 @@ declare module "../bindings" {
 		fuzzyFind(options: FuzzyFindOptions): Promise<FuzzyFindResult>;
 		/** Search files for a regex pattern. */
 		grep(options: GrepOptions, onMatch?: TsFunc<GrepMatch>): Promise<GrepResult>;
+		/** Search and replace a regex pattern across files. */
+		replace(options: ReplaceOptions): Promise<ReplaceResult>;
 		/** Search in-memory content for a regex pattern. */
 		search(content: string | Uint8Array, options: SearchOptions): SearchResult;
+		/** List the file types accepted by the `type` filter. */
+		listFileTypes(): FileTypeDefinition[];
 		/** Quick check if content matches a pattern. */
 		hasMatch(
 			content: string | Uint8Array,
@@ -14,9 +14,11 @@ export { type ClipboardImage, copyToClipboard, readImageFromClipboard } from "./
 
 export {
 	type ContextLine,
+	type FileTypeDefinition,
 	type FuzzyFindMatch,
 	type FuzzyFindOptions,
 	type FuzzyFindResult,
+	type GrepFileError,
 	fuzzyFind,
 	type GrepMatch,
 	type GrepOptions,
@@ -24,22 +26,54 @@ export {
 	type GrepSummary,
 	grep,
 	hasMatch,
+	listFileTypes,
+	type ReplaceFileResult,
+	type ReplaceOptions,
+	type ReplaceResult,
+	replace,
 	searchContent,
+	type Submatch,
 } from "./grep";
 
+// =============================================================================
+// Frecency (recently used files)
+// =============================================================================
+
+export {
+	type FileAccessKind,
+	type ListRecentFilesOptions,
+	listRecentFiles,
+	type RecentFile,
+	type RecordFileAccessOptions,
+	recordFileAccess,
+} from "./frecency";
+
 // =============================================================================
 // Glob (file discovery)
 // =============================================================================
 
 export {
 	FileType,
+	type FsChange,
+	type FsChangeEvent,
+	type FsChangeKind,
+	type FsWatcher,
+	type FsWatchOptions,
+	type GitFileStatus,
 	type GlobMatch,
 	type GlobOptions,
 	type GlobResult,
 	glob,
 	invalidateFsScanCache,
+	watchFs,
 } from "./glob";
 
+// =============================================================================
+// Directory tree
+// =============================================================================
+
+export { type TreeOptions, type TreeResult, tree } from "./tree";
+
 // =============================================================================
 // Image processing (photon-compatible API)
 // =============================================================================
@@ -104,20 +138,37 @@ export { getSystemInfo, type SystemInfo } from "./system-info";
 // =============================================================================
 
 export {
+	analyzeCommand,
+	type CommandAnalysis,
+	type CommandRedirect,
+	type CommandRedirectKind,
 	executeShell,
 	Shell,
+	type ShellChunk,
+	type ShellEnvChanges,
 	type ShellExecuteOptions,
 	type ShellExecuteResult,
 	type ShellOptions,
 	type ShellRunOptions,
 	type ShellRunResult,
+	type ShellStream,
 } from "./shell";
 
 // =============================================================================
 // PTY execution
 // =============================================================================
 
-export { type PtyRunResult, PtySession, type PtyStartOptions } from "./pty";
+export {
+	type PtyExpectOptions,
+	type PtyExpectResult,
+	type PtyExpectTarget,
+	type PtyRunResult,
+	PtySession,
+	type PtySnapshot,
+	type PtySnapshotFormat,
+	type PtySnapshotOptions,
+	type PtyStartOptions,
+} from "./pty";
 // =============================================================================
 // Process management
 // =============================================================================
@@ -17,6 +17,7 @@ import { embeddedAddon } from "./embedded-addon";
 
 // Import types to trigger declaration merging
 import "./clipboard/types";
+import "./frecency/types";
 import "./glob/types";
 import "./grep/types";
 import "./highlight/types";
@@ -28,6 +29,7 @@ import "./pty/types";
 import "./shell/types";
 import "./system-info/types";
 import "./text/types";
+import "./tree/types";
 import "./work/types";
 
 export type { NativeBindings, TsFunc } from "./bindings";
@@ -162,6 +164,8 @@ function validateNative(bindings: NativeBindings, source: string): void {
 	checkFn("readImageFromClipboard");
 	checkFn("glob");
 	checkFn("fuzzyFind");
+	checkFn("recordFileAccess");
+	checkFn("listRecentFiles");
 	checkFn("grep");
 	checkFn("search");
 	checkFn("hasMatch");
@@ -177,6 +181,7 @@ function validateNative(bindings: NativeBindings, source: string): void {
 	checkFn("extractSegments");
 	checkFn("matchesKittySequence");
 	checkFn("executeShell");
+	checkFn("analyzeCommand");
 	checkFn("PtySession");
 	checkFn("Shell");
 	checkFn("parseKey");
@@ -189,6 +194,8 @@ function validateNative(bindings: NativeBindings, source: string): void {
 	checkFn("getSystemInfo");
 	checkFn("getWorkProfile");
 	checkFn("invalidateFsScanCache");
+	checkFn("watchFs");
+	checkFn("tree");
 
 	if (missing.length) {
 		throw new Error(
@@ -4,7 +4,17 @@
 
 import { native } from "../native";
 
-export type { PtyRunResult, PtySessionConstructor, PtyStartOptions } from "./types";
+export type {
+	PtyExpectOptions,
+	PtyExpectResult,
+	PtyExpectTarget,
+	PtyRunResult,
+	PtySessionConstructor,
+	PtySnapshot,
+	PtySnapshotFormat,
+	PtySnapshotOptions,
+	PtyStartOptions,
+} from "./types";
 
 export const { PtySession } = native;
 export type PtySession = import("./types").PtySession;
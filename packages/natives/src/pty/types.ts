@@ -18,6 +18,78 @@ export interface PtyStartOptions extends Cancellable {
 	cols?: number;
 	/** PTY row count. */
 	rows?: number;
+	/** Lines of scrollback kept by the screen model (default: 1000). */
+	scrollback?: number;
+}
+
+/** Text format of a PTY screen snapshot. */
+export type PtySnapshotFormat = "plain" | "ansi";
+
+/**
+ * Options for `PtySession.snapshot`.
+ */
+export interface PtySnapshotOptions {
+	/** Text format: plain text (default) or with ANSI color and style codes. */
+	format?: PtySnapshotFormat;
+	/** Lines of scrollback to include above the screen (default: 0). */
+	scrollback?: number;
+}
+
+/**
+ * Rendered terminal screen of a PTY session.
+ */
+export interface PtySnapshot {
+	/** Screen rows (after any scrollback lines), joined with newlines. */
+	text: string;
+	/** Screen row count. */
+	rows: number;
+	/** Screen column count. */
+	cols: number;
+	/** Zero-based cursor row on the screen. */
+	cursorRow: number;
+	/** Zero-based cursor column on the screen. */
+	cursorCol: number;
+	/** Whether the program shows the cursor. */
+	cursorVisible: boolean;
+	/** Whether the alternate screen (used by full-screen programs such as vim or htop) is active. */
+	alternateScreen: boolean;
+	/** Zero-based screen rows that changed since the previous snapshot. */
+	dirtyRows: number[];
+	/** Number of scrollback lines at the start of `text`. */
+	scrollbackLines: number;
+}
+
+/** What `PtySession.expect` matches against. */
+export type PtyExpectTarget = "output" | "screen";
+
+/**
+ * Options for `PtySession.expect`.
+ */
+export interface PtyExpectOptions {
+	/** Raw output not consumed by earlier matches (default), or the rendered screen text. */
+	target?: PtyExpectTarget;
+	/** Abort signal for cancelling the wait. */
+	signal?: AbortSignal;
+}
+
+/**
+ * Result of `PtySession.expect`.
+ */
+export interface PtyExpectResult {
+	/** Whether the pattern matched. */
+	matched: boolean;
+	/** Text before the match; without a match, the unconsumed output (or the screen text). */
+	before: string;
+	/** Matched text. */
+	match?: string;
+	/** Capture groups of the match, `null` for groups that did not participate. */
+	groups: (string | null)[];
+	/** Whether the command exited without a match. */
+	eof: boolean;
+	/** Whether the wait was cancelled by abort signal. */
+	cancelled: boolean;
+	/** Whether the wait timed out. */
+	timedOut: boolean;
 }
 
 /**
@@ -38,10 +110,21 @@ export interface PtySession {
 	start(options: PtyStartOptions, onChunk?: TsFunc<string>): Promise<PtyRunResult>;
 	/** Write raw input bytes to PTY stdin. */
 	write(data: string): void;
+	/** Write a line of input, terminated by carriage return like the Enter key. */
+	sendLine(data: string): void;
+	/** Send a control character, e.g. `"c"` (or `"^C"`) for Ctrl+C. */
+	sendControl(key: string): void;
 	/** Resize active PTY. */
 	resize(cols: number, rows: number): void;
 	/** Force-kill active command. */
 	kill(): void;
+	/** Render the terminal screen of the running (or last) command. */
+	snapshot(options?: PtySnapshotOptions): PtySnapshot;
+	/**
+	 * Wait until the output (or rendered screen) matches a regex. Output matches consume the output up to the end of
+	 * the match. Resolves with `matched: false` when the command exits, the timeout elapses, or the signal aborts first.
+	 */
+	expect(pattern: string, timeoutMs?: number, options?: PtyExpectOptions): Promise<PtyExpectResult>;
 }
 
 /** Native PTY session constructor. */
@@ -3,9 +3,21 @@
  */
 
 import { native } from "../native";
-import type { ShellExecuteOptions, ShellExecuteResult } from "./types";
+import type { CommandAnalysis, ShellChunk, ShellExecuteOptions, ShellExecuteResult } from "./types";
 
-export type { ShellExecuteOptions, ShellExecuteResult, ShellOptions, ShellRunOptions, ShellRunResult } from "./types";
+export type {
+	CommandAnalysis,
+	CommandRedirect,
+	CommandRedirectKind,
+	ShellChunk,
+	ShellEnvChanges,
+	ShellExecuteOptions,
+	ShellExecuteResult,
+	ShellOptions,
+	ShellRunOptions,
+	ShellRunResult,
+	ShellStream,
+} from "./types";
 
 export const { Shell } = native;
 export type Shell = import("./types").Shell;
@@ -14,13 +26,37 @@ export type Shell = import("./types").Shell;
  * Execute a shell command using brush-core.
  *
  * @param options - Execution options including command, cwd, env, timeout
- * @param onChunk - Optional callback for streaming output chunks
+ * @param onChunk - Optional callback for streaming output chunks, tagged by stream with `separateStreams`
  * @returns Promise resolving to execution result with exit code and status
  */
-export async function executeShell(
+export function executeShell(
+	options: ShellExecuteOptions & { separateStreams: true },
+	onChunk?: (chunk: ShellChunk) => void,
+): Promise<ShellExecuteResult>;
+export function executeShell(
 	options: ShellExecuteOptions,
 	onChunk?: (chunk: string) => void,
+): Promise<ShellExecuteResult>;
+export async function executeShell(
+	options: ShellExecuteOptions,
+	onChunk?: ((chunk: string) => void) | ((chunk: ShellChunk) => void),
 ): Promise<ShellExecuteResult> {
-	const wrappedCallback = onChunk ? (err: Error | null, chunk: string) => !err && onChunk(chunk) : undefined;
+	const callback = onChunk as ((chunk: string | ShellChunk) => void) | undefined;
+	const wrappedCallback = callback
+		? (err: Error | null, chunk: string | ShellChunk) => !err && callback(chunk)
+		: undefined;
 	return native.executeShell(options, wrappedCallback);
 }
+
+/**
+ * Statically analyze a shell command without running it.
+ *
+ * Walks the parsed command, following wrappers such as `sudo` and `xargs`, `sh -c`, `eval`, and command substitutions,
+ * to report the programs invoked, file redirections, expansions only known at run time, and risk flags.
+ *
+ * @param command - Command to analyze
+ * @returns Promise resolving to the analysis; rejects if the command does not parse
+ */
+export async function analyzeCommand(command: string): Promise<CommandAnalysis> {
+	return native.analyzeCommand(command);
+}
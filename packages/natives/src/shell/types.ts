@@ -24,6 +24,30 @@ export interface ShellRunOptions extends Cancellable {
 	cwd?: string;
 	/** Environment variables to apply for this command. */
 	env?: Record<string, string>;
+	/** Read stdout and stderr from separate pipes and pass tagged `ShellChunk`s to the callback. */
+	separateStreams?: boolean;
+	/** Data to feed to the command's stdin (empty by default). */
+	stdin?: string | Uint8Array;
+	/**
+	 * Keep stdin open after `stdin` so `writeStdin()` can stream more input until `closeStdin()` or the command
+	 * exits.
+	 */
+	keepStdinOpen?: boolean;
+}
+
+/** Output stream of a `ShellChunk`. */
+export type ShellStream = "stdout" | "stderr";
+
+/**
+ * Output chunk of a command run with `separateStreams`.
+ */
+export interface ShellChunk {
+	/** Stream the chunk was read from. */
+	stream: ShellStream;
+	/** Sequence number across both streams, in the order chunks were read; sort by it to interleave them. */
+	seq: number;
+	/** Chunk text. */
+	text: string;
 }
 
 /**
@@ -36,6 +60,35 @@ export interface ShellRunResult {
 	cancelled: boolean;
 	/** Whether the command timed out. */
 	timedOut: boolean;
+	/** Bytes written to stdout (with `separateStreams`). */
+	stdoutBytes?: number;
+	/** Bytes written to stderr (with `separateStreams`). */
+	stderrBytes?: number;
+	/**
+	 * Whether the session was discarded after this command, because the command exited the shell or the interpreter
+	 * did not unwind after cancellation. The next command starts a fresh session.
+	 */
+	sessionReset: boolean;
+	/** Working directory of the session after the command, unless the session was discarded. */
+	cwd?: string;
+	/** Exported variables the command added, changed or removed, when it ran to completion. */
+	envChanges?: ShellEnvChanges;
+	/** Name of the signal that terminated the command (e.g. `"SIGKILL"`); `exitCode` is then 128 + its number. */
+	signal?: string;
+	/** Whether the command dumped core when terminated by `signal`. */
+	coreDumped?: boolean;
+}
+
+/**
+ * Changes to a session's exported variables made by a command.
+ */
+export interface ShellEnvChanges {
+	/** Newly exported variables and their values. */
+	added: Record<string, string>;
+	/** Exported variables with a new value. */
+	changed: Record<string, string>;
+	/** Variables no longer exported. */
+	removed: string[];
 }
 
 /**
@@ -52,21 +105,69 @@ export interface ShellExecuteOptions extends Cancellable {
 	sessionEnv?: Record<string, string>;
 	/** Optional snapshot path to source for bash sessions. */
 	snapshotPath?: string;
+	/** Read stdout and stderr from separate pipes and pass tagged `ShellChunk`s to the callback. */
+	separateStreams?: boolean;
+	/** Data to feed to the command's stdin (empty by default). */
+	stdin?: string | Uint8Array;
 }
 
 /**
 /** Internal result from the native brush-core binding. */
-export interface ShellExecuteResult extends ShellRunResult {}
+export interface ShellExecuteResult extends Omit<ShellRunResult, "sessionReset" | "cwd" | "envChanges"> {}
+
+/** Kind of a file redirection: `<`, `>`/`>|`, `>>`, or `<>`. */
+export type CommandRedirectKind = "read" | "write" | "append" | "readWrite";
+
+/** File redirection found by `analyzeCommand()`. */
+export interface CommandRedirect {
+	/** Target path, as written if it contains expansions. */
+	target: string;
+	kind: CommandRedirectKind;
+	/** Redirected file descriptor, if given explicitly. */
+	fd?: number;
+}
+
+/** Static analysis of a shell command, from `analyzeCommand()`. */
+export interface CommandAnalysis {
+	/** Programs the command invokes, in order of first use, as written. */
+	programs: string[];
+	/** File redirections, in order. */
+	redirects: CommandRedirect[];
+	/** Parameter expansions and substitutions whose value is only known at run time, as written. */
+	unresolved: string[];
+	/** Every program is known to only read, with no writing redirects. */
+	readOnly: boolean;
+	/** Writes files, through redirects or programs such as `cp` or `tee`. */
+	writesFiles: boolean;
+	/** Deletes or overwrites data (`rm`, `dd`, `find -delete`, `git clean`). */
+	destructive: boolean;
+	/** Uses the network (`curl`, `ssh`, `git push`, package installs). */
+	network: boolean;
+	/** Runs something as another user (`sudo`, `doas`, `su`). */
+	privilegeEscalation: boolean;
+	/** Executes downloaded code (`curl … | sh`, `eval "$(wget …)"`). */
+	pipesToShell: boolean;
+}
 
 /** Native Shell class instance. */
 export interface Shell {
 	/**
-	 * Run a command in the shell.
+	 * Run a command in the shell. Cancelling a command kills its process group and the jobs it started, keeping the
+	 * session's cwd, variables, functions, and aliases.
 	 * @param options Command execution options.
-	 * @param onChunk Optional callback for streamed output.
+	 * @param onChunk Optional callback for streamed output, tagged by stream with `separateStreams`.
 	 * @returns Promise resolving to the command result.
 	 */
+	run(options: ShellRunOptions & { separateStreams: true }, onChunk?: TsFunc<ShellChunk>): Promise<ShellRunResult>;
 	run(options: ShellRunOptions, onChunk?: TsFunc<string>): Promise<ShellRunResult>;
+	/**
+	 * Write data to the stdin of the running command started with `keepStdinOpen`. Until it starts, writes go to the
+	 * earliest such command still queued and are buffered, so writing right after `run()` returns is safe.
+	 * @throws If no command has open stdin.
+	 */
+	writeStdin(data: string | Uint8Array): void;
+	/** Close the stdin that `writeStdin()` writes to, signalling end of input. Does nothing when none is open. */
+	closeStdin(): void;
 	/**
 	 * Abort all running commands in this session.
 	 * @param reason Optional reason for the abort.
@@ -89,10 +190,20 @@ declare module "../bindings" {
 		/**
 		 * Execute a shell command with explicit session metadata.
 		 * @param options Execution options including session identifiers.
-		 * @param onChunk Optional callback for streamed output.
+		 * @param onChunk Optional callback for streamed output, tagged by stream with `separateStreams`.
 		 * @returns Promise resolving to the command result.
 		 */
-		executeShell(options: ShellExecuteOptions, onChunk?: TsFunc<string>): Promise<ShellExecuteResult>;
+		executeShell(
+			options: ShellExecuteOptions,
+			onChunk?: TsFunc<string> | TsFunc<ShellChunk>,
+		): Promise<ShellExecuteResult>;
+
+		/**
+		 * Statically analyze a shell command without running it.
+		 * @param command Command to parse.
+		 * @returns Promise resolving to the analysis; rejects if the command does not parse.
+		 */
+		analyzeCommand(command: string): Promise<CommandAnalysis>;
 
 		/** Shell class constructor for creating sessions. */
 		Shell: ShellConstructor;
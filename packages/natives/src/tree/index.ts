@@ -0,0 +1,9 @@
+/**
+ * Directory tree outlines powered by native bindings.
+ */
+
+import { native } from "../native";
+
+export type { TreeOptions, TreeResult } from "./types";
+
+export const { tree } = native;
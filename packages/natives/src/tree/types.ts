@@ -0,0 +1,51 @@
+/**
+ * Types for native directory tree rendering.
+ */
+
+import type { Cancellable } from "../bindings";
+
+/** Options for rendering a directory tree. */
+export interface TreeOptions extends Cancellable {
+	/** Directory to render. */
+	path: string;
+	/** Depth at which directories are collapsed; 1 lists only the root's children (default: unlimited). */
+	maxDepth?: number;
+	/** Maximum entries listed per directory; the rest are summarized as `… N more files` (default: 50). */
+	maxChildren?: number;
+	/** Approximate token budget, at about 4 characters per token. Directories are collapsed until the outline fits. */
+	maxTokens?: number;
+	/** Annotate every directory with its file count and size, not only collapsed ones (default: false). */
+	summaries?: boolean;
+	/** Use ASCII connectors instead of box-drawing characters (default: false). */
+	ascii?: boolean;
+	/** Include hidden files (default: false). */
+	hidden?: boolean;
+	/** Respect .gitignore files (default: true). */
+	gitignore?: boolean;
+	/** Include node_modules directories (default: false). */
+	includeNodeModules?: boolean;
+}
+
+/** Rendered directory tree. */
+export interface TreeResult {
+	/** Outline text, one entry per line. */
+	text: string;
+	/** Files (including symlinks) below the root. */
+	totalFiles: number;
+	/** Directories below the root. */
+	totalDirs: number;
+	/** Combined size of all files in bytes. */
+	totalSize: number;
+	/** Estimated token count of `text`. */
+	tokens: number;
+	/** Whether the depth limit, child cap, or token budget hid any entries. */
+	truncated: boolean;
+}
+
+declare module "../bindings" {
+	/** Native bindings for directory tree rendering. */
+	interface NativeBindings {
+		/** Render the directory tree below a path from the shared scan cache. */
+		tree(options: TreeOptions): Promise<TreeResult>;
+	}
+}
@@ -2,15 +2,23 @@ import { beforeAll, describe, expect, it } from "bun:test";
 import * as fs from "node:fs/promises";
 import * as os from "node:os";
 import * as path from "node:path";
+import { gzipSync } from "node:zlib";
 import {
 	FileType,
+	type FsChangeEvent,
 	fuzzyFind,
 	type GlobMatch,
 	glob,
 	grep,
 	htmlToMarkdown,
 	invalidateFsScanCache,
+	listFileTypes,
+	listRecentFiles,
+	recordFileAccess,
+	replace,
 	sanitizeText,
+	tree,
+	watchFs,
 } from "../src/index";
 
 let testDir: string;
@@ -92,15 +100,220 @@ describe("pi-natives", () => {
 			expect(result.filesWithMatches).toBeGreaterThan(0);
 		});
 
-		it("should treat unknown grep type filter as a strict extension filter", async () => {
+		it("should report submatch spans in content mode", async () => {
+			const result = await grep({
+				pattern: "hello",
+				path: path.join(testDir, "file1.ts"),
+			});
+
+			const [match] = result.matches;
+			expect(match.submatches?.length).toBe(1);
+			const span = match.submatches![0];
+			expect(span.text).toBe("hello");
+			expect(match.line.slice(span.startUtf16, span.endUtf16)).toBe("hello");
+		});
+
+		it("should support lookaround with the auto engine", async () => {
+			const result = await grep({
+				pattern: "return (?!\"hello\")",
+				path: testDir,
+			});
+
+			expect(result.totalMatches).toBe(1);
+			expect(result.matches[0].line).toContain("world");
+		});
+
+		it("should reject lookaround with the default engine", async () => {
+			await expect(grep({ pattern: "return (?!x)", path: testDir, engine: "default" })).rejects.toThrow();
+		});
+
+		it("should match regex metacharacters literally with fixedStrings", async () => {
+			const result = await grep({
+				pattern: "hello()",
+				path: testDir,
+				fixedStrings: true,
+			});
+
+			expect(result.totalMatches).toBe(1);
+			expect(result.matches[0].line).toContain("function hello()");
+		});
+
+		it("should report which of several patterns matched", async () => {
+			const result = await grep({
+				patterns: ["TODO", "FIXME"],
+				path: testDir,
+			});
+
+			expect(result.totalMatches).toBe(2);
+			const byPath = Object.fromEntries(result.matches.map(m => [m.path, m.patternIndex]));
+			expect(byPath["file1.ts"]).toBe(0);
+			expect(byPath["file2.ts"]).toBe(1);
+		});
+
+		it("should count non-matching lines with invertMatch", async () => {
 			const result = await grep({
 				pattern: "return",
+				path: path.join(testDir, "file1.ts"),
+				invertMatch: true,
+				mode: "count",
+			});
+
+			expect(result.totalMatches).toBe(3);
+		});
+
+		it("should skip files matching excludePatterns", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-exclude-"));
+			try {
+				await fs.writeFile(path.join(dir, "todo.ts"), "// TODO: implement\n");
+				await fs.writeFile(path.join(dir, "both.ts"), "// TODO: implement\n// FIXME: broken\n");
+
+				const result = await grep({
+					pattern: "TODO",
+					excludePatterns: ["FIXME"],
+					path: dir,
+					mode: "filesWithMatches",
+				});
+
+				expect(result.matches.map(m => m.path)).toEqual(["todo.ts"]);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should combine and negate file types", async () => {
+			const result = await grep({
+				pattern: "e",
 				path: testDir,
-				type: "definitelynotatype",
+				type: ["ts", "markdown", "!md"],
+				mode: "filesWithMatches",
 			});
 
-			expect(result.totalMatches).toBe(0);
-			expect(result.filesWithMatches).toBe(0);
+			expect(result.filesWithMatches).toBe(3);
+			expect(listFileTypes().some(def => def.name === "protobuf" && def.globs.includes("*.proto"))).toBe(true);
+		});
+
+		it("should prune files with the content index and pick up edits", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-index-"));
+			try {
+				for (let i = 0; i < 20; i++) {
+					await fs.writeFile(path.join(dir, `module${i}.ts`), `export const value${i} = ${i};\n`);
+				}
+				await fs.writeFile(path.join(dir, "target.ts"), "export const needleValue = 1;\n");
+
+				const first = await grep({ pattern: "needleValue", path: dir, index: true });
+				expect(first.totalMatches).toBe(1);
+				expect(first.filesSearched).toBe(1);
+
+				await fs.writeFile(path.join(dir, "module3.ts"), "export const needleValue3 = 3;\n");
+				const second = await grep({ pattern: "needleValue", path: dir, index: true });
+				expect(second.matches.map(m => m.path).sort()).toEqual(["module3.ts", "target.ts"]);
+			} finally {
+				invalidateFsScanCache(dir);
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should paginate in the requested sort order", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-sort-"));
+			try {
+				await fs.writeFile(path.join(dir, "a.ts"), "hit\n");
+				await fs.writeFile(path.join(dir, "b.ts"), "hit\nhit\nhit\n");
+				await fs.writeFile(path.join(dir, "c.ts"), "hit\nhit\n");
+
+				const byCount = await grep({ pattern: "hit", path: dir, sort: "matchCount", maxCount: 4 });
+				expect(byCount.matches.map(m => m.path)).toEqual(["b.ts", "b.ts", "b.ts", "c.ts"]);
+				expect(byCount.limitReached).toBe(true);
+
+				const next = await grep({ pattern: "hit", path: dir, sort: "matchCount", offset: 4 });
+				expect(next.matches.map(m => m.path)).toEqual(["c.ts", "a.ts"]);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should reject unknown grep type filters", async () => {
+			await expect(
+				grep({
+					pattern: "return",
+					path: testDir,
+					type: "definitelynotatype",
+				}),
+			).rejects.toThrow("Unknown file type 'definitelynotatype'");
+		});
+
+		it("should search gzip-compressed files with searchCompressed", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-compressed-"));
+			try {
+				await fs.writeFile(path.join(dir, "app.log.1"), gzipSync("ok\nerror: disk full\n"));
+
+				const plain = await grep({ pattern: "disk full", path: dir });
+				expect(plain.totalMatches).toBe(0);
+
+				const result = await grep({ pattern: "disk full", path: dir, searchCompressed: true });
+				expect(result.totalMatches).toBe(1);
+				expect(result.matches[0].path).toBe("app.log.1");
+				expect(result.matches[0].lineNumber).toBe(2);
+				expect(result.matches[0].member).toBeUndefined();
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should transcode UTF-16 and explicitly encoded files", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-encoding-"));
+			try {
+				await fs.writeFile(
+					path.join(dir, "app.rc"),
+					Buffer.from('\ufeffSTRINGTABLE\r\nIDS_TITLE "Café"\r\n', "utf16le"),
+				);
+				await fs.writeFile(path.join(dir, "legacy.txt"), Buffer.from("Café crème\n", "latin1"));
+
+				const utf16 = await grep({ pattern: "Café", path: path.join(dir, "app.rc") });
+				expect(utf16.totalMatches).toBe(1);
+				expect(utf16.matches[0].lineNumber).toBe(2);
+				expect(utf16.matches[0].encoding).toBe("utf-16le");
+
+				const latin1 = await grep({ pattern: "crème", path: path.join(dir, "legacy.txt"), encoding: "latin1" });
+				expect(latin1.totalMatches).toBe(1);
+				expect(latin1.matches[0].encoding).toBe("windows-1252");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+	});
+
+	describe("replace", () => {
+		it("should preview and apply capture-group replacements", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-replace-"));
+			try {
+				const file = path.join(dir, "rename.ts");
+				await fs.writeFile(file, "const oldName = 1;\nuse(oldName);\n");
+
+				const preview = await replace({ pattern: "old(\\w+)", replacement: "new$1", path: dir });
+				expect(preview.applied).toBe(false);
+				expect(preview.totalReplacements).toBe(2);
+				expect(preview.files[0].diff).toContain("+const newName = 1;");
+				expect(await fs.readFile(file, "utf8")).toContain("oldName");
+
+				const applied = await replace({ pattern: "old(\\w+)", replacement: "new$1", path: dir, apply: true });
+				expect(applied.files[0].diff).toBeUndefined();
+				expect(await fs.readFile(file, "utf8")).toBe("const newName = 1;\nuse(newName);\n");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should label single-file diffs with the file name", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-replace-"));
+			try {
+				const file = path.join(dir, "single.ts");
+				await fs.writeFile(file, "oldName\n");
+
+				const preview = await replace({ pattern: "old", replacement: "new", path: file });
+				expect(preview.files[0].diff).toContain("--- a/single.ts\n+++ b/single.ts");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
 		});
 	});
 
@@ -116,6 +329,44 @@ describe("pi-natives", () => {
 
 			expect(result.matches.some(match => match.path === "history-search.ts")).toBe(true);
 		});
+
+		it("should rank boundary-aligned matches first and report positions", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-fuzzy-"));
+			try {
+				await fs.mkdir(path.join(dir, "src", "utils"), { recursive: true });
+				await fs.mkdir(path.join(dir, "fixtures"));
+				await fs.writeFile(path.join(dir, "src", "utils", "fsCache.ts"), "");
+				await fs.writeFile(path.join(dir, "fixtures", "super-long-chain-cache.ts"), "");
+
+				const result = await fuzzyFind({ query: "fscache", path: dir });
+				const [best] = result.matches;
+				expect(best.path).toBe("src/utils/fsCache.ts");
+				expect(best.positions.map(i => best.path[i]).join("")).toBe("fsCache");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should boost recently used files with frecency", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-frecency-"));
+			try {
+				await fs.mkdir(path.join(dir, "src"));
+				await fs.writeFile(path.join(dir, "src", "config-writer.ts"), "");
+				await fs.writeFile(path.join(dir, "src", "config-loader.ts"), "");
+				recordFileAccess({ root: dir, path: "src/config-writer.ts", kind: "edit" });
+				recordFileAccess({ root: dir, path: path.join(dir, "src", "config-writer.ts") });
+
+				const recent = listRecentFiles({ root: dir });
+				expect(recent.map(file => [file.path, file.count])).toEqual([["src/config-writer.ts", 2]]);
+
+				const plain = await fuzzyFind({ query: "config", path: dir });
+				expect(plain.matches[0].path).toBe("src/config-loader.ts");
+				const boosted = await fuzzyFind({ query: "config", path: path.join(dir, "src"), frecency: true });
+				expect(boosted.matches[0].path).toBe("config-writer.ts");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
 	});
 
 	describe("find", () => {
@@ -151,6 +402,90 @@ describe("pi-natives", () => {
 			expect(result.matches.some(match => match.path === "newly-added.ts")).toBe(true);
 		});
 
+		it("should report changes and keep the cache current while watching", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-watch-"));
+			const events: FsChangeEvent[] = [];
+			const watcher = await watchFs({ path: dir }, event => events.push(event));
+			try {
+				await fs.mkdir(path.join(dir, "src"));
+				await fs.writeFile(path.join(dir, "src", "added.ts"), "");
+				const deadline = Date.now() + 5000;
+				while (!events.some(event => event.changes.some(change => change.path === "src/added.ts"))) {
+					if (Date.now() > deadline) throw new Error("no change event");
+					await Bun.sleep(20);
+				}
+
+				const added = events.flatMap(event => event.changes).find(change => change.path === "src/added.ts");
+				expect(added).toEqual({ path: "src/added.ts", kind: "add", fileType: FileType.File });
+				const result = await glob({ pattern: "**/*.ts", path: dir, cache: true });
+				expect(result.matches.map(match => match.path)).toEqual(["src/added.ts"]);
+			} finally {
+				watcher.close();
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should combine patterns, excludes, and size filters", async () => {
+			const result = await glob({
+				patterns: ["*.ts", "*.md"],
+				exclude: ["history-*"],
+				path: testDir,
+				minSize: 1,
+			});
+
+			const paths = result.matches.map(match => match.path);
+			expect(paths).toContain("file1.ts");
+			expect(paths).toContain("readme.md");
+			expect(paths.every(p => /\.(ts|md)$/.test(p) && !p.startsWith("history-"))).toBe(true);
+		});
+
+		it("should honor custom ignore files, overrides, and maxDepth", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-walk-"));
+			try {
+				await fs.mkdir(path.join(dir, "dist"));
+				await fs.mkdir(path.join(dir, "src", "deep"), { recursive: true });
+				await fs.writeFile(path.join(dir, ".ignore"), "dist/\n");
+				await fs.writeFile(path.join(dir, ".ompignore"), "*.snap\n");
+				await fs.writeFile(path.join(dir, "dist", "app.js"), "");
+				await fs.writeFile(path.join(dir, "src", "a.snap"), "");
+				await fs.writeFile(path.join(dir, "src", "deep", "b.ts"), "");
+
+				const paths = async (options: Partial<Parameters<typeof glob>[0]>) =>
+					(await glob({ pattern: "*", path: dir, fileType: FileType.File, ...options })).matches
+						.map(match => match.path)
+						.sort();
+				expect(await paths({})).toEqual(["src/deep/b.ts"]);
+				expect(await paths({ ignoreFiles: [] })).toEqual(["src/a.snap", "src/deep/b.ts"]);
+				expect(await paths({ maxDepth: 2 })).toEqual([]);
+				expect(await paths({ overrides: ["dist", "dist/**"] })).toEqual(["dist/app.js"]);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+				it("should report metadata only when requested", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-metadata-"));
+			try {
+				await fs.writeFile(path.join(dir, "run.sh"), "#!/bin/sh\n", { mode: 0o755 });
+				await fs.link(path.join(dir, "run.sh"), path.join(dir, "alias.sh"));
+				await fs.symlink("run.sh", path.join(dir, "link.sh"));
+
+				const plain = await glob({ pattern: "run.sh", path: dir });
+				expect(plain.matches[0].size).toBeUndefined();
+
+				const result = await glob({ pattern: "*.sh", path: dir, withMetadata: true });
+				const byPath = new Map(result.matches.map(match => [match.path, match]));
+				const run = byPath.get("run.sh")!;
+				expect(run.size).toBe(10);
+				expect(run.executable).toBe(true);
+				expect(run.gitStatus).toBeUndefined();
+				expect(byPath.get("alias.sh")!.ino).toBe(run.ino);
+				expect(byPath.get("link.sh")!.symlinkTarget).toBe("run.sh");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
 		it("should avoid scan work when maxResults is zero", async () => {
 			const result = await glob({
 				pattern: "**/*",
@@ -175,6 +510,31 @@ describe("pi-natives", () => {
 			expect(second.totalMatches).toBe(1);
 		});
 	});
+	describe("tree", () => {
+		it("should render an outline with collapsed directories and child caps", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-tree-"));
+			try {
+				await fs.mkdir(path.join(dir, "src", "deep"), { recursive: true });
+				await fs.writeFile(path.join(dir, "src", "deep", "a.ts"), "abc");
+				for (let i = 0; i < 5; i++) await fs.writeFile(path.join(dir, `f${i}.txt`), "");
+
+				const result = await tree({ path: dir, maxDepth: 1, maxChildren: 3, ascii: true });
+				const lines = result.text.trimEnd().split("\n");
+				expect(lines[0]).toBe(`${path.basename(dir)}/ (6 files, 3 B)`);
+				expect(lines.slice(1)).toEqual([
+					"|-- f0.txt",
+					"|-- f1.txt",
+					"|-- f2.txt",
+					"`-- ... 1 more dir and 2 more files",
+				]);
+				expect(result.totalDirs).toBe(2);
+				expect(result.truncated).toBe(true);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+	});
+
 	describe("htmlToMarkdown", () => {
 		it("should convert basic HTML to markdown", async () => {
 			const html = "<h1>Hello World</h1><p>This is a paragraph.</p>";